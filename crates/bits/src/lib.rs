@@ -218,6 +218,38 @@ impl Bits {
 
 		result
 	}
+
+	/// The first `len` bits packed into bytes, bit `i` is bit `i % 8` of byte
+	/// `i / 8`.
+	pub fn to_bytes(&self, len: usize) -> Vec<u8> {
+		let mut bytes: Vec<u8> = self
+			.bits
+			.iter()
+			.flat_map(|group| group.to_le_bytes())
+			.take(len.div_ceil(8))
+			.collect();
+		bytes.resize(len.div_ceil(8), 0);
+		if let Some(last) = bytes.last_mut()
+			&& !len.is_multiple_of(8)
+		{
+			*last &= (1 << (len % 8)) - 1;
+		}
+		bytes
+	}
+
+	/// Unpacks bytes that were packed by [Bits::to_bytes].
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		const BYTES_PER: usize = BITS_PER / 8;
+		let bits = bytes
+			.chunks(BYTES_PER)
+			.map(|chunk| {
+				let mut group = [0; BYTES_PER];
+				group[..chunk.len()].copy_from_slice(chunk);
+				BitHolder::from_le_bytes(group)
+			})
+			.collect();
+		Bits { bits }
+	}
 }
 
 #[derive(Debug)]
@@ -472,6 +504,17 @@ mod test {
 		assert_eq!(bits.first_one(), Some(123));
 	}
 
+	#[test]
+	fn bytes() {
+		let mut bits = create(&[1, 0, 1, 1, 0, 0, 0, 0, 1]);
+		bits.set(70, true);
+		assert_eq!(bits.to_bytes(9), vec![0b1101, 1]);
+		assert_eq!(bits.to_bytes(3), vec![0b101]);
+		assert_eq!(Bits::new().to_bytes(12), vec![0, 0]);
+		assert_eq!(Bits::from_bytes(&bits.to_bytes(71)), bits);
+		assert_eq!(Bits::from_bytes(&[]), Bits::new());
+	}
+
 	#[test]
 	fn test_ordering() {
 		let a = Bits { bits: vec![0] };
@@ -23,6 +23,42 @@ where
 	}
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rx<T> {
+	pub angle: T,
+	pub target: usize,
+}
+
+impl<T> RandomGate for Rx<T>
+where
+	StandardUniform: Distribution<T>,
+{
+	fn random<R: rand::prelude::Rng>(n_qubits: usize, rng: &mut R) -> Self {
+		Self {
+			angle: rng.random(),
+			target: rng.random_range(..n_qubits),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ry<T> {
+	pub angle: T,
+	pub target: usize,
+}
+
+impl<T> RandomGate for Ry<T>
+where
+	StandardUniform: Distribution<T>,
+{
+	fn random<R: rand::prelude::Rng>(n_qubits: usize, rng: &mut R) -> Self {
+		Self {
+			angle: rng.random(),
+			target: rng.random_range(..n_qubits),
+		}
+	}
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct X {
 	pub target: usize,
@@ -62,6 +98,47 @@ impl RandomGate for H {
 	}
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Z {
+	pub target: usize,
+}
+
+impl RandomGate for Z {
+	fn random<R: rand::prelude::Rng>(n_qubits: usize, rng: &mut R) -> Self {
+		Self {
+			target: rng.random_range(..n_qubits),
+		}
+	}
+}
+
+/// The phase gate $\text{diag}(1, i)$
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct S {
+	pub target: usize,
+}
+
+impl RandomGate for S {
+	fn random<R: rand::prelude::Rng>(n_qubits: usize, rng: &mut R) -> Self {
+		Self {
+			target: rng.random_range(..n_qubits),
+		}
+	}
+}
+
+/// The inverse of [S], $\text{diag}(1, -i)$
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sdg {
+	pub target: usize,
+}
+
+impl RandomGate for Sdg {
+	fn random<R: rand::prelude::Rng>(n_qubits: usize, rng: &mut R) -> Self {
+		Self {
+			target: rng.random_range(..n_qubits),
+		}
+	}
+}
+
 /// # Attention
 /// Currently there is nothing stopping you from having control == target.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,6 +10,10 @@ pub use parity_matrix::ParityMatrix;
 pub mod algorithm {
 	pub use super::gra_star_synth::GrayStar;
 	pub use super::patel_markov_hayes::PatelMarkovHayes;
-	pub use super::rowcol::RowCol;
-	pub use super::t_par::TPar;
+	pub use super::rowcol::{RowCol, TwoQubitEdge};
+	pub use super::t_par::{ParityVisitor, TPar};
+}
+
+pub mod gateset {
+	pub use super::t_par::gateset::{CNotRzXYH, QuarterPi};
 }
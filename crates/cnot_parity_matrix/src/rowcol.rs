@@ -5,7 +5,7 @@ use test_core::prelude::*;
 use crate::ParityMatrix;
 
 #[derive(Debug)]
-pub struct TwoQubitEdge([usize; 2]);
+pub struct TwoQubitEdge(pub [usize; 2]);
 
 impl Edge for TwoQubitEdge {
 	fn nodes(&self) -> Vec<usize> {
@@ -1,4 +1,4 @@
-mod gateset;
+pub(crate) mod gateset;
 mod parity;
 mod squirrel;
 mod state;
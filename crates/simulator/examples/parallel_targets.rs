@@ -0,0 +1,61 @@
+//! Compares [ParallelStatevector] with the generic [Statevector] for every
+//! target qubit, run with `--release`.
+
+use std::time::{Duration, Instant};
+
+use simulator::{Complex, ParallelStatevector, Simulatable, Statevector};
+
+const N_QUBITS: usize = 20;
+const REPETITIONS: usize = 20;
+
+/// $e^{i\theta X}$ on `target`.
+struct RotationX {
+	target: usize,
+	theta: f64,
+}
+
+impl Simulatable<f64> for RotationX {
+	fn matrix(&self) -> [Complex<f64>; 4] {
+		let cos = Complex {
+			re: self.theta.cos(),
+			im: 0.0,
+		};
+		let sin = Complex {
+			re: 0.0,
+			im: self.theta.sin(),
+		};
+		[cos, sin, sin, cos]
+	}
+
+	fn target(&self) -> usize {
+		self.target
+	}
+
+	fn controls(&self) -> Vec<usize> {
+		Vec::new()
+	}
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+	let start = Instant::now();
+	for _ in 0..REPETITIONS {
+		f();
+	}
+	start.elapsed()
+}
+
+fn main() {
+	let theta = 0.3;
+	for target in 0..N_QUBITS {
+		let gate = RotationX { target, theta };
+		let mut generic = Statevector::<f64>::new(N_QUBITS);
+		let mut parallel = ParallelStatevector::new(N_QUBITS);
+
+		let generic_time = time(|| generic.apply(&gate));
+		let apply_time = time(|| parallel.apply(&gate));
+		let pauli_time = time(|| parallel.apply_pauli(1 << target, 0, theta));
+		println!(
+			"X{target}:\tgeneric {generic_time:?}\tparallel {apply_time:?}\tapply_pauli {pauli_time:?}"
+		);
+	}
+}
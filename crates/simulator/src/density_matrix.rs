@@ -0,0 +1,195 @@
+use crate::{Complex, NoiseModel, ParallelStatevector, PauliChannel, Statevector};
+
+/// Larger registers do not fit into memory comfortably, $4^{12}$ amplitudes
+/// already take 256 MiB.
+pub const MAX_DENSITY_MATRIX_QUBITS: usize = 12;
+
+/// A density matrix for small registers, to simulate noise exactly.
+///
+/// The matrix is stored as a vector on twice the qubits, with
+/// $\rho_{rc}$ at index $r\cdot2^n+c$. Left multiplication acts on the upper
+/// half of the bits and right multiplication on the lower half, so the
+/// updates are the ones of [ParallelStatevector].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMatrix {
+	vector: ParallelStatevector,
+	n_qubits: usize,
+}
+
+impl DensityMatrix {
+	/// $|0\dots0\rangle\langle0\dots0|$
+	///
+	/// # Panics
+	/// Panics with more than [MAX_DENSITY_MATRIX_QUBITS] qubits.
+	pub fn new(n_qubits: usize) -> Self {
+		assert!(
+			n_qubits <= MAX_DENSITY_MATRIX_QUBITS,
+			"A density matrix on {n_qubits} qubits is too large"
+		);
+		Self {
+			vector: ParallelStatevector::new(2 * n_qubits),
+			n_qubits,
+		}
+	}
+
+	/// $|\psi\rangle\langle\psi|$
+	///
+	/// # Panics
+	/// Panics with more than [MAX_DENSITY_MATRIX_QUBITS] qubits.
+	pub fn from_statevector(state: &Statevector<f64>) -> Self {
+		let mut matrix = Self::new(state.n_qubits);
+		let n = state.n_qubits;
+		let (re, im) = matrix.vector.parts_mut();
+		for (r, row) in state.values.iter().enumerate() {
+			for (c, col) in state.values.iter().enumerate() {
+				re[(r << n) | c] = row.re * col.re + row.im * col.im;
+				im[(r << n) | c] = row.im * col.re - row.re * col.im;
+			}
+		}
+		matrix
+	}
+
+	/// Limits the amount of threads, see [ParallelStatevector::with_threads].
+	pub fn with_threads(mut self, threads: usize) -> Self {
+		self.vector = self.vector.with_threads(threads);
+		self
+	}
+
+	pub fn n_qubits(&self) -> usize {
+		self.n_qubits
+	}
+
+	/// $\rho_{rc}$
+	pub fn get(&self, row: usize, col: usize) -> Complex<f64> {
+		self.vector.get((row << self.n_qubits) | col)
+	}
+
+	pub fn trace(&self) -> f64 {
+		(0..1 << self.n_qubits).map(|i| self.get(i, i).re).sum()
+	}
+
+	/// # Apply Pauli
+	///
+	/// $\rho\mapsto U\rho U^\dagger$ with $U=e^{i\theta P}$, see
+	/// [ParallelStatevector::apply_pauli]. The rows of $\rho U^\dagger$ are
+	/// the rows of $\rho$ multiplied by $\overline{U}=e^{-i\theta\overline{P}}$,
+	/// where $\overline{P}=(-1)^{n_Y}P$.
+	pub fn apply_pauli(&mut self, x: usize, z: usize, theta: f64) {
+		let n = self.n_qubits;
+		self.vector.apply_pauli(x << n, z << n, theta);
+		let conjugate = if (x & z).count_ones().is_multiple_of(2) {
+			-theta
+		} else {
+			theta
+		};
+		self.vector.apply_pauli(x, z, conjugate);
+	}
+
+	/// Applies `channel` to `qubit`.
+	///
+	/// On the $2\times2$ blocks $\begin{pmatrix}a&b\\\\c&d\end{pmatrix}$ of
+	/// the qubit, $X\rho X$ gives $(d, c, b, a)$, $Y\rho Y$ gives
+	/// $(d, -c, -b, a)$ and $Z\rho Z$ gives $(a, -b, -c, d)$.
+	pub fn apply_channel(&mut self, qubit: usize, channel: &PauliChannel) {
+		let PauliChannel { px, py, pz } = *channel;
+		let keep_diagonal = 1.0 - px - py;
+		let flip_diagonal = px + py;
+		let keep_off = 1.0 - px - py - 2.0 * pz;
+		let flip_off = px - py;
+
+		let col_bit = 1 << qubit;
+		let row_bit = 1 << (qubit + self.n_qubits);
+		let (re, im) = self.vector.parts_mut();
+		for a in (0..re.len()).filter(|i| i & (col_bit | row_bit) == 0) {
+			let b = a | col_bit;
+			let c = a | row_bit;
+			let d = a | col_bit | row_bit;
+			for values in [&mut *re, &mut *im] {
+				let (va, vb, vc, vd) = (values[a], values[b], values[c], values[d]);
+				values[a] = keep_diagonal * va + flip_diagonal * vd;
+				values[d] = keep_diagonal * vd + flip_diagonal * va;
+				values[b] = keep_off * vb + flip_off * vc;
+				values[c] = keep_off * vc + flip_off * vb;
+			}
+		}
+	}
+
+	/// Applies the noise of a gate on the qubits in `support`.
+	pub fn apply_noise(&mut self, support: usize, model: &NoiseModel) {
+		let channel = model.channel(support.count_ones() as usize);
+		for qubit in (0..self.n_qubits).filter(|qubit| support & (1 << qubit) != 0) {
+			self.apply_channel(qubit, &channel);
+		}
+	}
+
+	/// $\langle\psi|\rho|\psi\rangle$
+	pub fn fidelity(&self, state: &Statevector<f64>) -> f64 {
+		let mut result = 0.0;
+		for (r, row) in state.values.iter().enumerate() {
+			// $\overline{\psi_r}\sum_c\rho_{rc}\psi_c$
+			let mut sum = Complex { re: 0.0, im: 0.0 };
+			for (c, col) in state.values.iter().enumerate() {
+				sum = sum + self.get(r, c) * *col;
+			}
+			result += row.re * sum.re + row.im * sum.im;
+		}
+		result
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn plus_state() -> Statevector<f64> {
+		let s = std::f64::consts::FRAC_1_SQRT_2;
+		Statevector {
+			values: vec![Complex { re: s, im: 0.0 }, Complex { re: 0.0, im: s }],
+			n_qubits: 1,
+		}
+	}
+
+	#[test]
+	fn unitaries_match_statevector() {
+		// $|+i\rangle$, then $e^{i\theta X_0Y_1}$ on two qubits
+		let theta = 0.4;
+		let mut state = Statevector::new(2);
+		let single = plus_state();
+		state.values[0] = single.values[0];
+		state.values[1] = single.values[1];
+		let mut matrix = DensityMatrix::from_statevector(&state);
+		assert!((matrix.fidelity(&state) - 1.0).abs() < 1e-12);
+
+		let mut vector = ParallelStatevector::from(state);
+		vector.apply_pauli(0b11, 0b10, theta);
+		matrix.apply_pauli(0b11, 0b10, theta);
+		let state = Statevector::from(vector);
+		assert!((matrix.fidelity(&state) - 1.0).abs() < 1e-12);
+		assert!((matrix.trace() - 1.0).abs() < 1e-12);
+	}
+
+	#[test]
+	fn channels() {
+		// $|+i\rangle$ is an eigenstate of $Y$, $X$ and $Z$ flip it
+		let state = plus_state();
+		let mut matrix = DensityMatrix::from_statevector(&state);
+		matrix.apply_channel(0, &PauliChannel::new(0.1, 0.2, 0.05));
+		assert!((matrix.fidelity(&state) - 0.85).abs() < 1e-12);
+		assert!((matrix.trace() - 1.0).abs() < 1e-12);
+
+		// Fully depolarised
+		let mut matrix = DensityMatrix::from_statevector(&state);
+		matrix.apply_channel(0, &PauliChannel::depolarizing(0.75));
+		assert!((matrix.get(0, 0).re - 0.5).abs() < 1e-12);
+		assert!(matrix.get(0, 1).abs_squared() < 1e-24);
+
+		// Only qubit 1 is affected
+		let mut matrix = DensityMatrix::new(2);
+		matrix.apply_noise(
+			0b10,
+			&NoiseModel::new(PauliChannel::new(0.1, 0.0, 0.3), 0.0),
+		);
+		assert!((matrix.get(0, 0).re - 0.9).abs() < 1e-12);
+		assert!((matrix.get(2, 2).re - 0.1).abs() < 1e-12);
+	}
+}
@@ -1,10 +1,16 @@
 use std::ops::{Add, Mul, Sub};
 
 mod complex;
+mod density_matrix;
+mod noise;
+mod parallel;
 mod statevector;
 mod target_state_iterator;
 
 pub use complex::Complex;
+pub use density_matrix::{DensityMatrix, MAX_DENSITY_MATRIX_QUBITS};
+pub use noise::{NoiseModel, PauliChannel, PauliError};
+pub use parallel::{PARALLEL_THRESHOLD, ParallelStatevector};
 pub use statevector::Statevector;
 
 pub trait Simulatable<T: Mul<Output = T> + Add<Output = T> + Sub<Output = T> + Copy> {
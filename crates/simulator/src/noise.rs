@@ -0,0 +1,183 @@
+use rand::{Rng, RngExt};
+
+/// A single qubit Pauli channel
+///
+/// $$\rho\mapsto(1-p_x-p_y-p_z)\rho+p_xX\rho X+p_yY\rho Y+p_zZ\rho Z$$
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PauliChannel {
+	pub px: f64,
+	pub py: f64,
+	pub pz: f64,
+}
+
+/// An error drawn from a [PauliChannel].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauliError {
+	X,
+	Y,
+	Z,
+}
+
+impl PauliError {
+	/// The x and z masks of the error on `qubit`.
+	pub fn masks(self, qubit: usize) -> (usize, usize) {
+		match self {
+			PauliError::X => (1 << qubit, 0),
+			PauliError::Y => (1 << qubit, 1 << qubit),
+			PauliError::Z => (0, 1 << qubit),
+		}
+	}
+}
+
+impl PauliChannel {
+	/// # Panics
+	/// Panics if a probability is negative or if they add up to more than 1.
+	pub fn new(px: f64, py: f64, pz: f64) -> Self {
+		assert!(
+			px >= 0.0 && py >= 0.0 && pz >= 0.0 && px + py + pz <= 1.0,
+			"({px}, {py}, {pz}) are not the probabilities of a Pauli channel"
+		);
+		Self { px, py, pz }
+	}
+
+	/// The depolarising channel, $X$, $Y$ and $Z$ each with probability
+	/// $\frac{p}{3}$.
+	pub fn depolarizing(p: f64) -> Self {
+		Self::new(p / 3.0, p / 3.0, p / 3.0)
+	}
+
+	/// The channel that does nothing.
+	pub fn identity() -> Self {
+		Self::new(0.0, 0.0, 0.0)
+	}
+
+	/// The probability of any error.
+	pub fn error_rate(&self) -> f64 {
+		self.px + self.py + self.pz
+	}
+
+	/// Multiplies the probabilities by `factor`, keeping their ratios if the
+	/// error rate would exceed 1.
+	pub fn scaled(&self, factor: f64) -> Self {
+		let rate = self.error_rate();
+		if rate == 0.0 {
+			return *self;
+		}
+		let factor = factor.max(0.0).min(1.0 / rate);
+		Self {
+			px: self.px * factor,
+			py: self.py * factor,
+			pz: self.pz * factor,
+		}
+	}
+
+	/// Draws the error of one application, [None] if there is none.
+	pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<PauliError> {
+		let r: f64 = rng.random();
+		if r < self.px {
+			Some(PauliError::X)
+		} else if r < self.px + self.py {
+			Some(PauliError::Y)
+		} else if r < self.error_rate() {
+			Some(PauliError::Z)
+		} else {
+			None
+		}
+	}
+}
+
+/// # Noise model
+///
+/// After a gate on `size` qubits every one of those qubits goes through the
+/// same [PauliChannel]. Its probabilities are the ones of `channel`
+/// multiplied by $\text{size}^{\text{size\\_scaling}}$, so with a scaling of 0
+/// a gate only gets noisier by touching more qubits, and with a positive
+/// scaling every qubit also gets noisier the larger the gate is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseModel {
+	pub channel: PauliChannel,
+	pub size_scaling: f64,
+}
+
+impl NoiseModel {
+	pub fn new(channel: PauliChannel, size_scaling: f64) -> Self {
+		Self {
+			channel,
+			size_scaling,
+		}
+	}
+
+	/// Depolarising noise with probability `p` on every qubit of a gate.
+	pub fn depolarizing(p: f64) -> Self {
+		Self::new(PauliChannel::depolarizing(p), 0.0)
+	}
+
+	pub fn with_size_scaling(mut self, size_scaling: f64) -> Self {
+		self.size_scaling = size_scaling;
+		self
+	}
+
+	/// The channel on every qubit after a gate on `size` qubits.
+	pub fn channel(&self, size: usize) -> PauliChannel {
+		if size == 0 {
+			return PauliChannel::identity();
+		}
+		self.channel.scaled((size as f64).powf(self.size_scaling))
+	}
+
+	/// The probability that a gate on `size` qubits has no error.
+	pub fn gate_fidelity(&self, size: usize) -> f64 {
+		(1.0 - self.channel(size).error_rate()).powi(size as i32)
+	}
+
+	/// Draws the errors after a gate on the qubits in `support`, as the x
+	/// and z masks of the Pauli string they form.
+	pub fn sample_error<R: Rng + ?Sized>(&self, support: usize, rng: &mut R) -> (usize, usize) {
+		let channel = self.channel(support.count_ones() as usize);
+		let (mut x, mut z) = (0, 0);
+		let mut rest = support;
+		while rest != 0 {
+			let qubit = rest.trailing_zeros() as usize;
+			rest &= rest - 1;
+			if let Some(error) = channel.sample(rng) {
+				let (error_x, error_z) = error.masks(qubit);
+				x |= error_x;
+				z |= error_z;
+			}
+		}
+		(x, z)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{SeedableRng, rngs::StdRng};
+
+	#[test]
+	fn rates_scale_with_size() {
+		let model = NoiseModel::new(PauliChannel::new(0.01, 0.0, 0.02), 1.0);
+		let channel = model.channel(4);
+		assert!((channel.px - 0.04).abs() < 1e-12 && (channel.pz - 0.08).abs() < 1e-12);
+		assert!((model.gate_fidelity(4) - 0.88f64.powi(4)).abs() < 1e-12);
+		assert_eq!(model.gate_fidelity(0), 1.0);
+
+		// Capped at certain error, with the same ratios
+		let channel = model.channel(100);
+		assert!((channel.error_rate() - 1.0).abs() < 1e-12);
+		assert!((channel.pz - 2.0 * channel.px).abs() < 1e-12);
+
+		let mut rng = StdRng::seed_from_u64(1);
+		let shots = 20000;
+		let mut errors = 0;
+		for _ in 0..shots {
+			let (x, z) = model.sample_error(0b1010, &mut rng);
+			assert_eq!((x | z) & !0b1010, 0);
+			assert_eq!(x & z, 0);
+			errors += (x | z).count_ones();
+		}
+		// 2 qubits with a rate of 0.06 each
+		let rate = errors as f64 / (2 * shots) as f64;
+		assert!((rate - 0.06).abs() < 0.01);
+	}
+}
@@ -0,0 +1,569 @@
+use std::thread;
+
+use crate::{Complex, Simulatable, Statevector};
+
+/// Registers with fewer qubits are always updated on the calling thread.
+pub const PARALLEL_THRESHOLD: usize = 14;
+
+/// The smallest amount of amplitude pairs given to one thread.
+const MIN_CHUNK: usize = 1 << 12;
+
+/// Blocks with fewer pairs are updated without splitting them into halves.
+const SMALL_BLOCK: usize = 16;
+
+/// A statevector specialised for `f64`.
+///
+/// The real and imaginary parts are stored in separate vectors, so the
+/// updates are plain loops over contiguous `f64` slices that the compiler can
+/// vectorise. Registers with at least [PARALLEL_THRESHOLD] qubits are split
+/// across threads. The results are the same as with [Statevector].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelStatevector {
+	re: Vec<f64>,
+	im: Vec<f64>,
+	n_qubits: usize,
+	threads: usize,
+}
+
+/// The two halves of an update, `lo` has the split bit as zero and `hi` as
+/// one. `offset` is the index of `lo[0]` in the full vector.
+struct Pair<'a> {
+	offset: usize,
+	lo_re: &'a mut [f64],
+	lo_im: &'a mut [f64],
+	hi_re: &'a mut [f64],
+	hi_im: &'a mut [f64],
+}
+
+/// What one thread updates at once. Small blocks are handed out as runs of
+/// whole blocks, so there is no item per block.
+enum Work<'a> {
+	/// Whole blocks of `2 * half` values starting at `offset`.
+	Blocks {
+		offset: usize,
+		re: &'a mut [f64],
+		im: &'a mut [f64],
+	},
+	Pair(Pair<'a>),
+}
+
+impl Work<'_> {
+	/// Calls `f` for every value with the split bit as zero with its index,
+	/// its value and the value at the index xor `half + low`, and stores the
+	/// two results. `low` has to be below `half`.
+	fn update<F: Fn(usize, [f64; 4]) -> [f64; 4]>(self, half: usize, low: usize, f: &F) {
+		match self {
+			// Neighbours, with constant indices
+			Work::Blocks { offset, re, im } if half == 1 => {
+				let blocks = re.chunks_exact_mut(2).zip(im.chunks_exact_mut(2));
+				for (block, (re, im)) in blocks.enumerate() {
+					let values = f(offset + 2 * block, [re[0], im[0], re[1], im[1]]);
+					[re[0], im[0], re[1], im[1]] = values;
+				}
+			}
+			// Too small to be worth splitting up
+			Work::Blocks { offset, re, im } if half < SMALL_BLOCK => {
+				let blocks = re
+					.chunks_exact_mut(2 * half)
+					.zip(im.chunks_exact_mut(2 * half));
+				for (block, (re, im)) in blocks.enumerate() {
+					let offset = offset + block * 2 * half;
+					for i in 0..half {
+						let j = half + (i ^ low);
+						let values = f(offset + i, [re[i], im[i], re[j], im[j]]);
+						[re[i], im[i], re[j], im[j]] = values;
+					}
+				}
+			}
+			Work::Blocks { offset, re, im } => {
+				let blocks = re.chunks_mut(2 * half).zip(im.chunks_mut(2 * half));
+				for (block, (re, im)) in blocks.enumerate() {
+					let (lo_re, hi_re) = re.split_at_mut(half);
+					let (lo_im, hi_im) = im.split_at_mut(half);
+					let pair = Pair {
+						offset: offset + block * 2 * half,
+						lo_re,
+						lo_im,
+						hi_re,
+						hi_im,
+					};
+					pair.update(low, f);
+				}
+			}
+			// The chunk of `hi` is already the one of `low`
+			Work::Pair(pair) => {
+				let low = low & (pair.lo_re.len() - 1);
+				pair.update(low, f);
+			}
+		}
+	}
+}
+
+impl Pair<'_> {
+	/// Like [Work::update] with `low` below the length of the chunks.
+	#[inline(always)]
+	fn update<F: Fn(usize, [f64; 4]) -> [f64; 4]>(self, low: usize, f: &F) {
+		let Pair {
+			offset,
+			lo_re,
+			lo_im,
+			hi_re,
+			hi_im,
+		} = self;
+		if low == 0 {
+			// Contiguous, so this can be vectorised
+			let values = lo_re
+				.iter_mut()
+				.zip(lo_im.iter_mut())
+				.zip(hi_re.iter_mut())
+				.zip(hi_im.iter_mut());
+			for (i, (((lo_re, lo_im), hi_re), hi_im)) in values.enumerate() {
+				[*lo_re, *lo_im, *hi_re, *hi_im] = f(offset + i, [*lo_re, *lo_im, *hi_re, *hi_im]);
+			}
+			return;
+		}
+
+		for i in 0..lo_re.len() {
+			let j = i ^ low;
+			let values = f(offset + i, [lo_re[i], lo_im[i], hi_re[j], hi_im[j]]);
+			[lo_re[i], lo_im[i], hi_re[j], hi_im[j]] = values;
+		}
+	}
+}
+
+impl ParallelStatevector {
+	/// The $|0\dots0\rangle$ state, using all available threads.
+	pub fn new(n_qubits: usize) -> Self {
+		let mut re = vec![0.0; 1 << n_qubits];
+		re[0] = 1.0;
+		Self {
+			im: vec![0.0; re.len()],
+			re,
+			n_qubits,
+			threads: thread::available_parallelism()
+				.map(|threads| threads.get())
+				.unwrap_or(1),
+		}
+	}
+
+	/// Limits the amount of threads, 1 disables threading.
+	pub fn with_threads(mut self, threads: usize) -> Self {
+		self.threads = threads.max(1);
+		self
+	}
+
+	pub fn n_qubits(&self) -> usize {
+		self.n_qubits
+	}
+
+	pub fn threads(&self) -> usize {
+		self.threads
+	}
+
+	pub fn re(&self) -> &[f64] {
+		&self.re
+	}
+
+	pub fn im(&self) -> &[f64] {
+		&self.im
+	}
+
+	pub(crate) fn parts_mut(&mut self) -> (&mut [f64], &mut [f64]) {
+		(&mut self.re, &mut self.im)
+	}
+
+	pub fn get(&self, index: usize) -> Complex<f64> {
+		Complex {
+			re: self.re[index],
+			im: self.im[index],
+		}
+	}
+
+	/// $\langle\text{self}|\text{other}\rangle$
+	pub fn inner_product(&self, other: &Self) -> Complex<f64> {
+		assert_eq!(
+			self.n_qubits, other.n_qubits,
+			"Both states need the same amount of qubits"
+		);
+		let mut result = Complex { re: 0.0, im: 0.0 };
+		for i in 0..self.re.len() {
+			result.re += self.re[i] * other.re[i] + self.im[i] * other.im[i];
+			result.im += self.re[i] * other.im[i] - self.im[i] * other.re[i];
+		}
+		result
+	}
+
+	/// The amount of pairs one thread handles at once, a power of two.
+	fn chunk_size(&self, pairs: usize) -> usize {
+		if self.threads == 1 || self.n_qubits < PARALLEL_THRESHOLD {
+			return pairs;
+		}
+
+		let chunk = (pairs / self.threads).max(MIN_CHUNK).min(pairs);
+		1 << chunk.ilog2()
+	}
+
+	/// Splits the vector into pairs of chunks where the chunks in `lo` have
+	/// bit `split` as zero. The chunk `c` of a block is paired with the chunk
+	/// `c ^ permutation` of the same block. If a whole block is one chunk,
+	/// neighbouring blocks are kept together instead.
+	fn work(&mut self, split: usize, permutation: usize) -> Vec<Work<'_>> {
+		let half = 1 << split;
+		let chunk = self.chunk_size(half);
+		if chunk == half {
+			let region = 2 * self.chunk_size(self.re.len() / 2).max(half);
+			return self
+				.re
+				.chunks_mut(region)
+				.zip(self.im.chunks_mut(region))
+				.enumerate()
+				.map(|(i, (re, im))| Work::Blocks {
+					offset: i * region,
+					re,
+					im,
+				})
+				.collect();
+		}
+
+		let mut work = Vec::new();
+		for (block, (re, im)) in self
+			.re
+			.chunks_mut(2 * half)
+			.zip(self.im.chunks_mut(2 * half))
+			.enumerate()
+		{
+			let (lo_re, hi_re) = re.split_at_mut(half);
+			let (lo_im, hi_im) = im.split_at_mut(half);
+			let mut his: Vec<Option<(&mut [f64], &mut [f64])>> = hi_re
+				.chunks_mut(chunk)
+				.zip(hi_im.chunks_mut(chunk))
+				.map(Some)
+				.collect();
+			for (c, (lo_re, lo_im)) in lo_re
+				.chunks_mut(chunk)
+				.zip(lo_im.chunks_mut(chunk))
+				.enumerate()
+			{
+				let (hi_re, hi_im) = his[c ^ permutation].take().unwrap();
+				work.push(Work::Pair(Pair {
+					offset: block * 2 * half + c * chunk,
+					lo_re,
+					lo_im,
+					hi_re,
+					hi_im,
+				}));
+			}
+		}
+
+		work
+	}
+
+	/// Applies the gate like [Statevector::apply].
+	pub fn apply<O: Simulatable<f64>>(&mut self, operation: &O) {
+		let [a, b, c, d] = operation.matrix();
+		let target = operation.target();
+		let controls: usize = operation.controls().iter().map(|i| 1 << i).sum();
+		let threads = self.threads;
+
+		let update = |index: usize, [x_re, x_im, y_re, y_im]: [f64; 4]| {
+			if index & controls != controls {
+				return [x_re, x_im, y_re, y_im];
+			}
+			[
+				a.re * x_re - a.im * x_im + b.re * y_re - b.im * y_im,
+				a.re * x_im + a.im * x_re + b.re * y_im + b.im * y_re,
+				c.re * x_re - c.im * x_im + d.re * y_re - d.im * y_im,
+				c.re * x_im + c.im * x_re + d.re * y_im + d.im * y_re,
+			]
+		};
+		run(threads, self.work(target, 0), |work| {
+			work.update(1 << target, 0, &update)
+		});
+	}
+
+	/// # Apply Pauli
+	///
+	/// Applies $e^{i\theta P}$ with $\theta$ in radians, where $P$ has $X$ on
+	/// the ones of `x` and $Z$ on the ones of `z` (both for $Y$). With $Y=iXZ$
+	///
+	/// $$P|b\rangle=i^{n_Y}(-1)^{|b\wedge z|}|b\oplus x\rangle.$$
+	pub fn apply_pauli(&mut self, x: usize, z: usize, theta: f64) {
+		let (cos, sin) = (theta.cos(), theta.sin());
+		let parity = move |b: usize| {
+			if (b & z).count_ones().is_multiple_of(2) {
+				1.0
+			} else {
+				-1.0
+			}
+		};
+
+		if x == 0 {
+			// Diagonal, $\cos\theta\pm i\sin\theta$
+			let chunk = self.chunk_size(self.re.len());
+			let chunks: Vec<(usize, &mut [f64], &mut [f64])> = self
+				.re
+				.chunks_mut(chunk)
+				.zip(self.im.chunks_mut(chunk))
+				.enumerate()
+				.map(|(i, (re, im))| (i * chunk, re, im))
+				.collect();
+			run(self.threads, chunks, |(offset, re, im)| {
+				for i in 0..re.len() {
+					let s = sin * parity(offset + i);
+					let (v_re, v_im) = (re[i], im[i]);
+					re[i] = cos * v_re - s * v_im;
+					im[i] = cos * v_im + s * v_re;
+				}
+			});
+			return;
+		}
+
+		// $i\sin\theta\cdot i^{n_Y}$
+		let n_y = (x & z).count_ones();
+		let (f_re, f_im) = match (n_y + 1) % 4 {
+			0 => (sin, 0.0),
+			1 => (0.0, sin),
+			2 => (-sin, 0.0),
+			_ => (0.0, -sin),
+		};
+		// $(b\oplus x)\wedge z$ has $n_Y$ more or less ones than $b\wedge z$
+		let y_parity = if n_y.is_multiple_of(2) { 1.0 } else { -1.0 };
+		let high = x.ilog2() as usize;
+		let low = x ^ (1 << high);
+		let chunk = self.chunk_size(1 << high);
+		let threads = self.threads;
+		let update = |index: usize, [x_re, x_im, y_re, y_im]: [f64; 4]| {
+			let p = parity(index);
+			let q = p * y_parity;
+			[
+				cos * x_re + q * (f_re * y_re - f_im * y_im),
+				cos * x_im + q * (f_re * y_im + f_im * y_re),
+				cos * y_re + p * (f_re * x_re - f_im * x_im),
+				cos * y_im + p * (f_re * x_im + f_im * x_re),
+			]
+		};
+		run(threads, self.work(high, low / chunk), |work| {
+			work.update(1 << high, low, &update)
+		});
+	}
+}
+
+/// Runs `f` on every item, spread over `threads` threads.
+fn run<T: Send, F: Fn(T) + Sync>(threads: usize, items: Vec<T>, f: F) {
+	if threads <= 1 || items.len() <= 1 {
+		items.into_iter().for_each(f);
+		return;
+	}
+
+	// Neighbouring items stay on the same thread
+	let group_size = items.len().div_ceil(threads);
+	let mut groups: Vec<Vec<T>> = Vec::new();
+	for item in items.into_iter() {
+		match groups.last_mut() {
+			Some(group) if group.len() < group_size => group.push(item),
+			_ => groups.push(vec![item]),
+		}
+	}
+
+	let f = &f;
+	thread::scope(|scope| {
+		for group in groups {
+			scope.spawn(move || group.into_iter().for_each(f));
+		}
+	});
+}
+
+impl From<Statevector<f64>> for ParallelStatevector {
+	fn from(value: Statevector<f64>) -> Self {
+		let mut state = Self::new(0);
+		state.n_qubits = value.n_qubits;
+		state.re = value.values.iter().map(|v| v.re).collect();
+		state.im = value.values.iter().map(|v| v.im).collect();
+		state
+	}
+}
+
+impl From<ParallelStatevector> for Statevector<f64> {
+	fn from(value: ParallelStatevector) -> Self {
+		Statevector {
+			values: value
+				.re
+				.into_iter()
+				.zip(value.im)
+				.map(|(re, im)| Complex { re, im })
+				.collect(),
+			n_qubits: value.n_qubits,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{RngExt, SeedableRng, rngs::StdRng};
+
+	struct Gate {
+		matrix: [Complex<f64>; 4],
+		target: usize,
+		controls: Vec<usize>,
+	}
+
+	impl Simulatable<f64> for Gate {
+		fn matrix(&self) -> [Complex<f64>; 4] {
+			self.matrix
+		}
+
+		fn target(&self) -> usize {
+			self.target
+		}
+
+		fn controls(&self) -> Vec<usize> {
+			self.controls.clone()
+		}
+	}
+
+	fn random_complex(rng: &mut StdRng) -> Complex<f64> {
+		Complex {
+			re: rng.random::<f64>() - 0.5,
+			im: rng.random::<f64>() - 0.5,
+		}
+	}
+
+	fn random_state(n_qubits: usize, rng: &mut StdRng) -> Statevector<f64> {
+		Statevector {
+			values: (0..1 << n_qubits).map(|_| random_complex(rng)).collect(),
+			n_qubits,
+		}
+	}
+
+	fn assert_close(a: &Statevector<f64>, b: &Statevector<f64>) {
+		for (a, b) in a.values().iter().zip(b.values().iter()) {
+			assert!((a.re - b.re).abs() < 1e-12 && (a.im - b.im).abs() < 1e-12);
+		}
+	}
+
+	#[test]
+	fn gates_match_generic() {
+		let mut rng = StdRng::seed_from_u64(1);
+		for (n_qubits, threads) in [
+			(3, 1),
+			(PARALLEL_THRESHOLD + 1, 1),
+			(PARALLEL_THRESHOLD + 1, 4),
+		] {
+			let mut generic = random_state(n_qubits, &mut rng);
+			let mut parallel = ParallelStatevector::from(generic.clone()).with_threads(threads);
+			for _ in 0..20 {
+				let target = rng.random_range(0..n_qubits);
+				let controls = (0..n_qubits)
+					.filter(|i| *i != target && rng.random_bool(0.2))
+					.collect();
+				let gate = Gate {
+					matrix: [(); 4].map(|_| random_complex(&mut rng)),
+					target,
+					controls,
+				};
+				generic.apply(&gate);
+				parallel.apply(&gate);
+			}
+			assert_close(&generic, &parallel.into());
+		}
+	}
+
+	#[test]
+	fn low_targets_match_generic() {
+		let mut rng = StdRng::seed_from_u64(3);
+		let n_qubits = PARALLEL_THRESHOLD + 2;
+		let initial = random_state(n_qubits, &mut rng);
+		for threads in [1, 4] {
+			let mut generic = initial.clone();
+			let mut parallel = ParallelStatevector::from(initial.clone()).with_threads(threads);
+
+			// Few work items even though every block is tiny
+			assert!(parallel.work(0, 0).len() <= threads);
+			assert!(parallel.work(1, 0).len() <= threads);
+
+			for target in [0, 1, 2] {
+				let gate = Gate {
+					matrix: [(); 4].map(|_| random_complex(&mut rng)),
+					target,
+					controls: vec![target + 3],
+				};
+				generic.apply(&gate);
+				parallel.apply(&gate);
+			}
+			// $e^{i\theta X_0Y_1}$, where $(Pv)_b=-i(-1)^{b_1}v_{b\oplus 3}$
+			let theta: f64 = 0.7;
+			let values = generic.values();
+			let expected = Statevector {
+				values: (0..values.len())
+					.map(|b| {
+						let sign = if b & 0b10 == 0 { 1.0 } else { -1.0 };
+						let other = values[b ^ 0b11];
+						Complex {
+							re: theta.cos() * values[b].re + theta.sin() * sign * other.re,
+							im: theta.cos() * values[b].im + theta.sin() * sign * other.im,
+						}
+					})
+					.collect(),
+				n_qubits,
+			};
+			parallel.apply_pauli(0b11, 0b10, theta);
+			assert_close(&expected, &parallel.into());
+		}
+	}
+
+	#[test]
+	#[should_panic]
+	fn inner_product_needs_equal_sizes() {
+		ParallelStatevector::new(3).inner_product(&ParallelStatevector::new(4));
+	}
+
+	#[test]
+	fn pauli_matches_gates() {
+		// $e^{i\theta X_0Z_2}$, by conjugating $e^{i\theta Z_0}$ with CNOT and H
+		let theta: f64 = 0.3;
+		let s = std::f64::consts::FRAC_1_SQRT_2;
+		let real = |v: f64| Complex { re: v, im: 0.0 };
+		let h = |target| Gate {
+			matrix: [real(s), real(s), real(s), real(-s)],
+			target,
+			controls: Vec::new(),
+		};
+		let cnot = Gate {
+			matrix: [real(0.0), real(1.0), real(1.0), real(0.0)],
+			target: 0,
+			controls: vec![2],
+		};
+		let rz = Gate {
+			matrix: [
+				Complex {
+					re: theta.cos(),
+					im: theta.sin(),
+				},
+				real(0.0),
+				real(0.0),
+				Complex {
+					re: theta.cos(),
+					im: -theta.sin(),
+				},
+			],
+			target: 0,
+			controls: Vec::new(),
+		};
+
+		let mut rng = StdRng::seed_from_u64(2);
+		for n_qubits in [3, PARALLEL_THRESHOLD + 1] {
+			let initial = random_state(n_qubits, &mut rng);
+			let mut expected = initial.clone();
+			for gate in [&h(0), &cnot, &rz, &cnot, &h(0)] {
+				expected.apply(gate);
+			}
+
+			for threads in [1, 4] {
+				let mut state = ParallelStatevector::from(initial.clone()).with_threads(threads);
+				state.apply_pauli(0b001, 0b100, theta);
+				assert_close(&expected, &state.into());
+			}
+		}
+	}
+}
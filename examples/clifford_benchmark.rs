@@ -0,0 +1,39 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use test_transpiler::{
+	connectivity::Connectivity,
+	experiment::{gate_count, multi_qubit_filter, random_clifford_tableau},
+	misc::NonZeroEvenUsize,
+};
+
+fn main() {
+	let gate_size = NonZeroEvenUsize::new(4).unwrap();
+	let samples = 20;
+
+	for n_qubits in [10, 20, 40] {
+		for (name, connectivity) in [
+			("full", None),
+			("line", Some(Connectivity::create_line(gate_size, n_qubits))),
+			(
+				"grid",
+				Some(Connectivity::create_square_grid(gate_size, n_qubits)),
+			),
+		] {
+			let qubits = connectivity
+				.as_ref()
+				.map(Connectivity::qubit_count)
+				.unwrap_or(n_qubits);
+			let mut rng = ChaCha8Rng::seed_from_u64(n_qubits as u64);
+			let mut total = 0;
+			for _ in 0..samples {
+				let tableau = random_clifford_tableau(qubits, &mut rng);
+				let decomposition = tableau.decompose(gate_size, connectivity.as_ref());
+				total += gate_count(&decomposition, multi_qubit_filter);
+			}
+			println!(
+				"{name} {qubits} qubits: {:.1} multi-qubit gates on average",
+				total as f64 / samples as f64
+			);
+		}
+	}
+}
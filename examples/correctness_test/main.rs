@@ -5,6 +5,7 @@ use test_transpiler::{
 	misc::NonZeroEvenUsize,
 	pauli::{CliffordPauliAngle, PauliAngle, PauliExp, PauliLetter, PauliString},
 	synthesize::synthesize,
+	verify::verify_equivalence,
 };
 
 const N_EXPS: usize = 30;
@@ -54,11 +55,14 @@ fn main() {
 			.map(move |_| random_exp::<_>(N_QUBITS, &mut rng))
 			.collect();
 
-		let (mut circuit, clifford, order) = synthesize(
+		let result = synthesize(
 			input,
 			NonZeroEvenUsize::new(GATE_SIZE).unwrap(),
 			connectivity.as_ref(),
 		);
+		let order = result.ordered();
+		let mut circuit = result.circuit;
+		let clifford = result.clifford;
 
 		let clifford: Vec<PauliExp<CliffordPauliAngle>> = if USE_TABLEAU {
 			let mut tableau: CliffordTableau = CliffordTableau::id();
@@ -88,6 +92,10 @@ fn main() {
 			}
 		}
 
+		let proof =
+			verify_equivalence(&order, &circuit).expect("synthesized circuit is not equivalent");
+		println!("round {i}: {proof:?}");
+
 		PauliExp::write_exp_file(
 			&circuit,
 			&format!("./examples/correctness_test/circuit{i}.exp"),
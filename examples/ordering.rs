@@ -0,0 +1,53 @@
+use test_transpiler::{
+	experiment::{gate_count, multi_qubit_filter},
+	misc::NonZeroEvenUsize,
+	pauli::PauliExp,
+	synthesize::{
+		Greedy, InputOrder, Lookahead, Randomized, SynthesisOrdering, synthesize_with_ordering,
+	},
+};
+
+const FOLDER: &str = "./datasets/molecules_small/";
+const GATE_SIZE: usize = 4;
+
+/// Compares the multi qubit gate counts of the ordering strategies.
+fn main() {
+	let gate_size = NonZeroEvenUsize::new(GATE_SIZE).unwrap();
+	let mut orderings: Vec<(&str, Box<dyn SynthesisOrdering>)> = vec![
+		("input_order", Box::new(InputOrder)),
+		("randomized", Box::new(Randomized::new(0))),
+		("greedy", Box::new(Greedy)),
+		("lookahead_1", Box::new(Lookahead { depth: 1, width: 4 })),
+		("lookahead_2", Box::new(Lookahead { depth: 2, width: 4 })),
+	];
+
+	let mut paths: Vec<_> = std::fs::read_dir(FOLDER)
+		.unwrap()
+		.map(|entry| entry.unwrap().path())
+		.collect();
+	paths.sort();
+
+	let names: Vec<&str> = orderings.iter().map(|(name, _)| *name).collect();
+	println!("name,{}", names.join(","));
+	let mut totals = vec![0; orderings.len()];
+	for path in paths {
+		let exps = PauliExp::read_exp_file(&path);
+		let counts: Vec<usize> = orderings
+			.iter_mut()
+			.map(|(_, ordering)| {
+				let result =
+					synthesize_with_ordering(exps.clone(), gate_size, None, ordering.as_mut());
+				gate_count(&result.circuit, multi_qubit_filter)
+			})
+			.collect();
+
+		for (total, count) in totals.iter_mut().zip(counts.iter()) {
+			*total += count;
+		}
+		let counts: Vec<String> = counts.iter().map(usize::to_string).collect();
+		println!("{},{}", path.display(), counts.join(","));
+	}
+
+	let totals: Vec<String> = totals.iter().map(usize::to_string).collect();
+	println!("total,{}", totals.join(","));
+}
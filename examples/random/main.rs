@@ -8,7 +8,7 @@ use test_transpiler::{
 	clifford_tableau::CliffordTableau,
 	connectivity::Connectivity,
 	misc::NonZeroEvenUsize,
-	pauli::{CliffordPauliAngle, PauliAngle, PauliExp, PauliLetter, PauliString},
+	pauli::{PauliAngle, PauliExp, PauliLetter, PauliString},
 	synthesize::synthesize,
 };
 
@@ -73,29 +73,19 @@ fn run_experiment(parameters: Parameters, connectivity: Arc<Option<Connectivity>
 			original_exponentials.push(random_exp::<_>(parameters.max_exp_size, &mut rng));
 		}
 
-		#[cfg(not(feature = "return_ordered"))]
-		let (mut circuit, clifford) = synthesize(
-			original_exponentials,
-			NonZeroEvenUsize::new(parameters.gate_size).unwrap(),
-			connectivity.as_ref().as_ref(),
-		);
-		#[cfg(feature = "return_ordered")]
-		let (mut circuit, clifford, _) = synthesize(
+		let result = synthesize(
 			original_exponentials,
 			NonZeroEvenUsize::new(parameters.gate_size).unwrap(),
 			connectivity.as_ref().as_ref(),
 		);
+		let mut circuit = result.circuit;
+		let clifford = result.clifford;
 
 		let mut clifford: Vec<PauliExp<PauliAngle>> = if parameters.use_tableau {
 			let mut tableau = CliffordTableau::id();
 
 			for op in clifford.iter() {
-				let neg = match op.angle {
-					CliffordPauliAngle::PiOver4 => false,
-					CliffordPauliAngle::NegPiOver4 => true,
-					_ => unreachable!(),
-				};
-				tableau.merge_pi_over_4_pauli(neg, &op.string);
+				tableau.merge_clifford(op.clone());
 			}
 
 			let decomposition = tableau.decompose(
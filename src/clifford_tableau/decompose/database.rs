@@ -0,0 +1,640 @@
+//! Optimal decompositions of Cliffords on a few qubits, looked up in a
+//! database of all of them.
+
+use std::{
+	collections::{HashMap, VecDeque},
+	fs::File,
+	io::{self, BufReader, BufWriter, Read, Write},
+	path::Path,
+};
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	misc::NonZeroEvenUsize,
+	pauli::{CliffordPauliAngle, Negate, PauliExp, PauliLetter, PauliString},
+};
+
+/// The most qubits a [CliffordDatabase] can be built for. Four qubits already
+/// have $47\,377\,612\,800$ tableaux without signs.
+pub const MAX_DATABASE_QUBITS: usize = 3;
+
+const MAGIC: &[u8; 4] = b"CTDB";
+const VERSION: u8 = 1;
+/// Marks the identity, which needs no step.
+const NO_STEP: u8 = u8::MAX;
+
+/// The amount of tableaux without signs on `n_qubits`, the order of the
+/// symplectic group $2^{n^2}\prod_{j=1}^n(4^j-1)$.
+fn tableau_count(n_qubits: usize) -> usize {
+	(1..=n_qubits).fold(1 << (n_qubits * n_qubits), |count, j| {
+		count * ((1 << (2 * j)) - 1)
+	})
+}
+
+/// # Clifford database
+///
+/// Every Clifford on `n_qubits` qubits with its shortest decomposition into
+/// $e^{i\frac{\pi}{4}P}$ where $P$ acts on at most `gate_size` qubits. The
+/// signs are ignored, they are fixed afterwards with $e^{i\frac{\pi}{2}P}$
+/// for a single Pauli $P$, split into pieces of at most `gate_size` qubits.
+/// So the number of $\frac{\pi}{4}$ rotations is optimal and there are at
+/// most $\lceil\frac{n}{\text{gate size}}\rceil$ rotations more.
+///
+/// The database is found by a breadth first search from the identity, which
+/// takes a while for three qubits ($1\,451\,520$ tableaux). It can be
+/// written to disk with [CliffordDatabase::write_file] to skip this.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliffordDatabase {
+	n_qubits: usize,
+	gate_size: usize,
+	/// The strings of the rotations as x and z masks.
+	generators: Vec<(u8, u8)>,
+	/// For every tableau, the generator that brings it one step closer to the
+	/// identity.
+	steps: HashMap<u64, u8>,
+}
+
+impl CliffordDatabase {
+	/// Builds the database, panics if `n_qubits` is zero or more than
+	/// [MAX_DATABASE_QUBITS].
+	pub fn new(n_qubits: usize, gate_size: NonZeroEvenUsize) -> Self {
+		Self::with_max_steps(n_qubits, gate_size, usize::MAX)
+	}
+
+	/// The database of the tableaux that take at most `max_steps` rotations.
+	fn with_max_steps(n_qubits: usize, gate_size: NonZeroEvenUsize, max_steps: usize) -> Self {
+		assert!(
+			(1..=MAX_DATABASE_QUBITS).contains(&n_qubits),
+			"A database has between 1 and {MAX_DATABASE_QUBITS} qubits"
+		);
+
+		let mut database = CliffordDatabase {
+			n_qubits,
+			gate_size: gate_size.as_value(),
+			generators: generators(n_qubits, gate_size.as_value()),
+			steps: HashMap::new(),
+		};
+
+		let identity = database.identity_key();
+		database.steps.insert(identity, NO_STEP);
+		let mut queue = VecDeque::from([(identity, 0)]);
+		while let Some((key, steps)) = queue.pop_front() {
+			if steps == max_steps {
+				continue;
+			}
+
+			for index in 0..database.generators.len() {
+				let next = database.apply(key, index as u8);
+				// Merging the same rotation twice is a Pauli, so `index` leads back
+				database.steps.entry(next).or_insert_with(|| {
+					queue.push_back((next, steps + 1));
+					index as u8
+				});
+			}
+		}
+
+		database
+	}
+
+	pub fn n_qubits(&self) -> usize {
+		self.n_qubits
+	}
+
+	pub fn gate_size(&self) -> NonZeroEvenUsize {
+		NonZeroEvenUsize::new(self.gate_size).unwrap()
+	}
+
+	/// The number of tableaux without signs in the database.
+	pub fn len(&self) -> usize {
+		self.steps.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.steps.is_empty()
+	}
+
+	/// # Reduction
+	///
+	/// The rotations that, merged into `tableau` one after the other, turn it
+	/// into the identity on `qubits`, signs included. `None` if there are too
+	/// many qubits or if the rows of `qubits` act on other qubits.
+	pub fn reduction(
+		&self,
+		tableau: &CliffordTableau,
+		qubits: &[usize],
+	) -> Option<Vec<PauliExp<CliffordPauliAngle>>> {
+		if qubits.len() > self.n_qubits {
+			return None;
+		}
+
+		let mut key = self.key(tableau, qubits)?;
+		let identity = self.identity_key();
+		let mut tableau = tableau.clone();
+		let mut reduction = Vec::new();
+		while key != identity {
+			let step = *self.steps.get(&key)?;
+			key = self.apply(key, step);
+
+			let (x, z) = self.generators[step as usize];
+			let mut string = PauliString::default();
+			for (local, qubit) in qubits.iter().enumerate() {
+				string.set(*qubit, letter(x >> local & 1 == 1, z >> local & 1 == 1));
+			}
+			tableau.merge_pi_over_4_pauli(false, &string);
+			reduction.push(PauliExp {
+				string,
+				angle: CliffordPauliAngle::PiOver4,
+			});
+		}
+
+		// Only signs are left, the Pauli that flips them is done in pieces
+		let signs = qubits
+			.iter()
+			.filter_map(
+				|qubit| match (tableau.x_signs.get(*qubit), tableau.z_signs.get(*qubit)) {
+					(false, false) => None,
+					(x, z) => Some((*qubit, letter(z, x))),
+				},
+			)
+			.collect::<Vec<_>>();
+		for chunk in signs.chunks(self.gate_size) {
+			let mut string = PauliString::default();
+			for (qubit, letter) in chunk {
+				string.set(*qubit, *letter);
+			}
+			reduction.push(PauliExp {
+				string,
+				angle: CliffordPauliAngle::PiOver2,
+			});
+		}
+
+		Some(reduction)
+	}
+
+	/// # Peephole
+	///
+	/// Replaces windows of consecutive rotations that act on at most
+	/// `n_qubits` qubits together by their optimal decomposition, if that is
+	/// shorter. The windows are taken greedily from the front.
+	pub fn peephole(
+		&self,
+		cliffords: &[PauliExp<CliffordPauliAngle>],
+	) -> Vec<PauliExp<CliffordPauliAngle>> {
+		let mut optimised = Vec::with_capacity(cliffords.len());
+		let mut start = 0;
+		while start < cliffords.len() {
+			let mut qubits: Vec<usize> = Vec::new();
+			let mut end = start;
+			while let Some(clifford) = cliffords.get(end) {
+				let mut extended = qubits.clone();
+				for target in clifford.string.targets() {
+					if !extended.contains(&target) {
+						extended.push(target);
+					}
+				}
+				if extended.len() > self.n_qubits || clifford.string.len() > self.gate_size {
+					break;
+				}
+				qubits = extended;
+				end += 1;
+			}
+
+			if end == start {
+				// Acts on too many qubits to be in any window
+				optimised.push(cliffords[start].clone());
+				start += 1;
+				continue;
+			}
+
+			let window = &cliffords[start..end];
+			let mut tableau = CliffordTableau::id();
+			for clifford in window.iter() {
+				tableau.merge_clifford(clifford.clone());
+			}
+			qubits.sort();
+			match self.reduction(&tableau, &qubits) {
+				Some(reduction) if reduction.len() < window.len() => {
+					optimised.extend(inverted(reduction));
+				}
+				_ => optimised.extend_from_slice(window),
+			}
+			start = end;
+		}
+
+		optimised
+	}
+
+	/// Writes the database in a small binary format.
+	pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		writer.write_all(MAGIC)?;
+		writer.write_all(&[VERSION, self.n_qubits as u8])?;
+		writer.write_all(&(self.gate_size as u64).to_le_bytes())?;
+		writer.write_all(&(self.steps.len() as u64).to_le_bytes())?;
+
+		let mut steps: Vec<_> = self.steps.iter().collect();
+		steps.sort();
+		for (key, step) in steps {
+			writer.write_all(&key.to_le_bytes())?;
+			writer.write_all(&[*step])?;
+		}
+
+		Ok(())
+	}
+
+	/// Reads a database written by [CliffordDatabase::write].
+	pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+		let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+		let mut magic = [0; 4];
+		reader.read_exact(&mut magic)?;
+		if magic != *MAGIC {
+			return Err(invalid("Not a Clifford database"));
+		}
+
+		let mut header = [0; 2];
+		reader.read_exact(&mut header)?;
+		let [version, n_qubits] = header;
+		if version != VERSION {
+			return Err(invalid("Unsupported Clifford database version"));
+		}
+		let n_qubits = n_qubits as usize;
+		if !(1..=MAX_DATABASE_QUBITS).contains(&n_qubits) {
+			return Err(invalid("Invalid number of qubits"));
+		}
+
+		let mut word = [0; 8];
+		reader.read_exact(&mut word)?;
+		let gate_size = u64::from_le_bytes(word) as usize;
+		if NonZeroEvenUsize::new(gate_size).is_none() {
+			return Err(invalid("Invalid gate size"));
+		}
+		reader.read_exact(&mut word)?;
+		let len = u64::from_le_bytes(word);
+		if len > tableau_count(n_qubits) as u64 {
+			return Err(invalid("More entries than tableaux"));
+		}
+		let len = len as usize;
+
+		let generators = generators(n_qubits, gate_size);
+		let mut steps = HashMap::with_capacity(len);
+		let mut entry = [0; 9];
+		for _ in 0..len {
+			reader.read_exact(&mut entry)?;
+			let key = u64::from_le_bytes(entry[..8].try_into().unwrap());
+			let step = entry[8];
+			if step != NO_STEP && step as usize >= generators.len() {
+				return Err(invalid("Invalid step"));
+			}
+			steps.insert(key, step);
+		}
+
+		Ok(CliffordDatabase {
+			n_qubits,
+			gate_size,
+			generators,
+			steps,
+		})
+	}
+
+	pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+
+	pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	/// The bits a row of the tableau takes up in a key, x bits first.
+	fn row_bits(&self) -> usize {
+		2 * self.n_qubits
+	}
+
+	fn identity_key(&self) -> u64 {
+		let n = self.n_qubits;
+		let mut key = 0;
+		for i in 0..n {
+			key |= (1 << i) << (i * self.row_bits());
+			key |= (1 << (n + i)) << ((n + i) * self.row_bits());
+		}
+		key
+	}
+
+	/// The rows of `qubits` without signs, first the x rows and then the z
+	/// rows. Unused local qubits are the identity.
+	fn key(&self, tableau: &CliffordTableau, qubits: &[usize]) -> Option<u64> {
+		let n = self.n_qubits;
+		let mut key = self.identity_key();
+		for (local, qubit) in qubits.iter().enumerate() {
+			for (row, string) in [
+				(local, tableau.get_x_row(*qubit)),
+				(n + local, tableau.get_z_row(*qubit)),
+			] {
+				let mut bits = 0u64;
+				for (target, letter) in string.letters() {
+					let position = qubits.iter().position(|q| *q == target)?;
+					let (x, z) = match letter {
+						PauliLetter::X => (true, false),
+						PauliLetter::Y => (true, true),
+						PauliLetter::Z => (false, true),
+						PauliLetter::I => (false, false),
+					};
+					bits |= (x as u64) << position;
+					bits |= (z as u64) << (n + position);
+				}
+				let shift = row * self.row_bits();
+				key &= !(((1 << self.row_bits()) - 1) << shift);
+				key |= bits << shift;
+			}
+		}
+		Some(key)
+	}
+
+	/// Merges the generator into the tableau of `key`, up to signs. A row
+	/// that anticommutes with the string gets multiplied by it.
+	fn apply(&self, key: u64, generator: u8) -> u64 {
+		let n = self.n_qubits;
+		let (gx, gz) = self.generators[generator as usize];
+		let mask = (1u64 << self.row_bits()) - 1;
+		let mut next = 0;
+		for row in 0..2 * n {
+			let shift = row * self.row_bits();
+			let bits = (key >> shift) & mask;
+			let x = (bits & ((1 << n) - 1)) as u8;
+			let z = (bits >> n) as u8;
+			let bits = match ((x & gz) ^ (z & gx)).count_ones() % 2 {
+				1 => bits ^ (gx as u64 | (gz as u64) << n),
+				_ => bits,
+			};
+			next |= bits << shift;
+		}
+		next
+	}
+}
+
+/// All strings on `n_qubits` qubits with at most `gate_size` letters, as x
+/// and z masks.
+fn generators(n_qubits: usize, gate_size: usize) -> Vec<(u8, u8)> {
+	let mut generators = Vec::new();
+	for x in 0..1u8 << n_qubits {
+		for z in 0..1u8 << n_qubits {
+			let support = (x | z).count_ones() as usize;
+			if support != 0 && support <= gate_size {
+				generators.push((x, z));
+			}
+		}
+	}
+	generators
+}
+
+fn letter(x: bool, z: bool) -> PauliLetter {
+	match (x, z) {
+		(true, true) => PauliLetter::Y,
+		(true, false) => PauliLetter::X,
+		(false, true) => PauliLetter::Z,
+		(false, false) => PauliLetter::I,
+	}
+}
+
+/// The decomposition of the tableau that a reduction turns into the
+/// identity, which is the reduction inverted and reversed.
+pub(super) fn inverted(
+	reduction: Vec<PauliExp<CliffordPauliAngle>>,
+) -> impl Iterator<Item = PauliExp<CliffordPauliAngle>> {
+	reduction.into_iter().rev().map(|mut clifford| {
+		clifford.angle.negate();
+		clifford
+	})
+}
+
+impl CliffordTableau {
+	/// # Decompose exact
+	///
+	/// Decomposes the tableau optimally with the database, see
+	/// [CliffordDatabase]. `None` if it acts non-trivially on more qubits than
+	/// the database has.
+	pub fn decompose_exact(
+		&self,
+		database: &CliffordDatabase,
+	) -> Option<Vec<PauliExp<CliffordPauliAngle>>> {
+		let qubits: Vec<usize> = (0..self.size())
+			.filter(|qubit| !self.is_identity_qubit(*qubit))
+			.collect();
+		let reduction = database.reduction(self, &qubits)?;
+		Some(inverted(reduction).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		pauli_string,
+		test_utils::{random_cliffords, tableau},
+	};
+	use bits::Bits;
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+
+	fn quarter_turns(cliffords: &[PauliExp<CliffordPauliAngle>]) -> usize {
+		cliffords
+			.iter()
+			.filter(|clifford| {
+				matches!(
+					clifford.angle,
+					CliffordPauliAngle::PiOver4 | CliffordPauliAngle::NegPiOver4
+				)
+			})
+			.count()
+	}
+
+	#[test]
+	fn known_optimal() {
+		let gate_size = NonZeroEvenUsize::new(2).unwrap();
+		let database = CliffordDatabase::new(2, gate_size);
+		assert_eq!(database.len(), 720);
+		assert_eq!(CliffordDatabase::new(1, gate_size).len(), 6);
+
+		let cnot = [
+			(pauli_string!("ZX"), CliffordPauliAngle::PiOver4),
+			(pauli_string!("Z"), CliffordPauliAngle::NegPiOver4),
+			(pauli_string!("IX"), CliffordPauliAngle::NegPiOver4),
+		]
+		.map(|(string, angle)| PauliExp { string, angle });
+		let swap: Vec<_> = [false, true, false]
+			.into_iter()
+			.flat_map(|flipped| {
+				cnot.iter().map(move |clifford| {
+					let mut clifford = clifford.clone();
+					if flipped {
+						let (q0, q1) = (clifford.string.get(0), clifford.string.get(1));
+						clifford.string.set(0, q1);
+						clifford.string.set(1, q0);
+					}
+					clifford
+				})
+			})
+			.collect();
+
+		let cnot_tableau = tableau(&cnot);
+		let decomposition = cnot_tableau.decompose_exact(&database).unwrap();
+		assert_eq!(quarter_turns(&decomposition), 3);
+		assert_eq!(tableau(&decomposition), cnot_tableau);
+
+		let swap_tableau = tableau(&swap);
+		let decomposition = swap_tableau.decompose_exact(&database).unwrap();
+		assert_eq!(quarter_turns(&decomposition), 3);
+		assert_eq!(tableau(&decomposition), swap_tableau);
+
+		let three_qubits = tableau(&[PauliExp {
+			string: pauli_string!("XXX"),
+			angle: CliffordPauliAngle::PiOver4,
+		}]);
+		assert!(three_qubits.decompose_exact(&database).is_none());
+	}
+
+	#[test]
+	fn exact_decompositions_agree() {
+		let mut rng = ChaCha8Rng::seed_from_u64(23);
+		let gate_size = NonZeroEvenUsize::new(2).unwrap();
+		let database = CliffordDatabase::new(2, gate_size);
+
+		for _ in 0..50 {
+			// Two qubits somewhere in the middle
+			let cliffords: Vec<_> = random_cliffords(2, 12, &mut rng)
+				.into_iter()
+				.map(|mut clifford| {
+					let (q0, q1) = (clifford.string.get(0), clifford.string.get(1));
+					clifford.string = PauliString::default();
+					clifford.string.set(5, q0);
+					clifford.string.set(2, q1);
+					clifford
+				})
+				.collect();
+			let expected = tableau(&cliffords);
+
+			let exact = expected.decompose_exact(&database).unwrap();
+			assert_eq!(tableau(&exact), expected);
+			let heuristic = expected.clone().decompose(gate_size, None);
+			assert!(exact.len() <= heuristic.len());
+			assert!(quarter_turns(&exact) <= quarter_turns(&heuristic));
+		}
+	}
+
+	#[test]
+	fn decompose_with_database() {
+		let mut rng = ChaCha8Rng::seed_from_u64(5);
+		let gate_size = NonZeroEvenUsize::new(2).unwrap();
+		let database = CliffordDatabase::new(2, gate_size);
+
+		for _ in 0..20 {
+			let expected = tableau(&random_cliffords(5, 40, &mut rng));
+			let finished = expected.clone().decompose_with_database(&database);
+			assert_eq!(tableau(&finished), expected);
+			assert!(finished.len() <= expected.clone().decompose(gate_size, None).len());
+		}
+	}
+
+	#[test]
+	fn peephole() {
+		let mut rng = ChaCha8Rng::seed_from_u64(7);
+		let database = CliffordDatabase::new(2, NonZeroEvenUsize::new(4).unwrap());
+
+		// Undoing itself vanishes
+		let cliffords = random_cliffords(2, 10, &mut rng);
+		let undone: Vec<_> = cliffords
+			.iter()
+			.cloned()
+			.chain(inverted(cliffords.clone()))
+			.collect();
+		assert!(database.peephole(&undone).is_empty());
+
+		for _ in 0..20 {
+			let cliffords = random_cliffords(4, 40, &mut rng);
+			let optimised = database.peephole(&cliffords);
+			assert!(optimised.len() <= cliffords.len());
+			assert_eq!(tableau(&optimised), tableau(&cliffords));
+		}
+	}
+
+	#[test]
+	fn write_and_read() {
+		let database = CliffordDatabase::new(2, NonZeroEvenUsize::new(2).unwrap());
+		let mut bytes = Vec::new();
+		database.write(&mut bytes).unwrap();
+		assert_eq!(bytes.len(), 22 + 9 * 720);
+		assert_eq!(
+			CliffordDatabase::read(&mut bytes.as_slice()).unwrap(),
+			database
+		);
+
+		let mut oversized = bytes.clone();
+		oversized[14..22].copy_from_slice(&u64::MAX.to_le_bytes());
+		let error = CliffordDatabase::read(&mut oversized.as_slice()).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+		bytes[0] = b'X';
+		let error = CliffordDatabase::read(&mut bytes.as_slice()).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn tableau_counts() {
+		assert_eq!(tableau_count(1), 6);
+		assert_eq!(tableau_count(2), 720);
+		assert_eq!(tableau_count(3), 1_451_520);
+	}
+
+	#[test]
+	fn three_qubits_few_steps() {
+		let gate_size = NonZeroEvenUsize::new(2).unwrap();
+		let database = CliffordDatabase::with_max_steps(3, gate_size, 3);
+		// 9 strings on one qubit and 27 on two, each merged twice is a Pauli
+		assert_eq!(database.generators.len(), 36);
+		assert!(database.len() > 36 * 35);
+
+		let mut rng = ChaCha8Rng::seed_from_u64(11);
+		for _ in 0..100 {
+			let count = rng.random_range(1..=3);
+			let cliffords: Vec<_> = random_cliffords(3, count, &mut rng)
+				.into_iter()
+				.map(|mut clifford| {
+					// At most two letters, so each is one of the generators
+					clifford.string.set(rng.random_range(0..3), PauliLetter::I);
+					clifford.angle = CliffordPauliAngle::PiOver4;
+					clifford
+				})
+				.filter(|clifford| !clifford.string.is_empty())
+				.collect();
+			let expected = tableau(&cliffords);
+
+			let exact = expected.decompose_exact(&database).unwrap();
+			assert_eq!(tableau(&exact), expected);
+			assert!(quarter_turns(&exact) <= cliffords.len());
+		}
+
+		// Cycling three qubits takes more than three rotations
+		let cycle = CliffordTableau::from_rows(
+			vec![PauliString::x(1), PauliString::x(2), PauliString::x(0)],
+			vec![PauliString::z(1), PauliString::z(2), PauliString::z(0)],
+			Bits::new(),
+			Bits::new(),
+		);
+		assert!(cycle.decompose_exact(&database).is_none());
+	}
+
+	#[test]
+	#[ignore = "takes long without optimisations"]
+	fn three_qubits() {
+		let database = CliffordDatabase::new(3, NonZeroEvenUsize::new(2).unwrap());
+		assert_eq!(database.len(), 1_451_520);
+
+		let mut rng = ChaCha8Rng::seed_from_u64(3);
+		for _ in 0..20 {
+			let expected = tableau(&random_cliffords(3, 30, &mut rng));
+			let exact = expected.decompose_exact(&database).unwrap();
+			assert_eq!(tableau(&exact), expected);
+		}
+	}
+}
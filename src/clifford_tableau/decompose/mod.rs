@@ -1,3 +1,4 @@
+mod database;
 mod delicate_solver;
 mod routing_help;
 mod simple_solver;
@@ -6,8 +7,9 @@ use crate::{
 	clifford_tableau::{CliffordTableau, decompose::routing_help::handle_target},
 	connectivity::{Connectivity, hypergraph::HyperEdgeIndex},
 	misc::{NonZeroEvenUsize, enforced_fixed_steiner_tree},
-	pauli::{CliffordPauliAngle, PauliExp, PauliLetter, PauliString},
+	pauli::{CliffordPauliAngle, Negate, PauliExp, PauliLetter, PauliString},
 };
+pub use database::{CliffordDatabase, MAX_DATABASE_QUBITS};
 use delicate_solver::{delicate_solver, fastest_delicate};
 use simple_solver::{fastest, simple_solver};
 
@@ -29,10 +31,23 @@ impl CliffordTableau {
 	) -> Vec<PauliExp<CliffordPauliAngle>> {
 		match connectivity {
 			Some(connectivity) => self.decompose_with_connectivity(gate_size, connectivity),
-			_ => self.decompose_full_connectivity(gate_size),
+			_ => self.decompose_full_connectivity(gate_size, None),
 		}
 	}
 
+	/// # Decompose with database
+	///
+	/// Decomposes the tableau like [CliffordTableau::decompose] with full
+	/// connectivity and the gate size of the database, but the last qubits
+	/// are finished optimally once few enough are left, see
+	/// [CliffordDatabase].
+	pub fn decompose_with_database(
+		self,
+		database: &CliffordDatabase,
+	) -> Vec<PauliExp<CliffordPauliAngle>> {
+		self.decompose_full_connectivity(database.gate_size(), Some(database))
+	}
+
 	fn decompose_with_connectivity(
 		mut self,
 		gate_size: NonZeroEvenUsize,
@@ -276,11 +291,13 @@ impl CliffordTableau {
 	fn decompose_full_connectivity(
 		mut self,
 		gate_size: NonZeroEvenUsize,
+		database: Option<&CliffordDatabase>,
 	) -> Vec<PauliExp<CliffordPauliAngle>> {
 		let mut decomposition: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
 		let mut dirty_qubits: Vec<usize> = (0..self.size()).collect();
+		let finish_at = database.map(|database| database.n_qubits()).unwrap_or(0);
 
-		while dirty_qubits.len() >= gate_size.as_value() {
+		while dirty_qubits.len() >= gate_size.as_value() && dirty_qubits.len() > finish_at {
 			let (qubit, letter) = fastest(&self, &dirty_qubits, gate_size).unwrap();
 
 			match letter {
@@ -360,7 +377,7 @@ impl CliffordTableau {
 		}
 
 		// then for remaining use delicate solver
-		while !dirty_qubits.is_empty() {
+		while dirty_qubits.len() > finish_at {
 			let (qubit, letter) = fastest_delicate(&self, &dirty_qubits).unwrap();
 
 			match letter {
@@ -435,6 +452,20 @@ impl CliffordTableau {
 			dirty_qubits.retain(|q| *q != qubit);
 		}
 
+		// and finish the rest optimally
+		if let Some(database) = database.filter(|_| !dirty_qubits.is_empty()) {
+			dirty_qubits.sort();
+			let strings = database
+				.reduction(&self, &dirty_qubits)
+				.expect("The rows of dirty qubits only act on dirty qubits");
+			for mut clifford in strings {
+				self.merge_clifford(clifford.clone());
+				// the decomposition has the reverse operation
+				clifford.angle.negate();
+				decomposition.push(clifford);
+			}
+		}
+
 		// Fix signs
 		let last = self
 			.x_signs
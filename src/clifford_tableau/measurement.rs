@@ -0,0 +1,220 @@
+use bits::Bits;
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	partition::diagonalise,
+	pauli::{CliffordPauliAngle, PauliAngle, PauliExp, PauliLetter, PauliString},
+};
+
+/// The outcome of measuring `qubit` after the full Clifford, from the
+/// outcomes after the remaining one. Outcomes are `true` for the $-1$
+/// eigenvalue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutcomeMap {
+	pub qubit: usize,
+	/// Flips the outcome, the Pauli frame.
+	pub flip: bool,
+	/// The qubits whose outcomes are added up, the classical permutation.
+	pub parity: Bits,
+}
+
+/// A Clifford $U$ before a measurement split into $U=FV$, where only $V$ has
+/// to be applied and $F$ is done on the outcomes.
+#[derive(Debug, Clone)]
+pub struct MeasurementReduction {
+	/// $V$, for example for [CliffordTableau::decompose].
+	pub remaining: CliffordTableau,
+	/// $V$ as exponentials, index 0 first.
+	pub cliffords: Vec<PauliExp<CliffordPauliAngle>>,
+	/// One for every measured qubit, in the order they were given.
+	pub outcomes: Vec<OutcomeMap>,
+}
+
+impl MeasurementReduction {
+	/// The qubits that have to be measured after $V$, which are all that
+	/// show up in a parity.
+	pub fn needed_qubits(&self) -> Bits {
+		let mut needed = Bits::new();
+		for outcome in self.outcomes.iter() {
+			needed |= &outcome.parity;
+		}
+		needed
+	}
+
+	/// Turns the outcomes after $V$ into the outcomes after $U$, bit `i` for
+	/// qubit `i`.
+	pub fn correct(&self, outcomes: &Bits) -> Bits {
+		let mut corrected = Bits::new();
+		for outcome in self.outcomes.iter() {
+			let parity = (&outcome.parity & outcomes).count_ones() % 2 == 1;
+			corrected.set(outcome.qubit, parity ^ outcome.flip);
+		}
+		corrected
+	}
+}
+
+impl CliffordTableau {
+	/// # Reduce for measurement
+	///
+	/// Splits the Clifford $U$ of the tableau, which is followed by measuring
+	/// `measured` in the computational basis, into $U=FV$ where $F$ maps
+	/// strings of $Z$ to strings of $Z$. Up to phases that a measurement does
+	/// not see, $F$ only permutes basis states like CNOTs and $X$ gates, so it
+	/// can be applied to the outcomes instead, see [MeasurementReduction].
+	///
+	/// The measured observables $U^\dagger Z_iU$ commute, so $V$ is the
+	/// Clifford that [diagonalise] finds for them, with at most one
+	/// $\frac{\pi}{4}$ rotation per measured qubit. Then
+	/// $F^\dagger Z_iF=VU^\dagger Z_iUV^\dagger$ is a string of $Z$.
+	pub fn reduce_for_measurement(&self, measured: &[usize]) -> MeasurementReduction {
+		let inverse = self.inverse();
+		let observables: Vec<PauliExp<PauliAngle>> = measured
+			.iter()
+			.map(|qubit| PauliExp {
+				string: inverse.conjugate(&PauliString::z(*qubit)).1,
+				angle: PauliAngle::MultipleOfPi(0.0),
+			})
+			.collect();
+		let diagonalisation =
+			diagonalise(&observables).expect("Measured observables always commute");
+
+		let frame = diagonalisation.tableau.inverse().compose(self).inverse();
+		let outcomes = measured
+			.iter()
+			.map(|qubit| {
+				let (flip, string) = frame.conjugate(&PauliString::z(*qubit));
+				let mut parity = Bits::new();
+				for (i, letter) in string.letters() {
+					debug_assert_eq!(letter, PauliLetter::Z);
+					parity.set(i, true);
+				}
+				OutcomeMap {
+					qubit: *qubit,
+					flip,
+					parity,
+				}
+			})
+			.collect();
+
+		MeasurementReduction {
+			remaining: diagonalisation.tableau,
+			cliffords: diagonalisation.cliffords,
+			outcomes,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		pauli_string,
+		simulate::{ApplyExp, apply_pauli_rotation},
+		test_utils::{random_cliffords, random_string, tableau},
+	};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+	use simulator::Statevector;
+
+	/// The probabilities of the outcomes of `measured`, indexed by the
+	/// outcomes as bits.
+	fn distribution(
+		state: &Statevector<f64>,
+		measured: &[usize],
+		map: impl Fn(&Bits) -> Bits,
+	) -> Vec<f64> {
+		let mut probabilities = vec![0.0; 1 << measured.len()];
+		for (index, value) in state.values().iter().enumerate() {
+			let mut outcomes = Bits::new();
+			for qubit in 0..state.n_qubits() {
+				outcomes.set(qubit, (index >> qubit) & 1 == 1);
+			}
+			let outcomes = map(&outcomes);
+			let key = measured
+				.iter()
+				.enumerate()
+				.filter(|(_, qubit)| outcomes.get(**qubit))
+				.map(|(i, _)| 1 << i)
+				.sum::<usize>();
+			probabilities[key] += value.abs_squared();
+		}
+		probabilities
+	}
+
+	#[test]
+	fn reduced_measurements_agree() {
+		let mut rng = ChaCha8Rng::seed_from_u64(8);
+		let n_qubits = 5;
+
+		let mut initial = Statevector::new(n_qubits);
+		for _ in 0..30 {
+			initial.apply_exp(&PauliExp {
+				string: random_string(n_qubits, &mut rng),
+				angle: PauliAngle::MultipleOfPi(rng.random()),
+			});
+		}
+
+		for measured in [vec![0, 1, 2, 3, 4], vec![3, 1]] {
+			let cliffords = random_cliffords(n_qubits, 25, &mut rng);
+			let tableau = tableau(&cliffords);
+
+			let reduction = tableau.reduce_for_measurement(&measured);
+			assert!(reduction.cliffords.len() <= measured.len());
+
+			let mut full = initial.clone();
+			let mut reduced = initial.clone();
+			for clifford in cliffords.iter() {
+				apply_pauli_rotation(
+					&mut full,
+					&clifford.string,
+					clifford.angle.as_multiple_of_pi(),
+				);
+			}
+			for clifford in reduction.cliffords.iter() {
+				apply_pauli_rotation(
+					&mut reduced,
+					&clifford.string,
+					clifford.angle.as_multiple_of_pi(),
+				);
+			}
+
+			let expected = distribution(&full, &measured, |outcomes| outcomes.clone());
+			let corrected =
+				distribution(&reduced, &measured, |outcomes| reduction.correct(outcomes));
+			for (a, b) in expected.iter().zip(corrected.iter()) {
+				assert!((a - b).abs() < 1e-10, "{expected:?} {corrected:?}");
+			}
+		}
+
+		// A CNOT, an $S^\dagger$ and an $X$ are done completely on the outcomes
+		let mut tableau = CliffordTableau::id();
+		for (string, angle) in [
+			(pauli_string!("ZX"), CliffordPauliAngle::PiOver4),
+			(pauli_string!("Z"), CliffordPauliAngle::NegPiOver4),
+			(pauli_string!("IX"), CliffordPauliAngle::NegPiOver4),
+			(pauli_string!("IZ"), CliffordPauliAngle::PiOver4),
+			(pauli_string!("X"), CliffordPauliAngle::PiOver2),
+		] {
+			tableau.merge_clifford(PauliExp { string, angle });
+		}
+		let reduction = tableau.reduce_for_measurement(&[0, 1]);
+		assert!(reduction.cliffords.is_empty());
+		let mut both = Bits::with_one(0);
+		both.set(1, true);
+		assert_eq!(
+			reduction.outcomes,
+			vec![
+				OutcomeMap {
+					qubit: 0,
+					flip: true,
+					parity: Bits::with_one(0),
+				},
+				OutcomeMap {
+					qubit: 1,
+					flip: false,
+					parity: both,
+				},
+			]
+		);
+	}
+}
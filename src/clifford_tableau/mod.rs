@@ -1,8 +1,14 @@
 mod decompose;
+mod measurement;
+mod serialize;
 
 use bits::Bits;
 
-use crate::pauli::{CliffordPauliAngle, PauliExp, PauliString};
+use crate::pauli::{CliffordPauliAngle, PauliExp, PauliLetter, PauliString};
+
+pub use decompose::{CliffordDatabase, MAX_DATABASE_QUBITS};
+pub use measurement::{MeasurementReduction, OutcomeMap};
+pub use serialize::TableauParseError;
 
 #[derive(Clone, Debug, Default, Eq)]
 pub struct CliffordTableau {
@@ -21,6 +27,23 @@ impl CliffordTableau {
 		self.x.len().max(self.z.len())
 	}
 
+	/// The tableau with the rows `x[i]`$=UX_iU^\dagger$ and `z[i]`$=UZ_iU^\dagger$,
+	/// which have to be a valid Clifford.
+	pub(crate) fn from_rows(
+		x: Vec<PauliString>,
+		z: Vec<PauliString>,
+		x_signs: Bits,
+		z_signs: Bits,
+	) -> Self {
+		debug_assert_eq!(x.len(), z.len());
+		CliffordTableau {
+			x,
+			z,
+			x_signs,
+			z_signs,
+		}
+	}
+
 	pub fn id_with_capacity(capacity: usize) -> Self {
 		CliffordTableau {
 			x: (0..capacity)
@@ -149,6 +172,152 @@ impl CliffordTableau {
 		}
 	}
 
+	/// # Prepend Clifford
+	///
+	/// Merges a Clifford Pauli exponential in front of the tableau, as if it
+	/// was the first Clifford merged. Only the rows of the qubits of the
+	/// exponential change, they become the old tableau conjugated with the
+	/// rows of the exponential.
+	pub fn prepend_clifford(&mut self, clifford: PauliExp<CliffordPauliAngle>) {
+		let space_needed = clifford.string.size().saturating_sub(self.x.len());
+		for _ in 0..space_needed {
+			let i = self.x.len();
+			self.x.push(PauliString::x(i));
+			self.z.push(PauliString::z(i));
+		}
+
+		let targets = clifford.string.targets();
+		let mut first = CliffordTableau::id();
+		first.merge_clifford(clifford);
+
+		// All rows have to be conjugated with the old tableau
+		let rows: Vec<_> = targets
+			.iter()
+			.map(|i| {
+				let (x_sign, x) = self.conjugate(&first.get_x_row(*i));
+				let (z_sign, z) = self.conjugate(&first.get_z_row(*i));
+				(
+					x_sign ^ first.x_signs.get(*i),
+					x,
+					z_sign ^ first.z_signs.get(*i),
+					z,
+				)
+			})
+			.collect();
+		for (i, (x_sign, x, z_sign, z)) in targets.into_iter().zip(rows) {
+			self.x[i] = x;
+			self.x_signs.set(i, x_sign);
+			self.z[i] = z;
+			self.z_signs.set(i, z_sign);
+		}
+	}
+
+	/// # Conjugate
+	///
+	/// Maps `string` $P$ through the Clifford $U$ of the tableau, $UPU^\dagger$,
+	/// and returns it as a sign, `true` for a negative one, and a string.
+	///
+	/// Per qubit the letters are the rows of the tableau, with
+	/// $UYU^\dagger=iUXU^\dagger UZU^\dagger$.
+	pub fn conjugate(&self, string: &PauliString) -> (bool, PauliString) {
+		let mut result = PauliString::default();
+		let mut phase = 0;
+		for (i, letter) in string.letters() {
+			let mut rows = Vec::new();
+			if matches!(letter, PauliLetter::X | PauliLetter::Y) {
+				rows.push((self.x_signs.get(i), self.get_x_row(i)));
+			}
+			if matches!(letter, PauliLetter::Z | PauliLetter::Y) {
+				rows.push((self.z_signs.get(i), self.get_z_row(i)));
+			}
+			if letter == PauliLetter::Y {
+				phase += 1;
+			}
+
+			for (sign, row) in rows {
+				phase += result.multiply(&row) as usize;
+				if sign {
+					phase += 2;
+				}
+			}
+		}
+		debug_assert!(phase.is_multiple_of(2));
+
+		(phase % 4 == 2, result)
+	}
+
+	/// # Compose
+	///
+	/// The tableau of `self` followed by `other`, the same as merging the
+	/// Cliffords of `other` into `self`.
+	pub fn compose(&self, other: &Self) -> Self {
+		let size = self.size().max(other.size());
+		let mut result = Self::id_with_capacity(size);
+		for i in 0..size {
+			let (sign, row) = other.conjugate(&self.get_x_row(i));
+			result.x[i] = row;
+			result.x_signs.set(i, sign ^ self.x_signs.get(i));
+
+			let (sign, row) = other.conjugate(&self.get_z_row(i));
+			result.z[i] = row;
+			result.z_signs.set(i, sign ^ self.z_signs.get(i));
+		}
+		result
+	}
+
+	/// # Inverse
+	///
+	/// The tableau of $U^\dagger$, so composing it with `self` in either order
+	/// gives the identity.
+	///
+	/// $U^\dagger X_jU$ anticommutes with $Z_i$ exactly when $X_j$
+	/// anticommutes with the row $UZ_iU^\dagger$, and the same for $X_i$ and
+	/// $Z_j$, which gives the letters. The sign is the one [Self::conjugate]
+	/// gives for them.
+	pub fn inverse(&self) -> Self {
+		let size = self.size();
+		let mut result = Self::id_with_capacity(size);
+		let x_rows: Vec<PauliString> = (0..size).map(|i| self.get_x_row(i)).collect();
+		let z_rows: Vec<PauliString> = (0..size).map(|i| self.get_z_row(i)).collect();
+
+		for j in 0..size {
+			// Which rows have an $X$ and which a $Z$ part on qubit `j`
+			let letter = |x: bool, z: bool| match (x, z) {
+				(false, false) => PauliLetter::I,
+				(true, false) => PauliLetter::X,
+				(false, true) => PauliLetter::Z,
+				(true, true) => PauliLetter::Y,
+			};
+			let has_x = |row: &PauliString| matches!(row.get(j), PauliLetter::X | PauliLetter::Y);
+			let has_z = |row: &PauliString| matches!(row.get(j), PauliLetter::Z | PauliLetter::Y);
+
+			let mut x = PauliString::default();
+			let mut z = PauliString::default();
+			for i in 0..size {
+				x.set(i, letter(has_z(&z_rows[i]), has_z(&x_rows[i])));
+				z.set(i, letter(has_x(&z_rows[i]), has_x(&x_rows[i])));
+			}
+
+			let (sign, row) = self.conjugate(&x);
+			debug_assert_eq!(row, PauliString::x(j));
+			result.x_signs.set(j, sign);
+			result.x[j] = x;
+
+			let (sign, row) = self.conjugate(&z);
+			debug_assert_eq!(row, PauliString::z(j));
+			result.z_signs.set(j, sign);
+			result.z[j] = z;
+		}
+		result
+	}
+
+	/// # Adjoint
+	///
+	/// $U^\dagger$, which is the [Self::inverse] since Cliffords are unitary.
+	pub fn adjoint(&self) -> Self {
+		self.inverse()
+	}
+
 	pub fn get_x_row(&self, index: usize) -> PauliString {
 		self.x.get(index).cloned().unwrap_or(PauliString::x(index))
 	}
@@ -241,3 +410,102 @@ impl PartialEq for CliffordTableau {
 		true
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		pauli::Negate,
+		simulate::apply_pauli_rotation,
+		test_utils::{random_cliffords, random_string, tableau},
+	};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+	use simulator::{Complex, Statevector};
+
+	fn apply_cliffords(state: &mut Statevector<f64>, cliffords: &[PauliExp<CliffordPauliAngle>]) {
+		for clifford in cliffords.iter() {
+			apply_pauli_rotation(state, &clifford.string, clifford.angle.as_multiple_of_pi());
+		}
+	}
+
+	#[test]
+	fn conjugate() {
+		let mut rng = ChaCha8Rng::seed_from_u64(6);
+		let n_qubits = 4;
+		let cliffords = random_cliffords(n_qubits, 20, &mut rng);
+		let tableau = tableau(&cliffords);
+
+		let mut initial = Statevector::new(n_qubits);
+		for value in initial.values.iter_mut() {
+			*value = Complex {
+				re: rng.random::<f64>() - 0.5,
+				im: rng.random::<f64>() - 0.5,
+			};
+		}
+
+		for _ in 0..20 {
+			let string = random_string(n_qubits, &mut rng);
+			let (sign, conjugated) = tableau.conjugate(&string);
+
+			// $UP|\psi\rangle=\pm P'U|\psi\rangle$, both with the phase of
+			// $e^{i\frac{\pi}{2}P}=iP$
+			let mut left = initial.clone();
+			apply_pauli_rotation(&mut left, &string, 0.5);
+			apply_cliffords(&mut left, &cliffords);
+			let mut right = initial.clone();
+			apply_cliffords(&mut right, &cliffords);
+			apply_pauli_rotation(&mut right, &conjugated, 0.5);
+
+			let factor = if sign { -1.0 } else { 1.0 };
+			for (a, b) in left.values().iter().zip(right.values().iter()) {
+				assert!((a.re - factor * b.re).abs() < 1e-10);
+				assert!((a.im - factor * b.im).abs() < 1e-10);
+			}
+		}
+	}
+
+	#[test]
+	fn compose_and_inverse() {
+		let mut rng = ChaCha8Rng::seed_from_u64(7);
+		let first = random_cliffords(5, 15, &mut rng);
+		let second = random_cliffords(6, 15, &mut rng);
+		let a = tableau(&first);
+		let b = tableau(&second);
+
+		let both: Vec<_> = first.iter().chain(second.iter()).cloned().collect();
+		assert_eq!(a.compose(&b), tableau(&both));
+
+		let inverse = a.inverse();
+		let reversed: Vec<_> = first
+			.iter()
+			.rev()
+			.map(|clifford| {
+				let mut clifford = clifford.clone();
+				clifford.angle.negate();
+				clifford
+			})
+			.collect();
+		assert_eq!(inverse, tableau(&reversed));
+		assert_eq!(a.adjoint(), inverse);
+		assert!(a.compose(&inverse).is_identity());
+		assert!(inverse.compose(&a).is_identity());
+		assert!(CliffordTableau::id().inverse().is_identity());
+	}
+
+	#[test]
+	fn prepend() {
+		let mut rng = ChaCha8Rng::seed_from_u64(8);
+		let cliffords = random_cliffords(5, 15, &mut rng);
+		let mut prepended = CliffordTableau::id();
+		for clifford in cliffords.iter().rev() {
+			prepended.prepend_clifford(clifford.clone());
+		}
+		assert_eq!(prepended, tableau(&cliffords));
+
+		let first = random_cliffords(7, 1, &mut rng);
+		let both: Vec<_> = first.iter().chain(cliffords.iter()).cloned().collect();
+		prepended.prepend_clifford(first[0].clone());
+		assert_eq!(prepended, tableau(&both));
+	}
+}
@@ -0,0 +1,420 @@
+//! Reading and writing tableaux as the text that stim prints and in a
+//! compact binary form.
+
+use std::{error::Error, fmt};
+
+use bits::Bits;
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{PauliLetter, PauliString},
+};
+
+const MAGIC: &[u8; 4] = b"CTAB";
+const VERSION: u8 = 1;
+/// The magic, the version and the number of qubits.
+const HEADER_LEN: usize = 13;
+
+/// The reason why a tableau could not be read.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TableauParseError {
+	/// The first line is not `+-` followed by `xz-` for every qubit.
+	InvalidHeader,
+	/// A line is not `|` followed by a pair of characters per qubit.
+	InvalidLine { line: usize },
+	/// A sign is something else than `+` or `-`.
+	InvalidSign { line: usize, found: char },
+	/// A letter is something else than `_`, `X`, `Y` or `Z`.
+	InvalidLetter { line: usize, found: char },
+	/// There are not as many rows as qubits.
+	WrongRowCount { expected: usize, found: usize },
+	/// The binary form does not start with the magic bytes.
+	InvalidMagic,
+	/// The binary form has an unknown version.
+	UnsupportedVersion(u8),
+	/// The binary form is shorter or longer than the number of qubits needs.
+	WrongLength { expected: usize, found: usize },
+	/// The rows do not have the commutation relations of $X_i$ and $Z_i$.
+	NotAClifford,
+}
+
+impl fmt::Display for TableauParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			TableauParseError::InvalidHeader => {
+				write!(f, "expected a header like '+-xz-xz-'")
+			}
+			TableauParseError::InvalidLine { line } => {
+				write!(f, "line {line} is not '|' followed by pairs of characters")
+			}
+			TableauParseError::InvalidSign { line, found } => {
+				write!(f, "line {line}: '{found}' is not a sign (+-)")
+			}
+			TableauParseError::InvalidLetter { line, found } => {
+				write!(f, "line {line}: '{found}' is not a Pauli letter (_XYZ)")
+			}
+			TableauParseError::WrongRowCount { expected, found } => {
+				write!(f, "expected {expected} rows, found {found}")
+			}
+			TableauParseError::InvalidMagic => write!(f, "not a binary tableau"),
+			TableauParseError::UnsupportedVersion(version) => {
+				write!(f, "unsupported binary tableau version {version}")
+			}
+			TableauParseError::WrongLength { expected, found } => {
+				write!(f, "expected {expected} bytes, found {found}")
+			}
+			TableauParseError::NotAClifford => write!(f, "the rows are not a Clifford"),
+		}
+	}
+}
+
+impl Error for TableauParseError {}
+
+impl CliffordTableau {
+	/// # To stim
+	///
+	/// The tableau as stim prints it. Column pair `k` holds the images of
+	/// $X_k$ and $Z_k$, first their signs and then a row per qubit, where `_`
+	/// is the identity. A CNOT is
+	///
+	/// ```text
+	/// +-xz-xz-
+	/// | ++ ++
+	/// | XZ _Z
+	/// | X_ XZ
+	/// ```
+	pub fn to_stim(&self) -> String {
+		let n = self.size();
+		let mut text = String::from("+-");
+		for _ in 0..n {
+			text.push_str("xz-");
+		}
+
+		text.push_str("\n|");
+		for k in 0..n {
+			text.push(' ');
+			text.push(sign(self.x_signs.get(k)));
+			text.push(sign(self.z_signs.get(k)));
+		}
+
+		for qubit in 0..n {
+			text.push_str("\n|");
+			for k in 0..n {
+				text.push(' ');
+				text.push(letter(self.x[k].get(qubit)));
+				text.push(letter(self.z[k].get(qubit)));
+			}
+		}
+
+		text
+	}
+
+	/// # From stim
+	///
+	/// Reads the text of [CliffordTableau::to_stim]. Surrounding whitespace
+	/// is ignored.
+	pub fn from_stim(text: &str) -> Result<Self, TableauParseError> {
+		let mut lines = text.trim().lines().map(str::trim);
+
+		let header = lines.next().ok_or(TableauParseError::InvalidHeader)?;
+		let n = header
+			.strip_prefix("+-")
+			.filter(|columns| {
+				columns.len().is_multiple_of(3) && columns.as_bytes().chunks(3).all(|c| c == b"xz-")
+			})
+			.map(|columns| columns.len() / 3)
+			.ok_or(TableauParseError::InvalidHeader)?;
+
+		let pairs =
+			|line: usize, text: Option<&str>| -> Result<Vec<(char, char)>, TableauParseError> {
+				let pairs: Vec<(char, char)> = text
+					.and_then(|text| text.strip_prefix('|'))
+					.ok_or(TableauParseError::InvalidLine { line })?
+					.split_whitespace()
+					.map(|pair| {
+						let mut chars = pair.chars();
+						match (chars.next(), chars.next(), chars.next()) {
+							(Some(a), Some(b), None) => Ok((a, b)),
+							_ => Err(TableauParseError::InvalidLine { line }),
+						}
+					})
+					.collect::<Result<_, _>>()?;
+				match pairs.len() == n {
+					true => Ok(pairs),
+					false => Err(TableauParseError::InvalidLine { line }),
+				}
+			};
+
+		let mut tableau = CliffordTableau {
+			x: vec![PauliString::id_with_capacity(n); n],
+			z: vec![PauliString::id_with_capacity(n); n],
+			x_signs: Bits::with_capacity(n),
+			z_signs: Bits::with_capacity(n),
+		};
+
+		for (k, (x, z)) in pairs(2, lines.next())?.into_iter().enumerate() {
+			tableau.x_signs.set(k, parse_sign(x, 2)?);
+			tableau.z_signs.set(k, parse_sign(z, 2)?);
+		}
+
+		let rows: Vec<&str> = lines.collect();
+		if rows.len() != n {
+			return Err(TableauParseError::WrongRowCount {
+				expected: n,
+				found: rows.len(),
+			});
+		}
+		for (qubit, row) in rows.into_iter().enumerate() {
+			let line = qubit + 3;
+			for (k, (x, z)) in pairs(line, Some(row))?.into_iter().enumerate() {
+				tableau.x[k].set(qubit, parse_letter(x, line)?);
+				tableau.z[k].set(qubit, parse_letter(z, line)?);
+			}
+		}
+
+		tableau.validate()
+	}
+
+	/// # To bytes
+	///
+	/// A compact binary form: `CTAB`, a version byte, the number of qubits
+	/// $n$ as a little endian `u64` and then $2n(2n+1)$ bits packed with
+	/// [Bits::to_bytes]. These are the rows $UX_0U^\dagger$ to
+	/// $UZ_{n-1}U^\dagger$ with $2n$ bits each, the x bits first, followed by
+	/// the signs of all rows in the same order.
+	pub fn to_bytes(&self) -> Vec<u8> {
+		let n = self.size();
+		let mut bits = Bits::with_capacity(2 * n * (2 * n + 1));
+		for (row, string) in self.x.iter().chain(self.z.iter()).enumerate() {
+			for (qubit, letter) in string.letters() {
+				let offset = row * 2 * n;
+				bits.set(
+					offset + qubit,
+					matches!(letter, PauliLetter::X | PauliLetter::Y),
+				);
+				bits.set(
+					offset + n + qubit,
+					matches!(letter, PauliLetter::Z | PauliLetter::Y),
+				);
+			}
+		}
+		for k in 0..n {
+			bits.set(4 * n * n + k, self.x_signs.get(k));
+			bits.set(4 * n * n + n + k, self.z_signs.get(k));
+		}
+
+		let mut bytes = Vec::with_capacity(HEADER_LEN + (2 * n * (2 * n + 1)).div_ceil(8));
+		bytes.extend_from_slice(MAGIC);
+		bytes.push(VERSION);
+		bytes.extend_from_slice(&(n as u64).to_le_bytes());
+		bytes.extend(bits.to_bytes(2 * n * (2 * n + 1)));
+		bytes
+	}
+
+	/// # From bytes
+	///
+	/// Reads the binary form of [CliffordTableau::to_bytes].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, TableauParseError> {
+		if bytes.len() < HEADER_LEN {
+			return Err(TableauParseError::WrongLength {
+				expected: HEADER_LEN,
+				found: bytes.len(),
+			});
+		}
+		if bytes[..4] != *MAGIC {
+			return Err(TableauParseError::InvalidMagic);
+		}
+		if bytes[4] != VERSION {
+			return Err(TableauParseError::UnsupportedVersion(bytes[4]));
+		}
+
+		let n = u64::from_le_bytes(bytes[5..HEADER_LEN].try_into().unwrap()) as usize;
+		let expected = n
+			.checked_mul(2)
+			.and_then(|rows| rows.checked_mul(rows + 1))
+			.map(|bits| HEADER_LEN + bits.div_ceil(8))
+			.unwrap_or(usize::MAX);
+		if bytes.len() != expected {
+			return Err(TableauParseError::WrongLength {
+				expected,
+				found: bytes.len(),
+			});
+		}
+
+		let bits = Bits::from_bytes(&bytes[HEADER_LEN..]);
+		let row = |row: usize| {
+			let mut string = PauliString::id_with_capacity(n);
+			for qubit in 0..n {
+				let offset = row * 2 * n;
+				let letter = match (bits.get(offset + qubit), bits.get(offset + n + qubit)) {
+					(true, true) => PauliLetter::Y,
+					(true, false) => PauliLetter::X,
+					(false, true) => PauliLetter::Z,
+					(false, false) => continue,
+				};
+				string.set(qubit, letter);
+			}
+			string
+		};
+
+		CliffordTableau {
+			x: (0..n).map(row).collect(),
+			z: (n..2 * n).map(row).collect(),
+			x_signs: bits.get_range(4 * n * n..4 * n * n + n),
+			z_signs: bits.get_range(4 * n * n + n..4 * n * n + 2 * n),
+		}
+		.validate()
+	}
+
+	/// Checks that the rows commute like $X_i$ and $Z_i$ do.
+	fn validate(self) -> Result<Self, TableauParseError> {
+		let n = self.size();
+		for i in 0..n {
+			for j in i..n {
+				let valid = self.x[i].commutes_with(&self.x[j])
+					&& self.z[i].commutes_with(&self.z[j])
+					&& self.x[i].anticommutes_with(&self.z[j]) == (i == j)
+					&& self.z[i].anticommutes_with(&self.x[j]) == (i == j);
+				if !valid {
+					return Err(TableauParseError::NotAClifford);
+				}
+			}
+		}
+		Ok(self)
+	}
+}
+
+fn sign(negative: bool) -> char {
+	match negative {
+		true => '-',
+		false => '+',
+	}
+}
+
+fn letter(letter: PauliLetter) -> char {
+	match letter {
+		PauliLetter::I => '_',
+		PauliLetter::X => 'X',
+		PauliLetter::Y => 'Y',
+		PauliLetter::Z => 'Z',
+	}
+}
+
+fn parse_sign(found: char, line: usize) -> Result<bool, TableauParseError> {
+	match found {
+		'+' => Ok(false),
+		'-' => Ok(true),
+		_ => Err(TableauParseError::InvalidSign { line, found }),
+	}
+}
+
+fn parse_letter(found: char, line: usize) -> Result<PauliLetter, TableauParseError> {
+	match found {
+		'_' => Ok(PauliLetter::I),
+		'X' => Ok(PauliLetter::X),
+		'Y' => Ok(PauliLetter::Y),
+		'Z' => Ok(PauliLetter::Z),
+		_ => Err(TableauParseError::InvalidLetter { line, found }),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		experiment::random_clifford_tableau,
+		pauli::{CliffordPauliAngle, PauliExp},
+		pauli_string,
+	};
+	use rand::SeedableRng;
+	use rand_chacha::ChaCha8Rng;
+
+	#[test]
+	fn stim_text() {
+		let mut cnot = CliffordTableau::id();
+		for (string, angle) in [
+			(pauli_string!("ZX"), CliffordPauliAngle::PiOver4),
+			(pauli_string!("Z"), CliffordPauliAngle::NegPiOver4),
+			(pauli_string!("IX"), CliffordPauliAngle::NegPiOver4),
+		] {
+			cnot.merge_clifford(PauliExp { string, angle });
+		}
+		let text = "+-xz-xz-\n| ++ ++\n| XZ _Z\n| X_ XZ";
+		assert_eq!(cnot.to_stim(), text);
+		assert_eq!(CliffordTableau::from_stim(text), Ok(cnot));
+
+		let mut y = CliffordTableau::id();
+		y.merge_clifford(PauliExp {
+			string: pauli_string!("Y"),
+			angle: CliffordPauliAngle::PiOver2,
+		});
+		assert_eq!(y.to_stim(), "+-xz-\n| --\n| XZ");
+		assert_eq!(CliffordTableau::from_stim("\n+-xz-\n| --\n| XZ\n"), Ok(y));
+		assert_eq!(CliffordTableau::id().to_stim(), "+-\n|");
+	}
+
+	#[test]
+	fn round_trips() {
+		let mut rng = ChaCha8Rng::seed_from_u64(25);
+		for n_qubits in [1, 2, 5, 40] {
+			let tableau = random_clifford_tableau(n_qubits, &mut rng);
+			assert_eq!(
+				CliffordTableau::from_stim(&tableau.to_stim()),
+				Ok(tableau.clone())
+			);
+			let bytes = tableau.to_bytes();
+			assert_eq!(
+				bytes.len(),
+				13 + (2 * n_qubits * (2 * n_qubits + 1)).div_ceil(8)
+			);
+			assert_eq!(CliffordTableau::from_bytes(&bytes), Ok(tableau));
+		}
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(
+			CliffordTableau::from_stim("+-xz-\n| +*\n| XZ"),
+			Err(TableauParseError::InvalidSign {
+				line: 2,
+				found: '*'
+			})
+		);
+		assert_eq!(
+			CliffordTableau::from_stim("+-xz-\n| ++\n| XA"),
+			Err(TableauParseError::InvalidLetter {
+				line: 3,
+				found: 'A'
+			})
+		);
+		assert_eq!(
+			CliffordTableau::from_stim("+-xz-xz-\n| ++ ++\n| XZ __"),
+			Err(TableauParseError::WrongRowCount {
+				expected: 2,
+				found: 1
+			})
+		);
+		assert_eq!(
+			CliffordTableau::from_stim("+-xz-\n| ++\n| XX"),
+			Err(TableauParseError::NotAClifford)
+		);
+		assert_eq!(
+			CliffordTableau::from_stim("+-xy-\n| ++\n| XZ"),
+			Err(TableauParseError::InvalidHeader)
+		);
+
+		let mut bytes = CliffordTableau::id_with_capacity(2).to_bytes();
+		assert_eq!(
+			CliffordTableau::from_bytes(&bytes[..bytes.len() - 1]),
+			Err(TableauParseError::WrongLength {
+				expected: 16,
+				found: 15
+			})
+		);
+		bytes[0] = b'X';
+		assert_eq!(
+			CliffordTableau::from_bytes(&bytes),
+			Err(TableauParseError::InvalidMagic)
+		);
+	}
+}
@@ -1,6 +1,6 @@
 pub mod measure;
 mod random;
-pub use random::random_exp;
+pub use random::{random_clifford_tableau, random_exp};
 
 use std::{
 	env,
@@ -12,7 +12,9 @@ use std::{
 };
 
 pub use measure::{gate_count, gate_depth, multi_qubit_filter};
+use simulator::NoiseModel;
 
+use crate::simulate::estimated_fidelity;
 use crate::synthesize::synthesize;
 use crate::{
 	clifford_tableau::CliffordTableau,
@@ -28,13 +30,24 @@ pub struct FolderIterator {
 impl Iterator for FolderIterator {
 	type Item = (String, Vec<PauliExp<PauliAngle>>);
 
+	/// Files that can not be read are reported on stderr and skipped.
 	fn next(&mut self) -> Option<Self::Item> {
-		self.paths.next().map(|path| {
-			let path = path.unwrap().path();
+		for path in self.paths.by_ref() {
+			let path = match path {
+				Ok(entry) => entry.path(),
+				Err(err) => {
+					eprintln!("Skipping unreadable directory entry: {err}");
+					continue;
+				}
+			};
 			let name = format!("{}", path.display());
-			let target = PauliExp::read_exp_file(path);
-			(name, target)
-		})
+			match PauliExp::try_read_exp_file(&path) {
+				Ok(target) => return Some((name, target)),
+				Err(err) => eprintln!("Skipping {name}: {err}"),
+			}
+		}
+
+		None
 	}
 }
 
@@ -50,20 +63,63 @@ pub fn from_folder<P: AsRef<Path>>(
 	run_experiment(targets, gate_size, connectivity, output_file);
 }
 
+/// Like [from_folder], with the fidelity columns of [run_noisy_experiment].
+pub fn noisy_from_folder<P: AsRef<Path>>(
+	folder: P,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Arc<Option<Connectivity>>,
+	noise: NoiseModel,
+	output_file: &str,
+) {
+	let paths = fs::read_dir(folder).unwrap();
+	let targets = FolderIterator { paths };
+
+	run_noisy_experiment(targets, gate_size, connectivity, noise, output_file);
+}
+
 pub fn run_experiment<T: Iterator<Item = (String, Vec<PauliExp<PauliAngle>>)> + Send + 'static>(
 	targets: T,
 	gate_size: NonZeroEvenUsize,
 	connectivity: Arc<Option<Connectivity>>,
 	output_file: &str,
+) {
+	run(targets, gate_size, connectivity, None, output_file);
+}
+
+/// Like [run_experiment], with two more columns for the
+/// [estimated_fidelity] of the input and of the output under `noise`. Every
+/// exponential of the input counts as a single gate on all of its qubits.
+pub fn run_noisy_experiment<
+	T: Iterator<Item = (String, Vec<PauliExp<PauliAngle>>)> + Send + 'static,
+>(
+	targets: T,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Arc<Option<Connectivity>>,
+	noise: NoiseModel,
+	output_file: &str,
+) {
+	run(targets, gate_size, connectivity, Some(noise), output_file);
+}
+
+fn run<T: Iterator<Item = (String, Vec<PauliExp<PauliAngle>>)> + Send + 'static>(
+	targets: T,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Arc<Option<Connectivity>>,
+	noise: Option<NoiseModel>,
+	output_file: &str,
 ) {
 	let mut file = File::options()
 		.create_new(true)
 		.write(true)
 		.open(output_file)
 		.expect("Failed to create output file");
+	let fidelity_header = match noise {
+		Some(_) => ",input_fidelity,output_fidelity",
+		None => "",
+	};
 	writeln!(
 		file,
-		"name,input_count,output_count,output_base_count,output_tableau_count,input_depth,output_depth,output_base_depth,output_tableau_depth"
+		"name,input_count,output_count,output_base_count,output_tableau_count,input_depth,output_depth,output_base_depth,output_tableau_depth{fidelity_header}"
 	)
 	.expect("Failed to write to file");
 
@@ -95,11 +151,11 @@ pub fn run_experiment<T: Iterator<Item = (String, Vec<PauliExp<PauliAngle>>)> +
 
 				let input_count = gate_count(&target, multi_qubit_filter);
 				let input_depth = gate_depth(&target, multi_qubit_filter);
+				let input_fidelity = noise.map(|noise| estimated_fidelity(&target, &noise));
 
-				#[cfg(not(feature = "return_ordered"))]
-				let (mut circuit, clifford) = synthesize(target, gate_size, connectivity);
-				#[cfg(feature = "return_ordered")]
-				let (mut circuit, clifford, _) = synthesize(target, gate_size, connectivity);
+				let result = synthesize(target, gate_size, connectivity);
+				let mut circuit = result.circuit;
+				let clifford = result.clifford;
 
 				let output_base_count = gate_count(&circuit, multi_qubit_filter);
 				let output_base_depth = gate_depth(&circuit, multi_qubit_filter);
@@ -120,13 +176,21 @@ pub fn run_experiment<T: Iterator<Item = (String, Vec<PauliExp<PauliAngle>>)> +
 				let output_count = gate_count(&circuit, multi_qubit_filter);
 				let output_depth = gate_depth(&circuit, multi_qubit_filter);
 
+				let fidelities = match (input_fidelity, noise) {
+					(Some(input_fidelity), Some(noise)) => {
+						let output_fidelity = estimated_fidelity(&circuit, &noise);
+						format!(",{input_fidelity},{output_fidelity}")
+					}
+					_ => String::new(),
+				};
+
 				println!(
-					"{name},{input_count},{output_count},{output_base_count},{output_tableau_count},{input_depth},{output_depth},{output_base_depth},{output_tableau_depth}"
+					"{name},{input_count},{output_count},{output_base_count},{output_tableau_count},{input_depth},{output_depth},{output_base_depth},{output_tableau_depth}{fidelities}"
 				);
 				let mut f = file.lock().unwrap();
 				writeln!(
 					f,
-					"{name},{input_count},{output_count},{output_base_count},{output_tableau_count},{input_depth},{output_depth},{output_base_depth},{output_tableau_depth}"
+					"{name},{input_count},{output_count},{output_base_count},{output_tableau_count},{input_depth},{output_depth},{output_base_depth},{output_tableau_depth}{fidelities}"
 				)
 				.expect("Failed towrite to file.");
 			}
@@ -1,6 +1,10 @@
+use bits::Bits;
 use rand::prelude::*;
 
-use crate::pauli::{PauliAngle, PauliExp, PauliLetter, PauliString};
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{PauliAngle, PauliExp, PauliLetter, PauliString},
+};
 
 pub fn random_exp<R: Rng>(max_exp_size: usize, rng: &mut R) -> PauliExp<PauliAngle> {
 	let n_letters = (1_usize..=max_exp_size).choose(rng);
@@ -21,3 +25,220 @@ pub fn random_exp<R: Rng>(max_exp_size: usize, rng: &mut R) -> PauliExp<PauliAng
 		angle: PauliAngle::MultipleOfPi(rng.random()),
 	}
 }
+
+/// # Random Clifford tableau
+///
+/// A uniformly random Clifford on `n_qubits` qubits, following Bravyi and
+/// Maslov, "Hadamard-free circuits expose the structure of the Clifford
+/// group". Every Clifford is $F_1HSF_2P$ with Hadamard-free $F_1$ and $F_2$, a
+/// layer of Hadamards $H$, a qubit permutation $S$ and a Pauli $P$. $H$ and
+/// $S$ come from the quantum Mallows distribution, which weighs them by how
+/// many Cliffords they give, the rest is uniform.
+pub fn random_clifford_tableau<R: Rng>(n_qubits: usize, rng: &mut R) -> CliffordTableau {
+	let n = n_qubits;
+	let (hadamards, permutation) = quantum_mallows(n, rng);
+	let first = hadamard_free(n, rng);
+	let second = hadamard_free(n, rng);
+
+	let mut middle: Vec<Vec<bool>> = (0..2 * n)
+		.map(|row| match row < n {
+			true => second[permutation[row]].clone(),
+			false => second[n + permutation[row - n]].clone(),
+		})
+		.collect();
+	for (qubit, hadamard) in hadamards.into_iter().enumerate() {
+		if hadamard {
+			middle.swap(qubit, n + qubit);
+		}
+	}
+
+	let symplectic = multiply(&first, &middle);
+	let row = |bits: &[bool]| {
+		let mut string = PauliString::id_with_capacity(n);
+		for qubit in 0..n {
+			let letter = match (bits[qubit], bits[n + qubit]) {
+				(true, true) => PauliLetter::Y,
+				(true, false) => PauliLetter::X,
+				(false, true) => PauliLetter::Z,
+				(false, false) => continue,
+			};
+			string.set(qubit, letter);
+		}
+		string
+	};
+	let mut signs = || {
+		let mut signs = Bits::with_capacity(n);
+		for qubit in 0..n {
+			signs.set(qubit, rng.random_bool(0.5));
+		}
+		signs
+	};
+
+	CliffordTableau::from_rows(
+		symplectic[..n].iter().map(|bits| row(bits)).collect(),
+		symplectic[n..].iter().map(|bits| row(bits)).collect(),
+		signs(),
+		signs(),
+	)
+}
+
+/// Samples the Hadamards and the permutation of the qubits. Step $i$ picks
+/// $k<2m$ for the $m=n-i$ qubits left with probability proportional to
+/// $2^{-k}$, where $k<m$ means a Hadamard.
+fn quantum_mallows<R: Rng>(n_qubits: usize, rng: &mut R) -> (Vec<bool>, Vec<usize>) {
+	let mut hadamards = Vec::with_capacity(n_qubits);
+	let mut permutation = Vec::with_capacity(n_qubits);
+	let mut remaining: Vec<usize> = (0..n_qubits).collect();
+	for m in (1..=n_qubits).rev() {
+		let k = loop {
+			let mut k = 0;
+			while k < 2 * m && rng.random_bool(0.5) {
+				k += 1;
+			}
+			if k < 2 * m {
+				break k;
+			}
+		};
+		hadamards.push(k < m);
+		let index = match k < m {
+			true => k,
+			false => 2 * m - k - 1,
+		};
+		permutation.push(remaining.remove(index));
+	}
+	(hadamards, permutation)
+}
+
+/// The symplectic matrix of a uniformly random Hadamard-free Clifford,
+/// $\begin{pmatrix}\Delta&0\\\\\Gamma\Delta&\Delta^{-T}\end{pmatrix}$ for a
+/// lower unitriangular $\Delta$ and a symmetric $\Gamma$. The rows are the
+/// images of $X_i$ and then $Z_i$, the columns are x bits and then z bits.
+fn hadamard_free<R: Rng>(n: usize, rng: &mut R) -> Vec<Vec<bool>> {
+	let mut delta = vec![vec![false; n]; n];
+	let mut gamma = vec![vec![false; n]; n];
+	for i in 0..n {
+		delta[i][i] = true;
+		gamma[i][i] = rng.random_bool(0.5);
+		for j in 0..i {
+			delta[i][j] = rng.random_bool(0.5);
+			let bit = rng.random_bool(0.5);
+			gamma[i][j] = bit;
+			gamma[j][i] = bit;
+		}
+	}
+
+	// Forward substitution, $\Delta$ is its own inverse on the diagonal
+	let mut inverse: Vec<Vec<bool>> = Vec::with_capacity(n);
+	for (i, row) in delta.iter().enumerate() {
+		let next = (0..n)
+			.map(|j| match j < i {
+				true => (j..i).fold(false, |sum, k| sum ^ (row[k] & inverse[k][j])),
+				false => i == j,
+			})
+			.collect();
+		inverse.push(next);
+	}
+
+	let product = multiply(&gamma, &delta);
+	let mut matrix = vec![vec![false; 2 * n]; 2 * n];
+	for i in 0..n {
+		for j in 0..n {
+			matrix[i][j] = delta[i][j];
+			matrix[n + i][j] = product[i][j];
+			matrix[n + i][n + j] = inverse[j][i];
+		}
+	}
+	matrix
+}
+
+fn multiply(a: &[Vec<bool>], b: &[Vec<bool>]) -> Vec<Vec<bool>> {
+	a.iter()
+		.map(|row| {
+			(0..b.first().map(Vec::len).unwrap_or_default())
+				.map(|j| {
+					row.iter()
+						.zip(b)
+						.fold(false, |sum, (a, b)| sum ^ (a & b[j]))
+				})
+				.collect()
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+	use crate::{connectivity::Connectivity, misc::NonZeroEvenUsize};
+	use rand_chacha::ChaCha8Rng;
+
+	/// The rows of the tableau, with or without signs.
+	fn key(tableau: &CliffordTableau, n_qubits: usize, signs: bool) -> String {
+		let mut key = String::new();
+		for qubit in 0..n_qubits {
+			key.push_str(&tableau.get_x_row(qubit).as_string());
+			key.push('|');
+			key.push_str(&tableau.get_z_row(qubit).as_string());
+			key.push('|');
+		}
+		if signs {
+			key.push_str(&format!(
+				"{:?}{:?}",
+				tableau.get_x_signs(),
+				tableau.get_z_signs()
+			));
+		}
+		key
+	}
+
+	fn assert_uniform(n_qubits: usize, signs: bool, classes: usize, per_class: usize) {
+		let mut rng = ChaCha8Rng::seed_from_u64(24);
+		let mut counts: HashMap<String, usize> = HashMap::new();
+		for _ in 0..classes * per_class {
+			let tableau = random_clifford_tableau(n_qubits, &mut rng);
+			*counts.entry(key(&tableau, n_qubits, signs)).or_default() += 1;
+		}
+
+		assert_eq!(counts.len(), classes);
+		// Five standard deviations
+		let deviation = 5.0 * (per_class as f64).sqrt();
+		for count in counts.values() {
+			assert!(
+				(*count as f64 - per_class as f64).abs() < deviation,
+				"{counts:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn uniform() {
+		assert_uniform(1, true, 24, 1000);
+		assert_uniform(2, false, 720, 100);
+	}
+
+	#[test]
+	fn decomposes_on_topologies() {
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		for connectivity in [
+			Connectivity::create_line(gate_size, 10),
+			Connectivity::create_square_grid(gate_size, 10),
+		] {
+			for _ in 0..5 {
+				let expected = random_clifford_tableau(connectivity.qubit_count(), &mut rng);
+				for decomposition in [
+					expected.clone().decompose(gate_size, None),
+					expected.clone().decompose(gate_size, Some(&connectivity)),
+				] {
+					assert!(decomposition.iter().all(|exp| exp.string.len() <= 4));
+					let mut tableau = CliffordTableau::id();
+					for clifford in decomposition {
+						tableau.merge_clifford(clifford);
+					}
+					assert_eq!(tableau, expected);
+				}
+			}
+		}
+	}
+}
@@ -4,6 +4,16 @@ pub mod clifford_tableau;
 pub mod connectivity;
 pub mod draw;
 pub mod experiment;
+pub mod lower;
 pub mod misc;
+pub mod partition;
 pub mod pauli;
+pub mod phase_polynomial;
+pub mod product_formula;
+pub mod qasm;
+pub mod simulate;
 pub mod synthesize;
+pub mod verify;
+
+#[cfg(test)]
+mod test_utils;
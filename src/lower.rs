@@ -0,0 +1,533 @@
+//! Lowering of Pauli exponentials to gates.
+//!
+//! An exponential $e^{i\theta\pi P}$ on more than one qubit is lowered by
+//! changing the basis of every letter of $P$ to $Z$, computing the parity
+//! with a ladder of CNOTs and rotating the last qubit.
+//! Exponentials on a single qubit become a rotation around their axis, or a
+//! Pauli, $S$ or $S^\dagger$ gate for Clifford angles.
+//!
+//! The result is a [Circuit] of the gates of the [circuit] crate, so gate
+//! counts and depths can be compared in the same units as the algorithms of
+//! [cnot_parity_matrix], and it can be simulated on a [Statevector]. The
+//! OpenQASM export of [crate::qasm] writes out these gates.
+
+use bits::Bits;
+use circuit::{
+	Circuit,
+	gates::{CNot, H, Rx, Ry, Rz, S, Sdg, X, Y, Z},
+};
+use simulator::{Complex, Simulatable, Statevector};
+
+use crate::{
+	pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliLetter, PauliString},
+	phase_polynomial::ToExps,
+	simulate::ApplyExp,
+};
+
+/// A gate of a lowered circuit.
+///
+/// The rotations are the usual ones, like
+/// [cnot_parity_matrix::gateset::CNotRzXYH] and `rz` in OpenQASM, so
+/// `Rz { angle, .. }` is $R_z(\phi\pi)=e^{-i\frac{\phi\pi}{2}Z}$ for an
+/// angle of $\phi$. See [rotation_angle] for the exponential it comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gate {
+	X(X),
+	Y(Y),
+	Z(Z),
+	H(H),
+	S(S),
+	Sdg(Sdg),
+	Rx(Rx<PauliAngle>),
+	Ry(Ry<PauliAngle>),
+	Rz(Rz<PauliAngle>),
+	CNot(CNot),
+	/// A rotation around a string on any amount of qubits, for hardware
+	/// that has them natively. Unlike the other rotations this keeps the
+	/// angle of the exponential.
+	PauliRotation(PauliExp<PauliAngle>),
+}
+
+impl Gate {
+	/// The qubits the gate acts on.
+	pub fn qubits(&self) -> Vec<usize> {
+		match self {
+			Gate::X(X { target })
+			| Gate::Y(Y { target })
+			| Gate::Z(Z { target })
+			| Gate::H(H { target })
+			| Gate::S(S { target })
+			| Gate::Sdg(Sdg { target })
+			| Gate::Rx(Rx { target, .. })
+			| Gate::Ry(Ry { target, .. })
+			| Gate::Rz(Rz { target, .. }) => vec![*target],
+			Gate::CNot(cnot) => vec![cnot.control(), cnot.target()],
+			Gate::PauliRotation(exp) => exp.string.targets(),
+		}
+	}
+}
+
+impl From<CNot> for Gate {
+	fn from(value: CNot) -> Self {
+		Gate::CNot(value)
+	}
+}
+
+/// # Lower
+///
+/// Lowers the exponentials to gates, index 0 first. With `native_rotations`
+/// the exponentials on more than one qubit are kept as
+/// [Gate::PauliRotation] instead of being written out with CNOTs.
+/// Exponentials that are only a global phase are left out.
+pub fn lower<T: Negate + Clone + Into<PauliAngle>>(
+	exps: &[PauliExp<T>],
+	native_rotations: bool,
+) -> Circuit<Gate> {
+	let mut circuit = Circuit::new();
+	for exp in exps.iter() {
+		let angle: PauliAngle = exp.angle.clone().into();
+		if exp.is_empty() || angle == PauliAngle::Clifford(CliffordPauliAngle::Zero) {
+			continue;
+		}
+
+		let targets = exp.string.targets();
+		if let [target] = targets[..] {
+			circuit.push(single_qubit(exp.string.get(target), target, angle));
+		} else if native_rotations {
+			circuit.push(Gate::PauliRotation(PauliExp {
+				string: exp.string.clone(),
+				angle,
+			}));
+		} else {
+			lower_ladder(&exp.string, &targets, angle, &mut circuit);
+		}
+	}
+	circuit
+}
+
+/// The angle $\phi$ of the rotation $e^{-i\frac{\phi\pi}{2}P}$ that equals
+/// $e^{i\theta\pi P}$ up to a global phase, that is $\phi=-2\theta$.
+pub fn rotation_angle(angle: &PauliAngle) -> PauliAngle {
+	let mut angle = angle.to_expression();
+	angle.scale(-2.0);
+	angle.simplify()
+}
+
+/// The angle of the exponential for a rotation, the inverse of
+/// [rotation_angle].
+pub fn exp_angle(rotation: &PauliAngle) -> PauliAngle {
+	let mut angle = rotation.to_expression();
+	angle.scale(-0.5);
+	angle.simplify()
+}
+
+fn single_qubit(letter: PauliLetter, target: usize, angle: PauliAngle) -> Gate {
+	// $S=e^{i\frac{\pi}{4}}e^{-i\frac{\pi}{4}Z}$ and $P=ie^{i\frac{\pi}{2}P}$
+	match (letter, angle) {
+		(
+			PauliLetter::X,
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver2 | CliffordPauliAngle::NegPiOver2),
+		) => Gate::X(X { target }),
+		(
+			PauliLetter::Y,
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver2 | CliffordPauliAngle::NegPiOver2),
+		) => Gate::Y(Y { target }),
+		(
+			PauliLetter::Z,
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver2 | CliffordPauliAngle::NegPiOver2),
+		) => Gate::Z(Z { target }),
+		(PauliLetter::Z, PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4)) => {
+			Gate::S(S { target })
+		}
+		(PauliLetter::Z, PauliAngle::Clifford(CliffordPauliAngle::PiOver4)) => {
+			Gate::Sdg(Sdg { target })
+		}
+		(PauliLetter::X, angle) => Gate::Rx(Rx {
+			angle: rotation_angle(&angle),
+			target,
+		}),
+		(PauliLetter::Y, angle) => Gate::Ry(Ry {
+			angle: rotation_angle(&angle),
+			target,
+		}),
+		(_, angle) => Gate::Rz(Rz {
+			angle: rotation_angle(&angle),
+			target,
+		}),
+	}
+}
+
+/// The gates that change the basis of every letter of `string` to $Z$, and
+/// the ones that change it back.
+pub(crate) fn z_basis_change(string: &PauliString) -> (Vec<Gate>, Vec<Gate>) {
+	// $e^{i\theta X}=He^{i\theta Z}H$ and $e^{i\theta Y}=SHe^{i\theta Z}HS^\dagger$
+	let mut before = Vec::new();
+	let mut after = Vec::new();
+	for (i, letter) in string.letters() {
+		match letter {
+			PauliLetter::X => {
+				before.push(Gate::H(H { target: i }));
+				after.push(Gate::H(H { target: i }));
+			}
+			PauliLetter::Y => {
+				before.push(Gate::Sdg(Sdg { target: i }));
+				before.push(Gate::H(H { target: i }));
+				after.push(Gate::H(H { target: i }));
+				after.push(Gate::S(S { target: i }));
+			}
+			_ => {}
+		}
+	}
+	(before, after)
+}
+
+fn lower_ladder(
+	string: &PauliString,
+	targets: &[usize],
+	angle: PauliAngle,
+	circuit: &mut Circuit<Gate>,
+) {
+	let (before, after) = z_basis_change(string);
+	for gate in before {
+		circuit.push(gate);
+	}
+
+	for pair in targets.windows(2) {
+		circuit.push(CNot::new(pair[0], pair[1]).unwrap());
+	}
+	circuit.push(Gate::Rz(Rz {
+		angle: rotation_angle(&angle),
+		target: *targets.last().unwrap(),
+	}));
+	for pair in targets.windows(2).rev() {
+		circuit.push(CNot::new(pair[0], pair[1]).unwrap());
+	}
+
+	for gate in after {
+		circuit.push(gate);
+	}
+}
+
+/// How many "layers" of the gates that pass `filter` we need, like
+/// [crate::experiment::gate_depth].
+pub fn circuit_depth<F: Fn(&Gate) -> bool>(circuit: &Circuit<Gate>, filter: F) -> usize {
+	let mut layers: Vec<Bits> = Vec::new();
+	for gate in circuit.iter().filter(|gate| filter(gate)) {
+		let qubits = gate.qubits();
+		let layer = layers
+			.iter()
+			.rposition(|layer| qubits.iter().any(|qubit| layer.get(*qubit)))
+			.map_or(0, |i| i + 1);
+		if layer == layers.len() {
+			layers.push(Bits::new());
+		}
+		for qubit in qubits {
+			layers[layer].set(qubit, true);
+		}
+	}
+	layers.len()
+}
+
+pub fn circuit_count<F: Fn(&Gate) -> bool>(circuit: &Circuit<Gate>, filter: F) -> usize {
+	circuit.iter().filter(|gate| filter(gate)).count()
+}
+
+pub fn multi_qubit_gate_filter(gate: &Gate) -> bool {
+	gate.qubits().len() >= 2
+}
+
+impl ToExps for Gate {
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>> {
+		let exp = |string: PauliString, angle: PauliAngle| vec![PauliExp { string, angle }];
+		let clifford = |string, angle: CliffordPauliAngle| exp(string, angle.into());
+		match self {
+			Gate::X(X { target }) => clifford(PauliString::x(*target), CliffordPauliAngle::PiOver2),
+			Gate::Y(Y { target }) => clifford(PauliString::y(*target), CliffordPauliAngle::PiOver2),
+			Gate::Z(Z { target }) => clifford(PauliString::z(*target), CliffordPauliAngle::PiOver2),
+			Gate::H(H { target }) => [
+				PauliString::z(*target),
+				PauliString::x(*target),
+				PauliString::z(*target),
+			]
+			.into_iter()
+			.flat_map(|string| clifford(string, CliffordPauliAngle::PiOver4))
+			.collect(),
+			Gate::S(S { target }) => {
+				clifford(PauliString::z(*target), CliffordPauliAngle::NegPiOver4)
+			}
+			Gate::Sdg(Sdg { target }) => {
+				clifford(PauliString::z(*target), CliffordPauliAngle::PiOver4)
+			}
+			Gate::Rx(Rx { angle, target }) => exp(PauliString::x(*target), exp_angle(angle)),
+			Gate::Ry(Ry { angle, target }) => exp(PauliString::y(*target), exp_angle(angle)),
+			Gate::Rz(Rz { angle, target }) => exp(PauliString::z(*target), exp_angle(angle)),
+			Gate::CNot(cnot) => cnot.to_exps(),
+			Gate::PauliRotation(exp) => vec![exp.clone()],
+		}
+	}
+}
+
+/// A gate on one qubit with controls, for [Statevector::apply].
+struct Unitary {
+	matrix: [Complex<f64>; 4],
+	target: usize,
+	controls: Vec<usize>,
+}
+
+impl Simulatable<f64> for Unitary {
+	fn matrix(&self) -> [Complex<f64>; 4] {
+		self.matrix
+	}
+
+	fn target(&self) -> usize {
+		self.target
+	}
+
+	fn controls(&self) -> Vec<usize> {
+		self.controls.clone()
+	}
+}
+
+/// The angle $\theta\pi$ of the exponential $e^{i\theta\pi P}$ for a rotation.
+fn bound_radians(angle: &PauliAngle) -> f64 {
+	let angle = exp_angle(angle).to_expression();
+	assert!(
+		angle.terms().is_empty(),
+		"Can not simulate the unbound parameters in {angle}"
+	);
+	angle.constant_part() * std::f64::consts::PI
+}
+
+/// # Simulate
+///
+/// Applies the gates to `state`, index 0 first. The gates on one qubit and
+/// the CNOTs go through [Statevector::apply], the Pauli rotations through
+/// [ApplyExp].
+///
+/// # Panics
+/// Panics if an angle has parameters, see [crate::pauli::bind], or if a gate
+/// does not fit into the state.
+pub fn simulate(circuit: &Circuit<Gate>, state: &mut Statevector<f64>) {
+	let c = |re, im| Complex { re, im };
+	let zero = c(0.0, 0.0);
+	let one = c(1.0, 0.0);
+	let half = std::f64::consts::FRAC_1_SQRT_2;
+
+	for gate in circuit.iter() {
+		let (matrix, target, controls) = match gate {
+			Gate::X(X { target }) => ([zero, one, one, zero], *target, Vec::new()),
+			Gate::Y(Y { target }) => ([zero, c(0.0, -1.0), c(0.0, 1.0), zero], *target, Vec::new()),
+			Gate::Z(Z { target }) => ([one, zero, zero, c(-1.0, 0.0)], *target, Vec::new()),
+			Gate::H(H { target }) => (
+				[c(half, 0.0), c(half, 0.0), c(half, 0.0), c(-half, 0.0)],
+				*target,
+				Vec::new(),
+			),
+			Gate::S(S { target }) => ([one, zero, zero, c(0.0, 1.0)], *target, Vec::new()),
+			Gate::Sdg(Sdg { target }) => ([one, zero, zero, c(0.0, -1.0)], *target, Vec::new()),
+			Gate::Rx(Rx { angle, target }) => {
+				let (sin, cos) = bound_radians(angle).sin_cos();
+				(
+					[c(cos, 0.0), c(0.0, sin), c(0.0, sin), c(cos, 0.0)],
+					*target,
+					Vec::new(),
+				)
+			}
+			Gate::Ry(Ry { angle, target }) => {
+				let (sin, cos) = bound_radians(angle).sin_cos();
+				(
+					[c(cos, 0.0), c(sin, 0.0), c(-sin, 0.0), c(cos, 0.0)],
+					*target,
+					Vec::new(),
+				)
+			}
+			Gate::Rz(Rz { angle, target }) => {
+				let (sin, cos) = bound_radians(angle).sin_cos();
+				([c(cos, sin), zero, zero, c(cos, -sin)], *target, Vec::new())
+			}
+			Gate::CNot(cnot) => ([zero, one, one, zero], cnot.target(), vec![cnot.control()]),
+			Gate::PauliRotation(exp) => {
+				state.apply_exp(exp);
+				continue;
+			}
+		};
+		state.apply(&Unitary {
+			matrix,
+			target,
+			controls,
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{pauli_string, verify::verify_equivalence};
+	use cnot_parity_matrix::gateset::{CNotRzXYH, QuarterPi};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+
+	fn random_exps(
+		n_qubits: usize,
+		n_exps: usize,
+		rng: &mut ChaCha8Rng,
+	) -> Vec<PauliExp<PauliAngle>> {
+		let angles = [
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4),
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver2),
+			PauliAngle::Clifford(CliffordPauliAngle::Zero),
+		];
+		(0..n_exps)
+			.map(|_| {
+				let mut string = PauliString::default();
+				let letters = [PauliLetter::X, PauliLetter::Y, PauliLetter::Z];
+				// Mostly single qubit exponentials, so the Clifford gates show up
+				if rng.random_bool(0.5) {
+					string.set(
+						rng.random_range(0..n_qubits),
+						letters[rng.random_range(0..3)],
+					);
+				} else {
+					for i in 0..n_qubits {
+						if rng.random_bool(0.6) {
+							string.set(i, letters[rng.random_range(0..3)]);
+						}
+					}
+				}
+				let angle = match rng.random_range(0..6) {
+					i @ 0..4 => angles[i].clone(),
+					_ => PauliAngle::MultipleOfPi(rng.random()),
+				};
+				PauliExp { string, angle }
+			})
+			.collect()
+	}
+
+	fn distance(a: &Statevector<f64>, b: &Statevector<f64>) -> f64 {
+		// Equal up to a global phase when $|\langle a|b\rangle|=1$
+		let (re, im) =
+			a.values()
+				.iter()
+				.zip(b.values().iter())
+				.fold((0.0, 0.0), |(re, im), (a, b)| {
+					(
+						re + a.re * b.re + a.im * b.im,
+						im + a.re * b.im - a.im * b.re,
+					)
+				});
+		1.0 - (re * re + im * im).sqrt()
+	}
+
+	#[test]
+	fn lowering_is_equivalent() {
+		let mut rng = ChaCha8Rng::seed_from_u64(5);
+		let n_qubits = 4;
+		let exps = random_exps(n_qubits, 40, &mut rng);
+
+		let mut expected = Statevector::new(n_qubits);
+		expected.apply(&Unitary {
+			matrix: [
+				Complex { re: 0.6, im: 0.0 },
+				Complex { re: 0.0, im: 0.0 },
+				Complex { re: 0.8, im: 0.0 },
+				Complex { re: 0.0, im: 0.0 },
+			],
+			target: 1,
+			controls: Vec::new(),
+		});
+		let initial = expected.clone();
+		expected.apply_exps(&exps);
+
+		for native_rotations in [false, true] {
+			let circuit = lower(&exps, native_rotations);
+			verify_equivalence(&exps, &circuit.to_exps()).unwrap();
+
+			let mut state = initial.clone();
+			simulate(&circuit, &mut state);
+			assert!(distance(&state, &expected) < 1e-10);
+		}
+
+		let ladder = lower(&exps, false);
+		let native = lower(&exps, true);
+		assert!(
+			circuit_count(&ladder, multi_qubit_gate_filter)
+				> circuit_count(&native, multi_qubit_gate_filter)
+		);
+		assert!(circuit_depth(&ladder, |_| true) >= circuit_depth(&native, |_| true));
+	}
+
+	#[test]
+	fn rotations_are_standard() {
+		// $e^{-i\frac{\pi}{8}Z}=R_z(\frac{\pi}{4})$
+		let exps = vec![PauliExp {
+			string: PauliString::z(1),
+			angle: PauliAngle::MultipleOfPi(-0.125),
+		}];
+		let circuit = lower(&exps, false);
+		let rz = Rz {
+			angle: PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			target: 1,
+		};
+		assert_eq!(circuit.gates, vec![Gate::Rz(rz.clone())]);
+		assert_eq!(
+			circuit.to_exps(),
+			CNotRzXYH::Rz(Rz {
+				angle: QuarterPi(1),
+				target: 1
+			})
+			.to_exps()
+		);
+		assert_eq!(exp_angle(&rz.angle), exps[0].angle);
+	}
+
+	#[test]
+	fn gates() {
+		let exps = vec![
+			PauliExp {
+				string: pauli_string!("XIYZ"),
+				angle: PauliAngle::MultipleOfPi(0.125),
+			},
+			PauliExp {
+				string: pauli_string!("IZ"),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4),
+			},
+			PauliExp {
+				string: pauli_string!("IIY"),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::Zero),
+			},
+			PauliExp {
+				string: pauli_string!("X"),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2),
+			},
+		];
+		let angle = rotation_angle(&PauliAngle::MultipleOfPi(0.125));
+		assert_eq!(
+			lower(&exps, false).gates,
+			vec![
+				Gate::H(H { target: 0 }),
+				Gate::Sdg(Sdg { target: 2 }),
+				Gate::H(H { target: 2 }),
+				Gate::CNot(CNot::new(0, 2).unwrap()),
+				Gate::CNot(CNot::new(2, 3).unwrap()),
+				Gate::Rz(Rz {
+					angle: angle.clone(),
+					target: 3
+				}),
+				Gate::CNot(CNot::new(2, 3).unwrap()),
+				Gate::CNot(CNot::new(0, 2).unwrap()),
+				Gate::H(H { target: 0 }),
+				Gate::H(H { target: 2 }),
+				Gate::S(S { target: 2 }),
+				Gate::S(S { target: 1 }),
+				Gate::X(X { target: 0 }),
+			]
+		);
+
+		let native = lower(&exps, true);
+		assert_eq!(native.gates[0], Gate::PauliRotation(exps[0].clone()));
+		assert_eq!(native.len(), 3);
+		assert_eq!(circuit_depth(&native, |_| true), 2);
+		assert_eq!(circuit_depth(&native, multi_qubit_gate_filter), 1);
+	}
+}
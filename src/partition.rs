@@ -0,0 +1,310 @@
+//! Partitioning into commuting groups and their diagonalisation.
+//!
+//! Exponentials whose strings commute can be reordered freely, so a set of
+//! exponentials where the order does not matter (like the terms of a
+//! Hamiltonian) can be split into groups of commuting exponentials. The
+//! groups are the colour classes of a colouring of the anticommutation graph.
+//!
+//! The strings of a commuting group can be mapped to strings of only $Z$ and
+//! $I$ by a single Clifford, see [diagonalise].
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliLetter, PauliString},
+};
+
+/// The order in which the vertices of the anticommutation graph are
+/// coloured. Every vertex gets the smallest colour that none of its
+/// neighbours has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colouring {
+	/// In the order of the input.
+	Greedy,
+	/// By decreasing degree, ties in the order of the input.
+	LargestFirst,
+	/// Always the vertex with the most distinct colours among its
+	/// neighbours, ties by degree and then in the order of the input.
+	DSatur,
+}
+
+/// The neighbours of every exponential, the exponentials it anticommutes
+/// with.
+pub fn anticommutation_graph<A: Negate>(exps: &[PauliExp<A>]) -> Vec<Vec<usize>> {
+	let mut graph = vec![Vec::new(); exps.len()];
+	for i in 0..exps.len() {
+		for j in i + 1..exps.len() {
+			if exps[i].string.anticommutes_with(&exps[j].string) {
+				graph[i].push(j);
+				graph[j].push(i);
+			}
+		}
+	}
+	graph
+}
+
+/// # Commuting groups
+///
+/// Partitions `exps` into groups of commuting exponentials, as sorted
+/// indices into `exps`. The groups are ordered by colour.
+///
+/// Reordering exponentials changes the circuit, so this is only meant for
+/// sets where the order does not matter.
+pub fn commuting_groups<A: Negate>(exps: &[PauliExp<A>], colouring: Colouring) -> Vec<Vec<usize>> {
+	let graph = anticommutation_graph(exps);
+	let mut colours: Vec<Option<usize>> = vec![None; exps.len()];
+
+	let smallest_free = |colours: &[Option<usize>], vertex: usize| {
+		let mut used: Vec<usize> = graph[vertex].iter().filter_map(|n| colours[*n]).collect();
+		used.sort_unstable();
+		used.dedup();
+		used.iter()
+			.enumerate()
+			.find(|(i, colour)| *i != **colour)
+			.map(|(i, _)| i)
+			.unwrap_or(used.len())
+	};
+
+	match colouring {
+		Colouring::Greedy | Colouring::LargestFirst => {
+			let mut order: Vec<usize> = (0..exps.len()).collect();
+			if colouring == Colouring::LargestFirst {
+				order.sort_by_key(|vertex| std::cmp::Reverse(graph[*vertex].len()));
+			}
+			for vertex in order {
+				colours[vertex] = Some(smallest_free(&colours, vertex));
+			}
+		}
+		Colouring::DSatur => {
+			for _ in 0..exps.len() {
+				let saturation = |vertex: usize| {
+					let mut neighbours: Vec<usize> =
+						graph[vertex].iter().filter_map(|n| colours[*n]).collect();
+					neighbours.sort_unstable();
+					neighbours.dedup();
+					neighbours.len()
+				};
+				let vertex = (0..exps.len())
+					.filter(|vertex| colours[*vertex].is_none())
+					.max_by_key(|vertex| {
+						(
+							saturation(*vertex),
+							graph[*vertex].len(),
+							std::cmp::Reverse(*vertex),
+						)
+					})
+					.unwrap();
+				colours[vertex] = Some(smallest_free(&colours, vertex));
+			}
+		}
+	}
+
+	let mut groups: Vec<Vec<usize>> = Vec::new();
+	for (vertex, colour) in colours.into_iter().enumerate() {
+		let colour = colour.unwrap();
+		if groups.len() <= colour {
+			groups.resize(colour + 1, Vec::new());
+		}
+		groups[colour].push(vertex);
+	}
+	groups
+}
+
+/// A commuting group written as $C^\dagger\left(\prod_je^{i\theta_jD_j}\right)C$
+/// where every $D_j$ only has $Z$ and $I$.
+#[derive(Debug, Clone)]
+pub struct Diagonalisation {
+	/// $C$ as a tableau, the same as merging `cliffords`.
+	pub tableau: CliffordTableau,
+	/// $C$ as exponentials, index 0 first.
+	pub cliffords: Vec<PauliExp<CliffordPauliAngle>>,
+	/// The exponentials of the group with the diagonal strings $D_j$, in
+	/// the same order. Signs are moved into the angles.
+	pub exponentials: Vec<PauliExp<PauliAngle>>,
+}
+
+impl Diagonalisation {
+	/// $C$, the diagonal exponentials and $C^\dagger$ in time order, which is
+	/// equal to the group.
+	pub fn circuit(&self) -> Vec<PauliExp<PauliAngle>> {
+		let inverse = self.cliffords.iter().rev().map(|clifford| {
+			let mut clifford = clifford.clone();
+			clifford.angle.negate();
+			clifford
+		});
+		self.cliffords
+			.iter()
+			.cloned()
+			.map(PauliExp::from)
+			.chain(self.exponentials.iter().cloned())
+			.chain(inverse.map(PauliExp::from))
+			.collect()
+	}
+}
+
+fn is_diagonal(string: &PauliString) -> bool {
+	string.letters().all(|(_, letter)| letter == PauliLetter::Z)
+}
+
+/// # Diagonalise
+///
+/// Finds a Clifford $C$ that maps every string of `group` to a string of only
+/// $Z$ and $I$. Gives [None] if the strings do not commute.
+///
+/// A string $P$ that still has an $X$ or $Y$ on qubit $q$ anticommutes with
+/// $Z_q$. With $O=iZ_qP$, which is $P$ with that letter swapped between $X$
+/// and $Y$, the rotation $e^{i\frac{\pi}{4}O}$ maps $P$ to $\pm Z_q$. The
+/// strings that are already diagonal commute with both $P$ and $Z_q$, so they
+/// stay as they are. Every string needs at most one rotation and there are
+/// at most as many rotations as qubits.
+pub fn diagonalise(group: &[PauliExp<PauliAngle>]) -> Option<Diagonalisation> {
+	for (i, a) in group.iter().enumerate() {
+		if group[i + 1..]
+			.iter()
+			.any(|b| a.string.anticommutes_with(&b.string))
+		{
+			return None;
+		}
+	}
+
+	let mut exponentials = group.to_vec();
+	let mut cliffords = Vec::new();
+	let mut tableau = CliffordTableau::id();
+	while let Some(exp) = exponentials.iter().find(|exp| !is_diagonal(&exp.string)) {
+		let (pivot, letter) = exp
+			.string
+			.letters()
+			.find(|(_, letter)| *letter != PauliLetter::Z)
+			.unwrap();
+		let mut rotation = exp.string.clone();
+		rotation.set(
+			pivot,
+			match letter {
+				PauliLetter::X => PauliLetter::Y,
+				_ => PauliLetter::X,
+			},
+		);
+
+		// $e^{i\theta P}=c^\dagger e^{i\theta cPc^\dagger}c$
+		for exp in exponentials.iter_mut() {
+			exp.push_pi_over_4(false, &rotation);
+		}
+		let clifford = PauliExp {
+			string: rotation,
+			angle: CliffordPauliAngle::PiOver4,
+		};
+		tableau.merge_clifford(clifford.clone());
+		cliffords.push(clifford);
+	}
+
+	Some(Diagonalisation {
+		tableau,
+		cliffords,
+		exponentials,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{pauli_string, test_utils::random_string, verify::verify_equivalence};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+
+	fn exp(string: PauliString, angle: f64) -> PauliExp<PauliAngle> {
+		PauliExp {
+			string,
+			angle: PauliAngle::MultipleOfPi(angle),
+		}
+	}
+
+	#[test]
+	fn colourings_are_valid() {
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		let exps: Vec<PauliExp<PauliAngle>> = (0..40)
+			.map(|_| exp(random_string(5, &mut rng), 0.1))
+			.collect();
+
+		for colouring in [
+			Colouring::Greedy,
+			Colouring::LargestFirst,
+			Colouring::DSatur,
+		] {
+			let groups = commuting_groups(&exps, colouring);
+			let mut seen: Vec<usize> = groups.iter().flatten().copied().collect();
+			seen.sort_unstable();
+			assert_eq!(seen, (0..exps.len()).collect::<Vec<_>>());
+			for group in groups.iter() {
+				assert!(!group.is_empty());
+				for (i, a) in group.iter().enumerate() {
+					for b in group[i + 1..].iter() {
+						assert!(exps[*a].string.commutes_with(&exps[*b].string));
+					}
+				}
+			}
+		}
+
+		// X, Y and Z on a qubit all anticommute, ZZ commutes with XX
+		let exps = vec![
+			exp(pauli_string!("XI"), 0.1),
+			exp(pauli_string!("YI"), 0.1),
+			exp(pauli_string!("ZI"), 0.1),
+			exp(pauli_string!("ZZ"), 0.1),
+			exp(pauli_string!("XX"), 0.1),
+		];
+		let groups = commuting_groups(&exps, Colouring::DSatur);
+		assert_eq!(groups.len(), 3);
+		assert_eq!(
+			commuting_groups(&exps, Colouring::Greedy),
+			vec![vec![0, 4], vec![1], vec![2, 3]]
+		);
+	}
+
+	#[test]
+	fn diagonalised_groups_are_equal() {
+		let mut rng = ChaCha8Rng::seed_from_u64(2);
+		let n_qubits = 6;
+
+		// Products of the stabilizers of a random Clifford commute
+		let mut cliffords: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
+		for _ in 0..20 {
+			cliffords.push(PauliExp {
+				string: random_string(n_qubits, &mut rng),
+				angle: CliffordPauliAngle::PiOver4,
+			});
+		}
+		let group: Vec<PauliExp<PauliAngle>> = (0..12)
+			.map(|_| {
+				let mut string = PauliString::default();
+				for i in 0..n_qubits {
+					if rng.random_bool(0.5) {
+						string.set(i, PauliLetter::Z);
+					}
+				}
+				let mut exp = exp(string, rng.random());
+				for clifford in cliffords.iter() {
+					exp.move_clifford_past(clifford);
+				}
+				exp
+			})
+			.collect();
+
+		let diagonalisation = diagonalise(&group).unwrap();
+		assert!(diagonalisation.cliffords.len() <= n_qubits);
+		assert!(
+			diagonalisation
+				.exponentials
+				.iter()
+				.all(|exp| is_diagonal(&exp.string))
+		);
+
+		let mut tableau = CliffordTableau::id();
+		for clifford in diagonalisation.cliffords.iter() {
+			tableau.merge_clifford(clifford.clone());
+		}
+		assert_eq!(tableau, diagonalisation.tableau);
+		verify_equivalence(&group, &diagonalisation.circuit()).unwrap();
+
+		let anticommuting = vec![exp(pauli_string!("XZ"), 0.1), exp(pauli_string!("ZZ"), 0.2)];
+		assert!(diagonalise(&anticommuting).is_none());
+	}
+}
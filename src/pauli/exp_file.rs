@@ -0,0 +1,873 @@
+//! Fallible reading and writing of the `.exp` format described in the README.
+
+use std::{
+	collections::BTreeMap,
+	error::Error,
+	fmt,
+	fs::File,
+	io::{self, BufRead, BufReader, BufWriter, Read, Write},
+	path::Path,
+};
+
+use bits::Bits;
+
+use super::{
+	AngleExpression, AngleExpressionError, CliffordPauliAngle, PauliAngle, PauliExp, PauliLetter,
+	PauliString,
+};
+
+/// The newest version of the format that can be read and written.
+pub const EXP_FORMAT_VERSION: u32 = 2;
+
+/// The qubit count that sparse strings are limited to when the file does not
+/// declare one with `#!qubits`.
+pub const MAX_SPARSE_QUBITS: usize = 1 << 20;
+
+/// The reason why a line of an `.exp` file could not be parsed.
+#[derive(Debug)]
+pub enum ExpParseErrorReason {
+	/// Reading from the underlying reader failed.
+	Io(io::Error),
+	/// The line has no `;` between the angle and the Pauli string.
+	MissingSeparator,
+	/// The angle (or parameter name) is empty.
+	EmptyAngle,
+	/// The Pauli string contains something else than `I`, `X`, `Y` or `Z`.
+	InvalidLetter(char),
+	/// The angle is not a number, a parameter name or a valid expression.
+	InvalidAngle(AngleExpressionError),
+	/// A sparse Pauli string term is not of the form `X3`.
+	InvalidSparseTerm(String),
+	/// The Pauli string acts on a qubit that is not below the declared count,
+	/// or [MAX_SPARSE_QUBITS] if there is none.
+	QubitOutOfRange { qubit: usize, qubits: usize },
+	/// A sparse Pauli string names the same qubit twice.
+	DuplicateQubit(usize),
+	/// A header line (`#!`) has a value that can not be understood.
+	InvalidHeader(String),
+	/// The file declares a format version that is not supported.
+	UnsupportedVersion(u32),
+	/// Header lines have to come before the first exponential.
+	HeaderAfterData,
+}
+
+/// An error that is given when an `.exp` file can not be read.
+///
+/// Both `line` and `column` start from 1. For errors that are not tied to a
+/// specific location (like failing to open a file) they are 0.
+#[derive(Debug)]
+pub struct ExpParseError {
+	pub line: usize,
+	pub column: usize,
+	pub reason: ExpParseErrorReason,
+}
+
+impl ExpParseError {
+	fn new(line: usize, column: usize, reason: ExpParseErrorReason) -> Self {
+		Self {
+			line,
+			column,
+			reason,
+		}
+	}
+}
+
+impl fmt::Display for ExpParseErrorReason {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ExpParseErrorReason::Io(err) => write!(f, "failed to read: {err}"),
+			ExpParseErrorReason::MissingSeparator => {
+				write!(f, "expected ';' between angle and Pauli string")
+			}
+			ExpParseErrorReason::EmptyAngle => write!(f, "the angle is empty"),
+			ExpParseErrorReason::InvalidLetter(c) => {
+				write!(f, "'{c}' is not a Pauli letter (IXYZ)")
+			}
+			ExpParseErrorReason::InvalidAngle(err) => write!(f, "invalid angle: {err}"),
+			ExpParseErrorReason::InvalidSparseTerm(term) => {
+				write!(f, "'{term}' is not a sparse Pauli term like X3")
+			}
+			ExpParseErrorReason::QubitOutOfRange { qubit, qubits } => {
+				write!(f, "qubit {qubit} is out of range for {qubits} qubits")
+			}
+			ExpParseErrorReason::DuplicateQubit(qubit) => {
+				write!(f, "qubit {qubit} appears more than once")
+			}
+			ExpParseErrorReason::InvalidHeader(line) => write!(f, "invalid header '{line}'"),
+			ExpParseErrorReason::UnsupportedVersion(version) => {
+				write!(f, "unsupported format version {version}")
+			}
+			ExpParseErrorReason::HeaderAfterData => {
+				write!(f, "header lines have to come before the exponentials")
+			}
+		}
+	}
+}
+
+impl fmt::Display for ExpParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}: {}", self.line, self.column, self.reason)
+	}
+}
+
+impl Error for ExpParseError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match &self.reason {
+			ExpParseErrorReason::Io(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<io::Error> for ExpParseError {
+	fn from(value: io::Error) -> Self {
+		Self::new(0, 0, ExpParseErrorReason::Io(value))
+	}
+}
+
+/// The fermion to qubit encoding that was used to create the exponentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+	JordanWigner,
+	BravyiKitaev,
+	Parity,
+}
+
+impl Encoding {
+	fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"JW" => Some(Encoding::JordanWigner),
+			"BK" => Some(Encoding::BravyiKitaev),
+			"P" => Some(Encoding::Parity),
+			_ => None,
+		}
+	}
+
+	fn name(&self) -> &'static str {
+		match self {
+			Encoding::JordanWigner => "JW",
+			Encoding::BravyiKitaev => "BK",
+			Encoding::Parity => "P",
+		}
+	}
+}
+
+/// The header of an `.exp` file. Files without a header are version 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpHeader {
+	pub version: u32,
+	pub qubits: Option<usize>,
+	pub molecule: Option<String>,
+	pub encoding: Option<Encoding>,
+	pub basis: Option<String>,
+	/// Any other `#!key value` lines.
+	pub metadata: BTreeMap<String, String>,
+}
+
+impl Default for ExpHeader {
+	fn default() -> Self {
+		Self {
+			version: 1,
+			qubits: None,
+			molecule: None,
+			encoding: None,
+			basis: None,
+			metadata: BTreeMap::new(),
+		}
+	}
+}
+
+impl ExpHeader {
+	/// An empty header of the newest version.
+	pub fn new() -> Self {
+		Self {
+			version: EXP_FORMAT_VERSION,
+			..Default::default()
+		}
+	}
+
+	fn parse_line(&mut self, line: &str, line_number: usize) -> Result<(), ExpParseError> {
+		let invalid = || {
+			ExpParseError::new(
+				line_number,
+				1,
+				ExpParseErrorReason::InvalidHeader(String::from(line)),
+			)
+		};
+
+		let directive = line.strip_prefix("#!").unwrap().trim();
+		let (key, value) = directive
+			.split_once(char::is_whitespace)
+			.map(|(key, value)| (key, value.trim()))
+			.unwrap_or((directive, ""));
+		if key.is_empty() || value.is_empty() {
+			return Err(invalid());
+		}
+
+		match key {
+			"exp" => {
+				let version = value
+					.strip_prefix('v')
+					.unwrap_or(value)
+					.parse::<u32>()
+					.map_err(|_| invalid())?;
+				if version == 0 || version > EXP_FORMAT_VERSION {
+					return Err(ExpParseError::new(
+						line_number,
+						1,
+						ExpParseErrorReason::UnsupportedVersion(version),
+					));
+				}
+				self.version = version;
+			}
+			"qubits" => self.qubits = Some(value.parse().map_err(|_| invalid())?),
+			"molecule" => self.molecule = Some(String::from(value)),
+			"encoding" => self.encoding = Some(Encoding::from_name(value).ok_or_else(invalid)?),
+			"basis" => self.basis = Some(String::from(value)),
+			_ => {
+				self.metadata.insert(String::from(key), String::from(value));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		writeln!(writer, "#!exp {}", self.version)?;
+		if let Some(qubits) = self.qubits {
+			writeln!(writer, "#!qubits {qubits}")?;
+		}
+		if let Some(molecule) = &self.molecule {
+			writeln!(writer, "#!molecule {molecule}")?;
+		}
+		if let Some(encoding) = &self.encoding {
+			writeln!(writer, "#!encoding {}", encoding.name())?;
+		}
+		if let Some(basis) = &self.basis {
+			writeln!(writer, "#!basis {basis}")?;
+		}
+		for (key, value) in self.metadata.iter() {
+			writeln!(writer, "#!{key} {value}")?;
+		}
+		Ok(())
+	}
+}
+
+/// A lazy iterator over the exponentials of an `.exp` file.
+///
+/// Lines are parsed only when they are requested, so the whole file never
+/// has to be in memory at once. After the first error the iterator stops.
+pub struct ExpReader<R: BufRead> {
+	reader: R,
+	buffer: String,
+	line: usize,
+	failed: bool,
+	header: Option<ExpHeader>,
+	/// The first exponential line, which is read while looking for the header.
+	pending: Option<String>,
+}
+
+impl<R: BufRead> ExpReader<R> {
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			buffer: String::new(),
+			line: 0,
+			failed: false,
+			header: None,
+			pending: None,
+		}
+	}
+
+	/// Reads the header lines if that has not yet been done.
+	pub fn header(&mut self) -> Result<&ExpHeader, ExpParseError> {
+		if self.header.is_none() {
+			let mut header = ExpHeader::default();
+			while self.read_line()? {
+				let line = self.buffer.trim();
+				if line.starts_with("#!") {
+					header.parse_line(line, self.line)?;
+				} else if !line.starts_with('#') {
+					self.pending = Some(self.buffer.clone());
+					break;
+				}
+			}
+			self.header = Some(header);
+		}
+
+		Ok(self.header.as_ref().unwrap())
+	}
+
+	/// Reads the next non-blank line to the buffer. Returns false at the end.
+	fn read_line(&mut self) -> Result<bool, ExpParseError> {
+		loop {
+			self.buffer.clear();
+			self.line += 1;
+			match self.reader.read_line(&mut self.buffer) {
+				Ok(0) => return Ok(false),
+				Ok(_) => {}
+				Err(err) => {
+					return Err(ExpParseError::new(
+						self.line,
+						0,
+						ExpParseErrorReason::Io(err),
+					));
+				}
+			}
+
+			let len = self.buffer.trim_end_matches(['\n', '\r']).len();
+			self.buffer.truncate(len);
+			if !self.buffer.trim().is_empty() {
+				return Ok(true);
+			}
+		}
+	}
+
+	fn next_exp(&mut self) -> Result<Option<PauliExp<PauliAngle>>, ExpParseError> {
+		self.header()?;
+
+		if let Some(pending) = self.pending.take() {
+			self.buffer = pending;
+		} else {
+			loop {
+				if !self.read_line()? {
+					return Ok(None);
+				}
+				let line = self.buffer.trim_start();
+				if line.starts_with("#!") {
+					return Err(ExpParseError::new(
+						self.line,
+						1,
+						ExpParseErrorReason::HeaderAfterData,
+					));
+				}
+				if !line.starts_with('#') {
+					break;
+				}
+			}
+		}
+
+		let qubits = self.header.as_ref().unwrap().qubits;
+		parse_line(&self.buffer, self.line, qubits).map(Some)
+	}
+}
+
+impl ExpReader<BufReader<File>> {
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ExpParseError> {
+		Ok(Self::new(BufReader::new(File::open(path)?)))
+	}
+}
+
+impl<R: BufRead> Iterator for ExpReader<R> {
+	type Item = Result<PauliExp<PauliAngle>, ExpParseError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+
+		let result = self.next_exp();
+		if result.is_err() {
+			self.failed = true;
+		}
+		result.transpose()
+	}
+}
+
+fn parse_line(
+	line: &str,
+	line_number: usize,
+	qubits: Option<usize>,
+) -> Result<PauliExp<PauliAngle>, ExpParseError> {
+	// Everything after a '#' is a comment
+	let line = line.split_once('#').map(|(line, _)| line).unwrap_or(line);
+
+	let (angle, letters) = line.split_once(';').ok_or_else(|| {
+		ExpParseError::new(
+			line_number,
+			line.trim_end().chars().count() + 1,
+			ExpParseErrorReason::MissingSeparator,
+		)
+	})?;
+
+	let angle = parse_angle(angle).map_err(|err| match err {
+		None => ExpParseError::new(line_number, 1, ExpParseErrorReason::EmptyAngle),
+		Some(err) => {
+			let leading = angle.chars().take_while(|c| c.is_whitespace()).count();
+			ExpParseError::new(
+				line_number,
+				leading + err.position + 1,
+				ExpParseErrorReason::InvalidAngle(err),
+			)
+		}
+	})?;
+
+	// The string starts after the angle and the separator
+	let offset = angle_columns(line) + 1;
+	let string = if letters.chars().any(|c| c.is_ascii_digit()) {
+		parse_sparse_string(letters, line_number, offset, qubits)?
+	} else {
+		parse_dense_string(letters, line_number, offset)?
+	};
+
+	if let Some(qubits) = qubits
+		&& string.size() > qubits
+	{
+		return Err(ExpParseError::new(
+			line_number,
+			offset + 1,
+			ExpParseErrorReason::QubitOutOfRange {
+				qubit: string.size() - 1,
+				qubits,
+			},
+		));
+	}
+
+	Ok(PauliExp { string, angle })
+}
+
+fn parse_letter(letter: char) -> Option<PauliLetter> {
+	match letter {
+		'I' => Some(PauliLetter::I),
+		'X' => Some(PauliLetter::X),
+		'Y' => Some(PauliLetter::Y),
+		'Z' => Some(PauliLetter::Z),
+		_ => None,
+	}
+}
+
+/// A string like `XIIZ`
+fn parse_dense_string(
+	letters: &str,
+	line_number: usize,
+	offset: usize,
+) -> Result<PauliString, ExpParseError> {
+	let trailing = letters.trim_end();
+	let mut string = PauliString::id_with_capacity(trailing.len());
+	for (i, letter) in trailing.chars().enumerate() {
+		match parse_letter(letter) {
+			Some(letter) => string.set(i, letter),
+			None => {
+				return Err(ExpParseError::new(
+					line_number,
+					offset + i + 1,
+					ExpParseErrorReason::InvalidLetter(letter),
+				));
+			}
+		}
+	}
+
+	Ok(string)
+}
+
+/// A string like `X3 Z17`. The indices are checked before anything is
+/// allocated for them.
+fn parse_sparse_string(
+	letters: &str,
+	line_number: usize,
+	offset: usize,
+	qubits: Option<usize>,
+) -> Result<PauliString, ExpParseError> {
+	let limit = qubits.unwrap_or(MAX_SPARSE_QUBITS);
+	let mut string = PauliString::id();
+	let mut seen = Bits::new();
+	for part in letters.split_whitespace() {
+		let byte = part.as_ptr() as usize - letters.as_ptr() as usize;
+		let start = offset + letters[..byte].chars().count() + 1;
+		let error = |reason| ExpParseError::new(line_number, start, reason);
+
+		let mut chars = part.chars();
+		let letter = chars.next().unwrap();
+		let letter = parse_letter(letter)
+			.ok_or_else(|| error(ExpParseErrorReason::InvalidLetter(letter)))?;
+		// `parse` alone would also accept a leading `+`
+		let digits = chars.as_str();
+		let index = digits
+			.bytes()
+			.all(|byte| byte.is_ascii_digit())
+			.then(|| digits.parse::<usize>().ok())
+			.flatten()
+			.ok_or_else(|| error(ExpParseErrorReason::InvalidSparseTerm(String::from(part))))?;
+		if index >= limit {
+			return Err(error(ExpParseErrorReason::QubitOutOfRange {
+				qubit: index,
+				qubits: limit,
+			}));
+		}
+		if seen.get(index) {
+			return Err(error(ExpParseErrorReason::DuplicateQubit(index)));
+		}
+		seen.set(index, true);
+		string.set(index, letter);
+	}
+
+	Ok(string)
+}
+
+/// The amount of characters before the first `;`.
+fn angle_columns(line: &str) -> usize {
+	line.chars().take_while(|c| *c != ';').count()
+}
+
+/// Parses the angle column of an `.exp` line. Numbers are multiples of pi.
+/// Anything else is a parameter name with an optional leading `-` (the
+/// original format) or, if it contains operators, an [AngleExpression].
+///
+/// Gives `Err(None)` when the angle is empty.
+pub(crate) fn parse_angle(angle: &str) -> Result<PauliAngle, Option<AngleExpressionError>> {
+	let angle = angle.trim();
+	if let Ok(v) = angle.parse::<f64>() {
+		return Ok(AngleExpression::constant(v).simplify());
+	}
+
+	let name = angle.strip_prefix('-').unwrap_or(angle);
+	if name.is_empty() {
+		return Err(None);
+	}
+
+	if !name.contains(['+', '-', '*', '/', ' ', '\t']) {
+		return Ok(PauliAngle::Parameter {
+			neg: name.len() != angle.len(),
+			name: String::from(name),
+		});
+	}
+
+	angle
+		.parse::<AngleExpression>()
+		.map(AngleExpression::simplify)
+		.map_err(Some)
+}
+
+pub(crate) fn format_angle(angle: &PauliAngle) -> String {
+	match angle {
+		PauliAngle::MultipleOfPi(v) => format!("{v}"),
+		PauliAngle::Parameter { neg: false, name } => name.clone(),
+		PauliAngle::Parameter { neg: true, name } => format!("-{name}"),
+		PauliAngle::Expression(expression) => format!("{expression}"),
+		PauliAngle::Clifford(CliffordPauliAngle::PiOver2) => String::from("0.5"),
+		PauliAngle::Clifford(CliffordPauliAngle::PiOver4) => String::from("0.25"),
+		PauliAngle::Clifford(CliffordPauliAngle::Zero) => String::from("0.0"),
+		PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4) => String::from("-0.25"),
+		PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2) => String::from("-0.5"),
+	}
+}
+
+/// Writes exponentials one at a time in the `.exp` format.
+///
+/// Without a header the output is readable by version 1 readers as long as
+/// no [AngleExpression]s are written.
+pub struct ExpWriter<W: Write> {
+	writer: W,
+	sparse: bool,
+}
+
+impl<W: Write> ExpWriter<W> {
+	pub fn new(writer: W) -> Self {
+		Self {
+			writer,
+			sparse: false,
+		}
+	}
+
+	/// Starts the output with the given header.
+	pub fn with_header(mut writer: W, header: &ExpHeader) -> io::Result<Self> {
+		header.write(&mut writer)?;
+		Ok(Self::new(writer))
+	}
+
+	/// When set, Pauli strings are written as `X3 Z17` instead of `IIIXI...`.
+	pub fn set_sparse(&mut self, sparse: bool) {
+		self.sparse = sparse;
+	}
+
+	pub fn write(&mut self, exp: &PauliExp<PauliAngle>) -> io::Result<()> {
+		let string = if self.sparse {
+			exp.string
+				.letters()
+				.map(|(i, letter)| format!("{letter}{i}"))
+				.collect::<Vec<_>>()
+				.join(" ")
+		} else {
+			exp.string.as_string()
+		};
+		writeln!(self.writer, "{};{string}", format_angle(&exp.angle))
+	}
+
+	/// Writes a `#` comment line.
+	pub fn write_comment(&mut self, comment: &str) -> io::Result<()> {
+		for line in comment.lines() {
+			writeln!(self.writer, "# {line}")?;
+		}
+		Ok(())
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+
+	/// Flushes and returns the underlying writer.
+	pub fn into_inner(mut self) -> io::Result<W> {
+		self.flush()?;
+		Ok(self.writer)
+	}
+}
+
+impl ExpWriter<BufWriter<File>> {
+	/// Creates a new file to write to. Fails if the file already exists.
+	pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+		let file = File::options().write(true).create_new(true).open(path)?;
+		Ok(Self::new(BufWriter::new(file)))
+	}
+}
+
+impl PauliExp<PauliAngle> {
+	/// Reads all exponentials from `reader`. See [ExpReader] for a lazy
+	/// alternative.
+	pub fn read_exp<R: Read>(reader: R) -> Result<Vec<Self>, ExpParseError> {
+		ExpReader::new(BufReader::new(reader)).collect()
+	}
+
+	pub fn try_read_exp_file<P: AsRef<Path>>(path: P) -> Result<Vec<Self>, ExpParseError> {
+		ExpReader::open(path)?.collect()
+	}
+
+	pub fn write_exp<W: Write>(exps: &[Self], writer: W) -> io::Result<()> {
+		let mut writer = ExpWriter::new(writer);
+		for exp in exps {
+			writer.write(exp)?;
+		}
+		writer.flush()
+	}
+
+	/// Reads the header and all exponentials from `reader`.
+	pub fn read_exp_with_header<R: Read>(
+		reader: R,
+	) -> Result<(ExpHeader, Vec<Self>), ExpParseError> {
+		let mut reader = ExpReader::new(BufReader::new(reader));
+		let header = reader.header()?.clone();
+		let exps = reader.collect::<Result<Vec<_>, _>>()?;
+		Ok((header, exps))
+	}
+
+	pub fn write_exp_with_header<W: Write>(
+		exps: &[Self],
+		header: &ExpHeader,
+		writer: W,
+	) -> io::Result<()> {
+		let mut writer = ExpWriter::with_header(writer, header)?;
+		for exp in exps {
+			writer.write(exp)?;
+		}
+		writer.flush()
+	}
+
+	/// Writes to a new file. Fails if the file already exists.
+	pub fn try_write_exp_file<P: AsRef<Path>>(exps: &[Self], path: P) -> io::Result<()> {
+		let mut writer = ExpWriter::create(path)?;
+		for exp in exps {
+			writer.write(exp)?;
+		}
+		writer.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn read_write_round_trip() {
+		let input = "0.25;XIIX\na;IIXI\n-a;XIII\n0.36;IIIZ\n-0.5;IIXI\n";
+		let exps = PauliExp::read_exp(input.as_bytes()).unwrap();
+		assert_eq!(exps.len(), 5);
+		assert_eq!(
+			exps[2].angle,
+			PauliAngle::Parameter {
+				neg: true,
+				name: String::from("a")
+			}
+		);
+		assert_eq!(exps[3].angle, PauliAngle::MultipleOfPi(0.36));
+
+		let mut output = Vec::new();
+		PauliExp::write_exp(&exps, &mut output).unwrap();
+		let again = PauliExp::read_exp(output.as_slice()).unwrap();
+		for (a, b) in exps.iter().zip(again.iter()) {
+			assert_eq!(a.angle, b.angle);
+			assert_eq!(a.string, b.string);
+		}
+	}
+
+	#[test]
+	fn invalid_letter_location() {
+		let input = "0.25;XIIX\n\n0.1;XIQX\n";
+		let err = PauliExp::read_exp(input.as_bytes()).unwrap_err();
+		assert_eq!(err.line, 3);
+		assert_eq!(err.column, 7);
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::InvalidLetter('Q')
+		));
+	}
+
+	#[test]
+	fn missing_separator_and_empty_angle() {
+		let err = PauliExp::read_exp("0.25 XIIX".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 10));
+		assert!(matches!(err.reason, ExpParseErrorReason::MissingSeparator));
+
+		let err = PauliExp::read_exp("0.1;X\n-;XX".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (2, 1));
+		assert!(matches!(err.reason, ExpParseErrorReason::EmptyAngle));
+	}
+
+	#[test]
+	fn extended_format() {
+		let input = "#!exp 2
+#!qubits 20
+#!molecule BeH2
+#!encoding JW
+#!basis sto3g
+#!source pauliopt
+# A comment line
+
+0.5*theta1 + 0.125;X3 Z17
+-t_1;XXIY # trailing comment
+theta1 - 2*phi/4;IZ
+";
+		let (header, exps) = PauliExp::read_exp_with_header(input.as_bytes()).unwrap();
+		assert_eq!(header.version, 2);
+		assert_eq!(header.qubits, Some(20));
+		assert_eq!(header.molecule.as_deref(), Some("BeH2"));
+		assert_eq!(header.encoding, Some(Encoding::JordanWigner));
+		assert_eq!(header.basis.as_deref(), Some("sto3g"));
+		assert_eq!(header.metadata.get("source").unwrap(), "pauliopt");
+
+		assert_eq!(exps.len(), 3);
+		let mut expected = AngleExpression::parameter(0.5, "theta1");
+		expected.add_constant(0.125);
+		assert_eq!(exps[0].angle, PauliAngle::Expression(expected));
+		assert_eq!(exps[0].string.as_string(), "IIIXIIIIIIIIIIIIIZ");
+		assert_eq!(
+			exps[1].angle,
+			PauliAngle::Parameter {
+				neg: true,
+				name: String::from("t_1")
+			}
+		);
+		let mut expected = AngleExpression::parameter(1.0, "theta1");
+		expected.add_term(-0.5, "phi");
+		assert_eq!(exps[2].angle, PauliAngle::Expression(expected));
+
+		// Writing and reading again keeps everything
+		let mut output = Vec::new();
+		let mut writer = ExpWriter::with_header(&mut output, &header).unwrap();
+		writer.set_sparse(true);
+		for exp in exps.iter() {
+			writer.write(exp).unwrap();
+		}
+		writer.flush().unwrap();
+		let (again_header, again) = PauliExp::read_exp_with_header(output.as_slice()).unwrap();
+		assert_eq!(header, again_header);
+		for (a, b) in exps.iter().zip(again.iter()) {
+			assert_eq!(a.angle, b.angle);
+			assert_eq!(a.string, b.string);
+		}
+	}
+
+	#[test]
+	fn extended_format_errors() {
+		let err = PauliExp::read_exp("#!qubits 4\n0.1;X4".as_bytes()).unwrap_err();
+		assert_eq!(err.line, 2);
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::QubitOutOfRange {
+				qubit: 4,
+				qubits: 4
+			}
+		));
+
+		let err = PauliExp::read_exp("0.1;X1\n#!qubits 4".as_bytes()).unwrap_err();
+		assert!(matches!(err.reason, ExpParseErrorReason::HeaderAfterData));
+
+		let err = PauliExp::read_exp("#!exp 3\n".as_bytes()).unwrap_err();
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::UnsupportedVersion(3)
+		));
+
+		let err = PauliExp::read_exp("0.1*a*b;X1".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 7));
+		assert!(matches!(err.reason, ExpParseErrorReason::InvalidAngle(_)));
+
+		// Checked before the string is allocated
+		let err = PauliExp::read_exp("#!qubits 4\n0.1;X99999999999999".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (2, 5));
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::QubitOutOfRange {
+				qubit: 99999999999999,
+				qubits: 4
+			}
+		));
+		let err = PauliExp::read_exp("0.1;Z3 X99999999999999".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 8));
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::QubitOutOfRange {
+				qubits: MAX_SPARSE_QUBITS,
+				..
+			}
+		));
+
+		let err = PauliExp::read_exp("0.1;X3 Z3".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 8));
+		assert!(matches!(err.reason, ExpParseErrorReason::DuplicateQubit(3)));
+		let err = PauliExp::read_exp("0.1;I3 Z3".as_bytes()).unwrap_err();
+		assert!(matches!(err.reason, ExpParseErrorReason::DuplicateQubit(3)));
+
+		// Any whitespace separates terms
+		let exps = PauliExp::read_exp("0.1;X3\tZ1  Y0".as_bytes()).unwrap();
+		assert_eq!(exps[0].string, crate::pauli_string!("YZIX"));
+
+		let err = PauliExp::read_exp("0.1;X1 Zq".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 8));
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::InvalidSparseTerm(_)
+		));
+		let err = PauliExp::read_exp("0.1;X+3".as_bytes()).unwrap_err();
+		assert_eq!((err.line, err.column), (1, 5));
+		assert!(matches!(
+			err.reason,
+			ExpParseErrorReason::InvalidSparseTerm(ref term) if term == "X+3"
+		));
+	}
+
+	#[test]
+	fn molecule_datasets_are_compatible() {
+		for path in std::fs::read_dir("./datasets/molecules_small").unwrap() {
+			let path = path.unwrap().path();
+			let lines = std::fs::read_to_string(&path)
+				.unwrap()
+				.lines()
+				.filter(|line| !line.trim().is_empty())
+				.count();
+			let exps = PauliExp::try_read_exp_file(&path).unwrap();
+			assert_eq!(exps.len(), lines);
+			assert!(
+				exps.iter()
+					.all(|exp| !matches!(exp.angle, PauliAngle::Expression(_)))
+			);
+		}
+	}
+
+	#[test]
+	fn reader_is_lazy_and_stops_after_error() {
+		let input = "0.1;X\n0.2;K\n0.3;Z\n";
+		let mut reader = ExpReader::new(input.as_bytes());
+		assert!(reader.next().unwrap().is_ok());
+		assert!(reader.next().unwrap().is_err());
+		assert!(reader.next().is_none());
+	}
+}
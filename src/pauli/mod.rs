@@ -1,14 +1,26 @@
 //! This module contains tools for working with Pauli exponentials.
 
+mod exp_file;
+mod parameters;
 mod pauli_angle;
 mod pauli_exp;
 mod pauli_string;
+mod pauli_sum;
 
 use std::fmt;
 
-pub use pauli_angle::{CliffordPauliAngle, Negate, PauliAngle};
+pub use exp_file::{
+	EXP_FORMAT_VERSION, Encoding, ExpHeader, ExpParseError, ExpParseErrorReason, ExpReader,
+	ExpWriter, MAX_SPARSE_QUBITS,
+};
+pub use parameters::{ParameterTable, bind, free_parameters};
+pub use pauli_angle::{
+	AngleExpression, AngleExpressionError, CLIFFORD_TOLERANCE, CliffordPauliAngle, Negate,
+	PauliAngle,
+};
 pub use pauli_exp::PauliExp;
 pub use pauli_string::PauliString;
+pub use pauli_sum::{PauliSum, PauliSumError};
 
 /// An enum with variants that correspond to the $X$, $Y$, and $Z$ Pauli matrices and $I$.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
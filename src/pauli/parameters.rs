@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{AngleExpression, CliffordPauliAngle, PauliAngle, PauliExp};
+
+/// Values for named parameters, as multiples of pi like all angles.
+///
+/// A circuit with parameters only has to be synthesized once, after that it
+/// can be bound with a new table for every evaluation:
+///
+/// ```
+/// use test_transpiler::pauli::{ParameterTable, PauliAngle, PauliExp, bind, free_parameters};
+/// use test_transpiler::pauli_string;
+///
+/// let circuit = vec![PauliExp {
+///     string: pauli_string!("XY"),
+///     angle: PauliAngle::Parameter { neg: true, name: String::from("theta") },
+/// }];
+/// assert_eq!(free_parameters(&circuit).len(), 1);
+///
+/// let mut table = ParameterTable::new();
+/// table.set("theta", 0.1);
+/// let bound = bind(&circuit, &table);
+/// assert_eq!(bound[0].angle, PauliAngle::MultipleOfPi(-0.1));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParameterTable {
+	values: BTreeMap<String, f64>,
+}
+
+impl ParameterTable {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the value of `name`, returning the previous value.
+	pub fn set(&mut self, name: &str, value: f64) -> Option<f64> {
+		self.values.insert(String::from(name), value)
+	}
+
+	pub fn get(&self, name: &str) -> Option<f64> {
+		self.values.get(name).copied()
+	}
+
+	pub fn remove(&mut self, name: &str) -> Option<f64> {
+		self.values.remove(name)
+	}
+
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	/// The (name, value) pairs sorted by name.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+		self.values
+			.iter()
+			.map(|(name, value)| (name.as_str(), *value))
+	}
+}
+
+impl<S: Into<String>> FromIterator<(S, f64)> for ParameterTable {
+	fn from_iter<I: IntoIterator<Item = (S, f64)>>(iter: I) -> Self {
+		Self {
+			values: iter
+				.into_iter()
+				.map(|(name, value)| (name.into(), value))
+				.collect(),
+		}
+	}
+}
+
+impl PauliAngle {
+	/// Replaces the parameters that have a value in `table`. Parameters
+	/// without a value are kept, so a table can be applied in parts.
+	///
+	/// A fully bound angle becomes [PauliAngle::Clifford] if it is a multiple
+	/// of $\frac{\pi}{4}$ (up to a global phase) and [PauliAngle::MultipleOfPi]
+	/// otherwise.
+	pub fn bind(&self, table: &ParameterTable) -> PauliAngle {
+		let expression = match self {
+			PauliAngle::MultipleOfPi(_) | PauliAngle::Clifford(_) => return self.clone(),
+			PauliAngle::Parameter { .. } | PauliAngle::Expression(_) => self.to_expression(),
+		};
+
+		let mut bound = AngleExpression::constant(expression.constant_part());
+		for (coefficient, name) in expression.terms() {
+			match table.get(name) {
+				Some(value) => bound.add_constant(coefficient * value),
+				None => bound.add_term(*coefficient, name),
+			}
+		}
+
+		if !bound.terms().is_empty() {
+			return bound.simplify();
+		}
+
+		let value = bound.constant_part();
+		match CliffordPauliAngle::from_multiple_of_pi(value) {
+			Some(angle) => PauliAngle::Clifford(angle),
+			None => PauliAngle::MultipleOfPi(value),
+		}
+	}
+
+	/// The names of the parameters in the angle.
+	pub fn parameters(&self) -> Vec<&str> {
+		match self {
+			PauliAngle::MultipleOfPi(_) | PauliAngle::Clifford(_) => Vec::new(),
+			PauliAngle::Parameter { name, .. } => vec![name.as_str()],
+			PauliAngle::Expression(expression) => expression
+				.terms()
+				.iter()
+				.map(|(_, name)| name.as_str())
+				.collect(),
+		}
+	}
+}
+
+/// # Bind parameters
+///
+/// Binds the parameters of every exponential in `circuit` with
+/// [PauliAngle::bind]. The strings and the amount of exponentials are kept
+/// as is, also for angles that become zero.
+pub fn bind(circuit: &[PauliExp<PauliAngle>], table: &ParameterTable) -> Vec<PauliExp<PauliAngle>> {
+	circuit
+		.iter()
+		.map(|exp| PauliExp {
+			string: exp.string.clone(),
+			angle: exp.angle.bind(table),
+		})
+		.collect()
+}
+
+/// The names of the parameters that are left in `circuit`, sorted.
+pub fn free_parameters(circuit: &[PauliExp<PauliAngle>]) -> BTreeSet<String> {
+	circuit
+		.iter()
+		.flat_map(|exp| exp.angle.parameters())
+		.map(String::from)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::pauli_string;
+
+	#[test]
+	fn bind_folds_cliffords() {
+		let mut sum = AngleExpression::parameter(0.5, "a");
+		sum.add_term(1.0, "b");
+		sum.add_constant(0.25);
+		let circuit = vec![
+			PauliExp {
+				string: pauli_string!("XX"),
+				angle: PauliAngle::Parameter {
+					neg: true,
+					name: String::from("a"),
+				},
+			},
+			PauliExp {
+				string: pauli_string!("ZZ"),
+				angle: PauliAngle::Expression(sum),
+			},
+			PauliExp {
+				string: pauli_string!("YI"),
+				angle: PauliAngle::MultipleOfPi(0.3),
+			},
+		];
+		let expected: BTreeSet<String> = ["a", "b"].into_iter().map(String::from).collect();
+		assert_eq!(free_parameters(&circuit), expected);
+
+		// Partially bound
+		let table: ParameterTable = [("a", 0.5)].into_iter().collect();
+		let partial = bind(&circuit, &table);
+		assert_eq!(
+			partial[0].angle,
+			PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2)
+		);
+		let mut remaining = AngleExpression::parameter(1.0, "b");
+		remaining.add_constant(0.5);
+		assert_eq!(partial[1].angle, PauliAngle::Expression(remaining));
+		assert_eq!(free_parameters(&partial).len(), 1);
+
+		// 0.5 * 0.5 + 0.25 + 1.25 is 1.75, which is -pi/4 up to a global phase
+		let mut table = table;
+		table.set("b", 1.25);
+		let bound = bind(&circuit, &table);
+		assert_eq!(
+			bound[1].angle,
+			PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4)
+		);
+		assert_eq!(bound[2].angle, PauliAngle::MultipleOfPi(0.3));
+		assert!(free_parameters(&bound).is_empty());
+
+		table.set("b", 0.1);
+		assert_eq!(circuit[1].angle.bind(&table), PauliAngle::MultipleOfPi(0.6));
+	}
+
+	#[test]
+	fn bind_keeps_non_finite_values() {
+		let angle = PauliAngle::Parameter {
+			neg: false,
+			name: String::from("a"),
+		};
+		for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+			assert_eq!(CliffordPauliAngle::from_multiple_of_pi(value), None);
+			let table: ParameterTable = [("a", value)].into_iter().collect();
+			match angle.bind(&table) {
+				PauliAngle::MultipleOfPi(bound) => {
+					assert_eq!(bound.is_nan(), value.is_nan());
+					assert!(!bound.is_finite());
+				}
+				bound => panic!("{value} was bound to {bound:?}"),
+			}
+		}
+	}
+}
@@ -1,3 +1,5 @@
+use std::{fmt, str::FromStr};
+
 #[allow(unused_imports)] // This is for documentation
 use super::PauliExp;
 
@@ -28,18 +30,74 @@ impl Negate for CliffordPauliAngle {
 	}
 }
 
+/// Angles closer than this to a multiple of $\frac{\pi}{4}$ are treated as
+/// Clifford.
+pub const CLIFFORD_TOLERANCE: f64 = 1e-10;
+
+impl CliffordPauliAngle {
+	/// The Clifford angle that equals `value` times pi up to a global phase,
+	/// or `None` if `value` is not a multiple of $\frac{1}{4}$ or not finite.
+	pub fn from_multiple_of_pi(value: f64) -> Option<Self> {
+		if !value.is_finite() {
+			return None;
+		}
+
+		// $e^{i(\theta+\pi)P}=-e^{i\theta P}$, so only the angle modulo pi matters
+		let reduced = if value.abs() <= 0.5 {
+			value
+		} else {
+			value - value.round()
+		};
+		let quarters = (reduced * 4.0).round();
+		if (reduced * 4.0 - quarters).abs() >= CLIFFORD_TOLERANCE {
+			return None;
+		}
+
+		Some(match quarters as i8 {
+			-2 => CliffordPauliAngle::NegPiOver2,
+			-1 => CliffordPauliAngle::NegPiOver4,
+			0 => CliffordPauliAngle::Zero,
+			1 => CliffordPauliAngle::PiOver4,
+			_ => CliffordPauliAngle::PiOver2,
+		})
+	}
+
+	pub fn as_multiple_of_pi(self) -> f64 {
+		match self {
+			CliffordPauliAngle::NegPiOver2 => -0.5,
+			CliffordPauliAngle::NegPiOver4 => -0.25,
+			CliffordPauliAngle::Zero => 0.0,
+			CliffordPauliAngle::PiOver4 => 0.25,
+			CliffordPauliAngle::PiOver2 => 0.5,
+		}
+	}
+}
+
 /// An angle for [PauliExp] that can be whatever.
 #[derive(Debug, Clone, PartialEq)]
 pub enum PauliAngle {
 	MultipleOfPi(f64),
 	Clifford(CliffordPauliAngle),
 	Parameter { neg: bool, name: String },
+	Expression(AngleExpression),
 }
 
 impl PauliAngle {
 	pub fn is_clifford(&self) -> bool {
 		matches!(self, PauliAngle::Clifford(_))
 	}
+
+	/// The angle as a linear combination of parameters.
+	pub fn to_expression(&self) -> AngleExpression {
+		match self {
+			PauliAngle::MultipleOfPi(v) => AngleExpression::constant(*v),
+			PauliAngle::Clifford(angle) => AngleExpression::constant(angle.as_multiple_of_pi()),
+			PauliAngle::Parameter { neg, name } => {
+				AngleExpression::parameter(if *neg { -1.0 } else { 1.0 }, name)
+			}
+			PauliAngle::Expression(expression) => expression.clone(),
+		}
+	}
 }
 
 impl Negate for PauliAngle {
@@ -48,6 +106,7 @@ impl Negate for PauliAngle {
 			PauliAngle::MultipleOfPi(v) => *v = -*v,
 			PauliAngle::Clifford(v) => v.negate(),
 			PauliAngle::Parameter { neg, .. } => *neg = !*neg,
+			PauliAngle::Expression(expression) => expression.negate(),
 		}
 	}
 }
@@ -57,3 +116,303 @@ impl From<CliffordPauliAngle> for PauliAngle {
 		Self::Clifford(value)
 	}
 }
+
+/// A linear combination of named parameters and a constant, for example
+/// `0.5*theta1 + 0.125`. Like all angles, the value is a multiple of pi.
+///
+/// The terms are kept sorted by name and without zero coefficients.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AngleExpression {
+	constant: f64,
+	terms: Vec<(f64, String)>,
+}
+
+impl AngleExpression {
+	pub fn constant(value: f64) -> Self {
+		Self {
+			constant: value,
+			terms: Vec::new(),
+		}
+	}
+
+	pub fn parameter(coefficient: f64, name: &str) -> Self {
+		let mut expression = Self::default();
+		expression.add_term(coefficient, name);
+		expression
+	}
+
+	pub fn constant_part(&self) -> f64 {
+		self.constant
+	}
+
+	/// The (coefficient, name) pairs sorted by name.
+	pub fn terms(&self) -> &[(f64, String)] {
+		&self.terms
+	}
+
+	pub fn add_constant(&mut self, value: f64) {
+		self.constant += value;
+	}
+
+	pub fn add_term(&mut self, coefficient: f64, name: &str) {
+		match self
+			.terms
+			.binary_search_by(|(_, other)| other.as_str().cmp(name))
+		{
+			Ok(i) => {
+				self.terms[i].0 += coefficient;
+				if self.terms[i].0 == 0.0 {
+					self.terms.remove(i);
+				}
+			}
+			Err(i) => {
+				if coefficient != 0.0 {
+					self.terms.insert(i, (coefficient, String::from(name)));
+				}
+			}
+		}
+	}
+
+	pub fn add_expression(&mut self, other: &Self) {
+		self.constant += other.constant;
+		for (coefficient, name) in other.terms.iter() {
+			self.add_term(*coefficient, name);
+		}
+	}
+
+	pub fn scale(&mut self, factor: f64) {
+		self.constant *= factor;
+		for (coefficient, _) in self.terms.iter_mut() {
+			*coefficient *= factor;
+		}
+		self.terms.retain(|(coefficient, _)| *coefficient != 0.0);
+	}
+
+	/// Converts the expression into the simplest [PauliAngle] that represents it.
+	pub fn simplify(self) -> PauliAngle {
+		match self.terms.as_slice() {
+			[] => match self.constant {
+				0.5 => PauliAngle::Clifford(CliffordPauliAngle::PiOver2),
+				0.25 => PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+				0.0 => PauliAngle::Clifford(CliffordPauliAngle::Zero),
+				-0.25 => PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4),
+				-0.5 => PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2),
+				v => PauliAngle::MultipleOfPi(v),
+			},
+			[(coefficient, name)] if self.constant == 0.0 && coefficient.abs() == 1.0 => {
+				PauliAngle::Parameter {
+					neg: *coefficient < 0.0,
+					name: name.clone(),
+				}
+			}
+			_ => PauliAngle::Expression(self),
+		}
+	}
+}
+
+impl Negate for AngleExpression {
+	fn negate(&mut self) {
+		self.scale(-1.0);
+	}
+}
+
+impl fmt::Display for AngleExpression {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut first = true;
+		for (coefficient, name) in self.terms.iter() {
+			let sign = if *coefficient < 0.0 { "-" } else { "+" };
+			match (first, sign) {
+				(true, "+") => {}
+				(true, _) => f.write_str("-")?,
+				(false, _) => write!(f, " {sign} ")?,
+			}
+			match coefficient.abs() {
+				1.0 => f.write_str(name)?,
+				v => write!(f, "{v}*{name}")?,
+			}
+			first = false;
+		}
+
+		if first {
+			write!(f, "{}", self.constant)
+		} else if self.constant < 0.0 {
+			write!(f, " - {}", -self.constant)
+		} else if self.constant > 0.0 {
+			write!(f, " + {}", self.constant)
+		} else {
+			Ok(())
+		}
+	}
+}
+
+/// An error from parsing an [AngleExpression], `position` is the character
+/// index where the problem was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AngleExpressionError {
+	pub position: usize,
+	pub message: &'static str,
+}
+
+impl fmt::Display for AngleExpressionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} (at character {})", self.message, self.position)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Number(f64),
+	Name(String),
+	Plus,
+	Minus,
+	Times,
+	Divide,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, AngleExpressionError> {
+	let chars: Vec<char> = input.chars().collect();
+	let mut tokens = Vec::new();
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		let start = i;
+		match c {
+			' ' | '\t' => {
+				i += 1;
+				continue;
+			}
+			'+' => tokens.push((start, Token::Plus)),
+			'-' => tokens.push((start, Token::Minus)),
+			'*' => tokens.push((start, Token::Times)),
+			'/' => tokens.push((start, Token::Divide)),
+			c if c.is_ascii_digit() || c == '.' => {
+				while i + 1 < chars.len()
+					&& (chars[i + 1].is_ascii_digit()
+						|| chars[i + 1] == '.'
+						|| matches!(chars[i + 1], 'e' | 'E')
+						|| (matches!(chars[i + 1], '+' | '-') && matches!(chars[i], 'e' | 'E')))
+				{
+					i += 1;
+				}
+				let text: String = chars[start..=i].iter().collect();
+				let value = text.parse::<f64>().map_err(|_| AngleExpressionError {
+					position: start,
+					message: "invalid number",
+				})?;
+				tokens.push((start, Token::Number(value)));
+			}
+			c if c.is_alphabetic() || c == '_' => {
+				while i + 1 < chars.len() && (chars[i + 1].is_alphanumeric() || chars[i + 1] == '_')
+				{
+					i += 1;
+				}
+				tokens.push((start, Token::Name(chars[start..=i].iter().collect())));
+			}
+			_ => {
+				return Err(AngleExpressionError {
+					position: start,
+					message: "unexpected character",
+				});
+			}
+		}
+		i += 1;
+	}
+
+	Ok(tokens)
+}
+
+impl FromStr for AngleExpression {
+	type Err = AngleExpressionError;
+
+	/// Parses sums of terms, where each term is a product of numbers and at
+	/// most one parameter name. Division is only allowed by numbers.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let tokens = tokenize(s)?;
+		let end = s.chars().count();
+		let mut expression = AngleExpression::default();
+		let mut iter = tokens.into_iter().peekable();
+
+		if iter.peek().is_none() {
+			return Err(AngleExpressionError {
+				position: 0,
+				message: "empty expression",
+			});
+		}
+
+		loop {
+			// Sign of the term
+			let mut sign = 1.0;
+			while let Some((_, token)) = iter.peek() {
+				match token {
+					Token::Plus => {}
+					Token::Minus => sign = -sign,
+					_ => break,
+				}
+				iter.next();
+			}
+
+			// The term itself
+			let mut coefficient = sign;
+			let mut name: Option<String> = None;
+			let mut divide = false;
+			loop {
+				match iter.next() {
+					Some((_, Token::Number(v))) => {
+						if divide {
+							coefficient /= v;
+						} else {
+							coefficient *= v;
+						}
+					}
+					Some((position, Token::Name(n))) => {
+						if divide || name.is_some() {
+							return Err(AngleExpressionError {
+								position,
+								message: "terms can only be linear in one parameter",
+							});
+						}
+						name = Some(n);
+					}
+					Some((position, _)) => {
+						return Err(AngleExpressionError {
+							position,
+							message: "expected a number or a parameter",
+						});
+					}
+					None => {
+						return Err(AngleExpressionError {
+							position: end,
+							message: "expected a number or a parameter",
+						});
+					}
+				}
+
+				match iter.peek() {
+					Some((_, Token::Times)) => divide = false,
+					Some((_, Token::Divide)) => divide = true,
+					_ => break,
+				}
+				iter.next();
+			}
+
+			match name {
+				Some(name) => expression.add_term(coefficient, &name),
+				None => expression.add_constant(coefficient),
+			}
+
+			// The '+' or '-' is handled as the sign of the next term
+			match iter.peek() {
+				None => break,
+				Some((_, Token::Plus | Token::Minus)) => {}
+				Some((position, _)) => {
+					return Err(AngleExpressionError {
+						position: *position,
+						message: "expected '+' or '-'",
+					});
+				}
+			}
+		}
+
+		Ok(expression)
+	}
+}
@@ -1,15 +1,11 @@
-use std::{
-	fs::{File, exists},
-	io::{self, BufRead, Write},
-	path::Path,
-};
+use std::{fs::exists, path::Path};
 
-use crate::pauli::{CliffordPauliAngle, PauliAngle, PauliLetter, pauli_angle::Negate};
+use crate::pauli::{CliffordPauliAngle, PauliAngle, pauli_angle::Negate};
 
 use super::PauliString;
 
 /// An Pauli exponential $e^{i\theta P}$ where $\theta$ is a [PauliAngle] and $P$ a [PauliString].
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PauliExp<T: Negate> {
 	pub string: PauliString,
 	pub angle: T,
@@ -58,72 +54,46 @@ impl<T: Negate> PauliExp<T> {
 			self.angle.negate();
 		}
 	}
+
+	/// Moves the Clifford $C$ from before `self` to after it (in time).
+	///
+	/// Because $e^{i\theta P}C=Ce^{i\theta C^\dagger PC}$, `self` is converted from
+	/// $e^{i\theta P}$ to $e^{i\theta C^\dagger PC}$.
+	pub fn move_clifford_past(&mut self, clifford: &PauliExp<CliffordPauliAngle>) {
+		match clifford.angle {
+			CliffordPauliAngle::PiOver4 => self.push_pi_over_4(true, &clifford.string),
+			CliffordPauliAngle::NegPiOver4 => self.push_pi_over_4(false, &clifford.string),
+			// $C^\dagger PC=\pm P$, see [crate::clifford_tableau::CliffordTableau::merge_clifford]
+			CliffordPauliAngle::PiOver2 | CliffordPauliAngle::NegPiOver2 => {
+				if self.string.anticommutes_with(&clifford.string) {
+					self.angle.negate();
+				}
+			}
+			CliffordPauliAngle::Zero => {}
+		}
+	}
 }
 
 impl PauliExp<PauliAngle> {
-	pub fn write_exp_file(exps: &Vec<Self>, path: &str) {
+	/// # Panics
+	/// Panics if the file already exists or can not be written. See
+	/// [PauliExp::try_write_exp_file] for a fallible version.
+	pub fn write_exp_file(exps: &[Self], path: &str) {
 		if exists(path).unwrap() {
 			panic!("Tried to overwrite a file");
 		}
 
-		let mut file = File::create(path).unwrap();
-		for pauli in exps {
-			let angle = match &pauli.angle {
-				PauliAngle::MultipleOfPi(v) => format!("{v}"),
-				PauliAngle::Parameter { neg: false, name } => name.clone(),
-				PauliAngle::Parameter { neg: true, name } => format!("-{name}"),
-				PauliAngle::Clifford(CliffordPauliAngle::PiOver2) => String::from("0.5"),
-				PauliAngle::Clifford(CliffordPauliAngle::PiOver4) => String::from("0.25"),
-				PauliAngle::Clifford(CliffordPauliAngle::Zero) => String::from("0.0"),
-				PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4) => String::from("-0.25"),
-				PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2) => String::from("-0.5"),
-			};
-			let string = pauli.string.as_string();
-			writeln!(&mut file, "{angle};{string}").unwrap();
-		}
-		file.flush().expect("Failed to write to file");
+		Self::try_write_exp_file(exps, path).expect("Failed to write to file");
 	}
 
+	/// # Panics
+	/// Panics if the file can not be read or is malformed. See
+	/// [PauliExp::try_read_exp_file] for a fallible version.
 	pub fn read_exp_file<P: AsRef<Path>>(path: P) -> Vec<Self> {
-		let file = File::open(path).expect("Failed to open file");
-		io::BufReader::new(file)
-			.lines()
-			.map(|line| {
-				let line = line.expect("Failed to read file");
-				let (angle, letters) = line.split_once(';').expect("File format is wrong");
-				let angle = match angle.parse::<f64>() {
-					Ok(0.5) => PauliAngle::Clifford(CliffordPauliAngle::PiOver2),
-					Ok(0.25) => PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
-					Ok(0.0) => PauliAngle::Clifford(CliffordPauliAngle::Zero),
-					Ok(-0.25) => PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4),
-					Ok(-0.5) => PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2),
-					Ok(v) => PauliAngle::MultipleOfPi(v),
-					Err(_) => match angle.strip_prefix('-') {
-						Some(angle) => PauliAngle::Parameter {
-							neg: true,
-							name: String::from(angle),
-						},
-						_ => PauliAngle::Parameter {
-							neg: false,
-							name: String::from(angle),
-						},
-					},
-				};
-
-				let mut string = PauliString::id();
-				for (i, letter) in letters.chars().enumerate() {
-					match letter {
-						'X' => string.set(i, PauliLetter::X),
-						'Y' => string.set(i, PauliLetter::Y),
-						'Z' => string.set(i, PauliLetter::Z),
-						'I' => {}
-						_ => panic!("File format error, PauliString contains non-valid letters"),
-					}
-				}
-
-				PauliExp { string, angle }
-			})
-			.collect()
+		match Self::try_read_exp_file(path) {
+			Ok(exps) => exps,
+			Err(err) => panic!("Failed to read file: {err}"),
+		}
 	}
 }
 
@@ -177,6 +177,15 @@ impl PauliString {
 		(&self.x | &self.z).iter_ones().collect()
 	}
 
+	/// The x and z bits as masks, where bit `i` belongs to qubit `i`. A $Y$
+	/// sets both. `None` if the string does not fit into a [usize].
+	pub fn masks(&self) -> Option<(usize, usize)> {
+		Some((
+			usize::try_from(self.x.clone()).ok()?,
+			usize::try_from(self.z.clone()).ok()?,
+		))
+	}
+
 	pub fn letters(&self) -> LetterIterator<'_> {
 		LetterIterator {
 			x: self.x.iter_ones(),
@@ -298,6 +307,34 @@ impl PauliString {
 		sign
 	}
 
+	/// # Multiply
+	///
+	/// Replaces `self` $P$ with the string of $PQ$, where $Q$ is `other`, and
+	/// returns the $k$ of the phase in $PQ=i^kR$.
+	///
+	/// Per qubit $XY=iZ$, $YZ=iX$ and $ZX=iY$ give $+1$, the reversed products
+	/// give $-1$, and everything else gives nothing.
+	pub fn multiply(&mut self, other: &Self) -> u8 {
+		let y = &self.x & &self.z;
+		let x = self.x.and_not(&self.z);
+		let z = self.z.and_not(&self.x);
+		let other_y = &other.x & &other.z;
+		let other_x = other.x.and_not(&other.z);
+		let other_z = other.z.and_not(&other.x);
+
+		let plus = (&x & &other_y).count_ones()
+			+ (&y & &other_z).count_ones()
+			+ (&z & &other_x).count_ones();
+		let minus = (&y & &other_x).count_ones()
+			+ (&z & &other_y).count_ones()
+			+ (&x & &other_z).count_ones();
+
+		self.x ^= &other.x;
+		self.z ^= &other.z;
+
+		((4 + plus % 4 - minus % 4) % 4) as u8
+	}
+
 	pub fn len(&self) -> usize {
 		(&self.x | &self.z).count_ones()
 	}
@@ -609,4 +646,21 @@ mod tests {
 		assert_eq!(pauli_string!("XX").steps_to_len_one(n), 3);
 		assert_eq!(pauli_string!("XXXXX").steps_to_len_one(n), 2);
 	}
+
+	#[test]
+	fn multiply_tracks_phase() {
+		// $(XYZ)(ZXY)=(-iY)(-iZ)(-iX)$
+		let mut p = pauli_string!("XYZ");
+		assert_eq!(p.multiply(&pauli_string!("ZXY")), 1);
+		assert_eq!(p, pauli_string!("YZX"));
+
+		// $(XX)(YY)=(iZ)(iZ)$
+		let mut p = pauli_string!("XX");
+		assert_eq!(p.multiply(&pauli_string!("YYI")), 2);
+		assert_eq!(p, pauli_string!("ZZ"));
+
+		let mut p = pauli_string!("IZ");
+		assert_eq!(p.multiply(&pauli_string!("IZ")), 0);
+		assert!(p.is_empty());
+	}
 }
@@ -0,0 +1,146 @@
+use std::{error::Error, fmt, path::Path};
+
+use super::{ExpParseError, PauliAngle, PauliExp, PauliString};
+
+/// A Hermitian operator $\sum_jc_jP_j$ with real coefficients.
+///
+/// In an `.exp` file the angle column is read as the coefficient, so the
+/// terms of a Hamiltonian are stored like the exponentials of a circuit.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PauliSum {
+	pub terms: Vec<(f64, PauliString)>,
+}
+
+/// The reason why exponentials can not be read as a [PauliSum].
+#[derive(Debug)]
+pub enum PauliSumError {
+	Parse(ExpParseError),
+	/// The coefficient of term `index` still has parameters, see
+	/// [super::bind].
+	UnboundParameter {
+		index: usize,
+		angle: String,
+	},
+}
+
+impl fmt::Display for PauliSumError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			PauliSumError::Parse(err) => write!(f, "{err}"),
+			PauliSumError::UnboundParameter { index, angle } => {
+				write!(f, "the coefficient {angle} of term {index} is not a number")
+			}
+		}
+	}
+}
+
+impl Error for PauliSumError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			PauliSumError::Parse(err) => Some(err),
+			PauliSumError::UnboundParameter { .. } => None,
+		}
+	}
+}
+
+impl From<ExpParseError> for PauliSumError {
+	fn from(value: ExpParseError) -> Self {
+		PauliSumError::Parse(value)
+	}
+}
+
+impl PauliSum {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn push(&mut self, coefficient: f64, string: PauliString) {
+		self.terms.push((coefficient, string));
+	}
+
+	pub fn len(&self) -> usize {
+		self.terms.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.terms.is_empty()
+	}
+
+	/// The amount of qubits up to the last non-identity letter.
+	pub fn n_qubits(&self) -> usize {
+		self.terms
+			.iter()
+			.map(|(_, string)| string.size())
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Takes the angles of `exps` as coefficients. They have to be bound.
+	pub fn try_from_exps(exps: &[PauliExp<PauliAngle>]) -> Result<Self, PauliSumError> {
+		let mut sum = Self::new();
+		for (index, exp) in exps.iter().enumerate() {
+			let angle = exp.angle.to_expression();
+			if !angle.terms().is_empty() {
+				return Err(PauliSumError::UnboundParameter {
+					index,
+					angle: format!("{angle}"),
+				});
+			}
+			sum.push(angle.constant_part(), exp.string.clone());
+		}
+		Ok(sum)
+	}
+
+	pub fn try_read_exp_file<P: AsRef<Path>>(path: P) -> Result<Self, PauliSumError> {
+		Self::try_from_exps(&PauliExp::try_read_exp_file(path)?)
+	}
+
+	/// Adds up the coefficients of equal strings and removes the terms that
+	/// are zero. The first occurrence of a string decides its position.
+	pub fn simplify(&mut self) {
+		let mut terms: Vec<(f64, PauliString)> = Vec::new();
+		for (coefficient, string) in self.terms.drain(..) {
+			match terms.iter_mut().find(|(_, other)| *other == string) {
+				Some((total, _)) => *total += coefficient,
+				None => terms.push((coefficient, string)),
+			}
+		}
+		terms.retain(|(coefficient, _)| *coefficient != 0.0);
+		self.terms = terms;
+	}
+}
+
+impl FromIterator<(f64, PauliString)> for PauliSum {
+	fn from_iter<I: IntoIterator<Item = (f64, PauliString)>>(iter: I) -> Self {
+		Self {
+			terms: iter.into_iter().collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::pauli_string;
+
+	#[test]
+	fn read_and_simplify() {
+		let input = "0.5;XX\n-0.25;ZI\n0.5;XX\n0.25;ZI\n-1;IY\n";
+		let exps = PauliExp::read_exp(input.as_bytes()).unwrap();
+		let mut sum = PauliSum::try_from_exps(&exps).unwrap();
+		assert_eq!(sum.len(), 5);
+		assert_eq!(sum.n_qubits(), 2);
+
+		sum.simplify();
+		assert_eq!(
+			sum.terms,
+			vec![(1.0, pauli_string!("XX")), (-1.0, pauli_string!("IY"))]
+		);
+
+		let exps = PauliExp::read_exp("0.5;XX\nt;ZZ\n".as_bytes()).unwrap();
+		assert!(matches!(
+			PauliSum::try_from_exps(&exps),
+			Err(PauliSumError::UnboundParameter { index: 1, .. })
+		));
+	}
+}
@@ -0,0 +1,473 @@
+//! Phase polynomials of diagonal exponentials.
+//!
+//! Exponentials of strings with only $Z$ and $I$ commute and act on a basis
+//! state $|x\rangle$ as a phase. $e^{i\theta Z_S}|x\rangle=e^{i\theta(-1)^{x_S}}|x\rangle$
+//! only depends on the parity $x_S$ of the qubits in $S$, so after CNOTs
+//! that put $x_S$ on a qubit $t$, the exponential is $e^{i\theta Z_t}$ on that
+//! qubit. This makes such a block, for example the exponentials of a
+//! [crate::partition::Diagonalisation], a phase polynomial that the
+//! algorithms of [cnot_parity_matrix] synthesise with CNOTs.
+//!
+//! The resulting circuits turn back into exponentials with [ToExps], so they
+//! can be compared against [crate::synthesize] and checked with
+//! [crate::verify::verify_equivalence].
+
+use std::{collections::BTreeMap, num::NonZeroU32};
+
+use bits::Bits;
+use circuit::{
+	Circuit,
+	gates::{CNot, H, Rz, X, Y},
+};
+use cnot_parity_matrix::{
+	ParityMatrix,
+	algorithm::{ParityVisitor, PatelMarkovHayes},
+	gateset::{CNotRzXYH, QuarterPi},
+};
+use test_core::Compiler;
+
+use crate::pauli::{
+	AngleExpression, CLIFFORD_TOLERANCE, CliffordPauliAngle, PauliAngle, PauliExp, PauliLetter,
+	PauliString,
+};
+
+/// $\left(\prod_Se^{i\theta_SZ_S}\right)$ followed by the CNOTs of `linear`,
+/// with every parity $S$ as [Bits] over the input qubits.
+#[derive(Debug, Clone)]
+pub struct PhasePolynomial {
+	/// Every parity at most once and never empty.
+	pub terms: Vec<(Bits, PauliAngle)>,
+	/// Row `i` is the parity on qubit `i` at the end.
+	pub linear: ParityMatrix,
+}
+
+/// A gate of a synthesised [PhasePolynomial].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhaseGate {
+	CNot(CNot),
+	/// $e^{i\theta Z_{\text{target}}}$
+	Rotation {
+		target: usize,
+		angle: PauliAngle,
+	},
+}
+
+impl From<CNot> for PhaseGate {
+	fn from(value: CNot) -> Self {
+		PhaseGate::CNot(value)
+	}
+}
+
+impl PhasePolynomial {
+	/// The phase polynomial of a block of diagonal exponentials with an
+	/// identity linear part. Gives [None] if a string has an $X$ or $Y$.
+	///
+	/// Exponentials of the same string are added up, the ones without a
+	/// letter or with an angle of zero only add a global phase and are left
+	/// out.
+	pub fn from_exps(exps: &[PauliExp<PauliAngle>]) -> Option<Self> {
+		let mut terms: Vec<(Bits, AngleExpression)> = Vec::new();
+		for exp in exps.iter() {
+			let mut parity = Bits::new();
+			for (qubit, letter) in exp.string.letters() {
+				if letter != PauliLetter::Z {
+					return None;
+				}
+				parity.set(qubit, true);
+			}
+			if parity.is_all_zero() {
+				continue;
+			}
+
+			let angle = exp.angle.to_expression();
+			match terms.iter_mut().find(|(other, _)| *other == parity) {
+				Some((_, total)) => total.add_expression(&angle),
+				None => terms.push((parity, angle)),
+			}
+		}
+
+		Some(Self {
+			terms: terms
+				.into_iter()
+				.filter(|(_, angle)| !angle.terms().is_empty() || angle.constant_part() != 0.0)
+				.map(|(parity, angle)| (parity, angle.simplify()))
+				.collect(),
+			linear: ParityMatrix::standard_basis(),
+		})
+	}
+
+	/// The amount of qubits up to the last one that is used.
+	pub fn n_qubits(&self) -> usize {
+		self.terms
+			.iter()
+			.filter_map(|(parity, _)| parity.last_one())
+			.map(|qubit| qubit + 1)
+			.chain([self.linear.size()])
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// # Synthesise
+	///
+	/// Places the rotations with the CNOTs of `visitor`, like the phase
+	/// polynomial part of [cnot_parity_matrix::algorithm::TPar], and then
+	/// reaches `linear` with the CNOTs of `solver`. Parities of a single qubit
+	/// are placed before any CNOT.
+	///
+	/// The visitor and the solver have their own device, so for example
+	/// [cnot_parity_matrix::algorithm::GrayStar] can be combined with
+	/// [cnot_parity_matrix::algorithm::RowCol].
+	///
+	/// # Panics
+	/// Panics if the visitor does not visit every parity or the solver does
+	/// not reach `linear`.
+	pub fn synthesize<
+		DV,
+		DM,
+		V: ParityVisitor<DV>,
+		M: Compiler<ParityMatrix, Circuit<CNot>, DM>,
+	>(
+		&self,
+		visitor: &V,
+		visitor_device: &DV,
+		solver: &M,
+		solver_device: &DM,
+	) -> Circuit<PhaseGate> {
+		let n = self.n_qubits();
+		let mut terms: BTreeMap<Bits, PauliAngle> = self.terms.iter().cloned().collect();
+		let mut state: Vec<Bits> = (0..n).map(Bits::with_one).collect();
+		let mut output = Circuit::new();
+
+		for (target, parity) in state.iter().enumerate() {
+			if let Some(angle) = terms.remove(parity) {
+				output.push(PhaseGate::Rotation { target, angle });
+			}
+		}
+
+		// The state is still the identity, so the parities are already in its
+		// span
+		let required = terms.keys().cloned().collect();
+		for cnot in visitor.visit(required, Vec::new(), visitor_device) {
+			apply_cnot(&mut state, cnot);
+			output.push(cnot);
+			if let Some(angle) = terms.remove(&state[cnot.target()]) {
+				output.push(PhaseGate::Rotation {
+					target: cnot.target(),
+					angle,
+				});
+			}
+		}
+		assert!(terms.is_empty(), "Visitor does not visit all parities");
+
+		let span = ParityMatrix::standard_from_rows(state.clone());
+		let rows = (0..n)
+			.map(|i| span.span_bits(&self.linear.get_row(i)).unwrap())
+			.collect();
+		for cnot in solver.compile(ParityMatrix::standard_from_rows(rows), solver_device) {
+			apply_cnot(&mut state, cnot);
+			output.push(cnot);
+		}
+		for (i, row) in state.iter().enumerate() {
+			assert_eq!(*row, self.linear.get_row(i), "ParityMatrix solving failed");
+		}
+
+		output
+	}
+
+	/// # TPar input
+	///
+	/// A straightforward circuit for [cnot_parity_matrix::algorithm::TPar],
+	/// which computes every parity on its first qubit with a ladder of CNOTs
+	/// and uncomputes it again. Gives [None] if an angle is not a multiple of
+	/// $\frac{\pi}{8}$, since the gate set only has $R_z$ rotations by
+	/// multiples of $\frac{\pi}{4}$.
+	pub fn t_par_circuit(&self) -> Option<Circuit<CNotRzXYH>> {
+		let mut circuit = Circuit::new();
+		for (parity, angle) in self.terms.iter() {
+			let angle = angle.to_expression();
+			let eighths = angle.constant_part() * 8.0;
+			if !angle.terms().is_empty() || (eighths - eighths.round()).abs() >= CLIFFORD_TOLERANCE
+			{
+				return None;
+			}
+
+			let mut qubits = parity.iter_ones();
+			let target = qubits.next().unwrap();
+			let ladder: Vec<CNot> = qubits
+				.map(|control| CNot::new(control, target).unwrap())
+				.collect();
+			for cnot in ladder.iter() {
+				circuit.push(*cnot);
+			}
+			// $e^{i\theta Z}=e^{i\theta}R_z(-2\theta)$
+			circuit.push(Rz {
+				angle: QuarterPi((-(eighths.round() as i64)).rem_euclid(8) as u32),
+				target,
+			});
+			for cnot in ladder.iter().rev() {
+				circuit.push(*cnot);
+			}
+		}
+
+		let solver = PatelMarkovHayes::new(NonZeroU32::MIN);
+		for cnot in solver.compile(self.linear.clone(), &()) {
+			circuit.push(cnot);
+		}
+		Some(circuit)
+	}
+}
+
+fn apply_cnot(state: &mut [Bits], cnot: CNot) {
+	let control = state[cnot.control()].clone();
+	state[cnot.target()] ^= control;
+}
+
+/// Gates that are equal to a sequence of exponentials up to a global phase.
+pub trait ToExps {
+	/// Index 0 is applied first.
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>>;
+}
+
+fn exp(string: PauliString, angle: CliffordPauliAngle) -> PauliExp<PauliAngle> {
+	PauliExp {
+		string,
+		angle: angle.into(),
+	}
+}
+
+impl ToExps for CNot {
+	/// $\text{CNOT}_{ct}=e^{i\frac{\pi}{4}(I-Z_c-X_t+Z_cX_t)}$ with three
+	/// commuting exponentials.
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>> {
+		let mut both = PauliString::z(self.control());
+		both.set(self.target(), PauliLetter::X);
+		vec![
+			exp(both, CliffordPauliAngle::PiOver4),
+			exp(
+				PauliString::z(self.control()),
+				CliffordPauliAngle::NegPiOver4,
+			),
+			exp(
+				PauliString::x(self.target()),
+				CliffordPauliAngle::NegPiOver4,
+			),
+		]
+	}
+}
+
+impl ToExps for PhaseGate {
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>> {
+		match self {
+			PhaseGate::CNot(cnot) => cnot.to_exps(),
+			PhaseGate::Rotation { target, angle } => vec![PauliExp {
+				string: PauliString::z(*target),
+				angle: angle.clone(),
+			}],
+		}
+	}
+}
+
+impl ToExps for CNotRzXYH {
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>> {
+		match self {
+			CNotRzXYH::CNot(cnot) => cnot.to_exps(),
+			// $R_z(\frac{k\pi}{4})=e^{i\frac{k\pi}{8}}e^{-i\frac{k\pi}{8}Z}$
+			CNotRzXYH::Rz(Rz { angle, target }) => vec![PauliExp {
+				string: PauliString::z(*target),
+				angle: AngleExpression::constant(-(angle.0 as f64) / 8.0).simplify(),
+			}],
+			CNotRzXYH::X(X { target }) => {
+				vec![exp(PauliString::x(*target), CliffordPauliAngle::PiOver2)]
+			}
+			CNotRzXYH::Y(Y { target }) => {
+				vec![exp(PauliString::y(*target), CliffordPauliAngle::PiOver2)]
+			}
+			CNotRzXYH::H(H { target }) => [
+				PauliString::z(*target),
+				PauliString::x(*target),
+				PauliString::z(*target),
+			]
+			.into_iter()
+			.map(|string| exp(string, CliffordPauliAngle::PiOver4))
+			.collect(),
+		}
+	}
+}
+
+impl<G: ToExps> ToExps for Circuit<G> {
+	fn to_exps(&self) -> Vec<PauliExp<PauliAngle>> {
+		self.iter().flat_map(|gate| gate.to_exps()).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{partition::diagonalise, pauli::Negate, pauli_string, verify::verify_equivalence};
+	use cnot_parity_matrix::algorithm::{GrayStar, RowCol, TPar, TwoQubitEdge};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+	use test_core::prelude::Connectivity;
+
+	fn random_block(
+		n_qubits: usize,
+		n_exps: usize,
+		rng: &mut ChaCha8Rng,
+		angle: impl Fn(&mut ChaCha8Rng) -> PauliAngle,
+	) -> Vec<PauliExp<PauliAngle>> {
+		(0..n_exps)
+			.map(|_| {
+				let mut string = PauliString::default();
+				for i in 0..n_qubits {
+					if rng.random_bool(0.5) {
+						string.set(i, PauliLetter::Z);
+					}
+				}
+				PauliExp {
+					string,
+					angle: angle(rng),
+				}
+			})
+			.collect()
+	}
+
+	fn cnot_count<G: ToExps>(circuit: &Circuit<G>) -> usize {
+		circuit
+			.to_exps()
+			.iter()
+			.filter(|exp| exp.string.len() == 2)
+			.count()
+	}
+
+	#[test]
+	fn from_exps() {
+		let exps = vec![
+			PauliExp {
+				string: pauli_string!("ZIZ"),
+				angle: PauliAngle::MultipleOfPi(0.1),
+			},
+			PauliExp {
+				string: pauli_string!("III"),
+				angle: PauliAngle::MultipleOfPi(0.3),
+			},
+			PauliExp {
+				string: pauli_string!("ZIZ"),
+				angle: PauliAngle::MultipleOfPi(0.15),
+			},
+			PauliExp {
+				string: pauli_string!("IZ"),
+				angle: PauliAngle::MultipleOfPi(0.0),
+			},
+		];
+		let polynomial = PhasePolynomial::from_exps(&exps).unwrap();
+		let mut parity = Bits::with_one(0);
+		parity.set(2, true);
+		assert_eq!(
+			polynomial.terms,
+			vec![(parity, PauliAngle::Clifford(CliffordPauliAngle::PiOver4))]
+		);
+		assert_eq!(polynomial.n_qubits(), 3);
+
+		let exps = vec![PauliExp {
+			string: pauli_string!("ZX"),
+			angle: PauliAngle::MultipleOfPi(0.1),
+		}];
+		assert!(PhasePolynomial::from_exps(&exps).is_none());
+	}
+
+	#[test]
+	fn gray_star_and_rowcol() {
+		let mut rng = ChaCha8Rng::seed_from_u64(3);
+		let n_qubits = 5;
+		let mut line: Connectivity<TwoQubitEdge> = Connectivity::new();
+		for i in 1..n_qubits {
+			line.add_edge(TwoQubitEdge([i - 1, i]));
+		}
+
+		for _ in 0..10 {
+			let block = random_block(n_qubits, 8, &mut rng, |rng| {
+				PauliAngle::MultipleOfPi(rng.random())
+			});
+			let polynomial = PhasePolynomial::from_exps(&block).unwrap();
+
+			let solver = PatelMarkovHayes::new(NonZeroU32::new(2).unwrap());
+			let circuit = polynomial.synthesize(&GrayStar, &(), &solver, &());
+			verify_equivalence(&block, &circuit.to_exps()).unwrap();
+
+			let circuit = polynomial.synthesize(&GrayStar, &(), &RowCol, &line);
+			verify_equivalence(&block, &circuit.to_exps()).unwrap();
+		}
+	}
+
+	#[test]
+	fn t_par() {
+		let mut rng = ChaCha8Rng::seed_from_u64(4);
+		let block = random_block(4, 10, &mut rng, |rng| {
+			PauliAngle::MultipleOfPi(rng.random_range(-8..8) as f64 / 8.0)
+		});
+		let polynomial = PhasePolynomial::from_exps(&block).unwrap();
+		let input = polynomial.t_par_circuit().unwrap();
+		verify_equivalence(&block, &input.to_exps()).unwrap();
+
+		let t_par = TPar::new(GrayStar, PatelMarkovHayes::new(NonZeroU32::MIN));
+		let output = t_par.compile(input.clone(), &());
+		verify_equivalence(&block, &output.to_exps()).unwrap();
+		assert!(cnot_count(&output) <= cnot_count(&input));
+
+		// Both paths reach the same linear part
+		let mut polynomial = polynomial;
+		for _ in 0..6 {
+			polynomial.linear.insert_cnot(CNot::random(4, &mut rng));
+		}
+		let solver = PatelMarkovHayes::new(NonZeroU32::MIN);
+		let circuit = polynomial.synthesize(&GrayStar, &(), &solver, &());
+		let input = polynomial.t_par_circuit().unwrap();
+		verify_equivalence(&circuit.to_exps(), &input.to_exps()).unwrap();
+		let output = t_par.compile(input, &());
+		verify_equivalence(&circuit.to_exps(), &output.to_exps()).unwrap();
+
+		let block = vec![PauliExp {
+			string: pauli_string!("ZZ"),
+			angle: PauliAngle::MultipleOfPi(0.1),
+		}];
+		let polynomial = PhasePolynomial::from_exps(&block).unwrap();
+		assert!(polynomial.t_par_circuit().is_none());
+	}
+
+	#[test]
+	fn diagonalised_group() {
+		let group = vec![
+			PauliExp {
+				string: pauli_string!("XXI"),
+				angle: PauliAngle::MultipleOfPi(0.1),
+			},
+			PauliExp {
+				string: pauli_string!("YYI"),
+				angle: PauliAngle::MultipleOfPi(-0.2),
+			},
+			PauliExp {
+				string: pauli_string!("ZZZ"),
+				angle: PauliAngle::MultipleOfPi(0.3),
+			},
+		];
+		let diagonalisation = diagonalise(&group).unwrap();
+		let polynomial = PhasePolynomial::from_exps(&diagonalisation.exponentials).unwrap();
+		let solver = PatelMarkovHayes::new(NonZeroU32::MIN);
+		let circuit = polynomial.synthesize(&GrayStar, &(), &solver, &());
+
+		let inverse = diagonalisation.cliffords.iter().rev().map(|clifford| {
+			let mut clifford = clifford.clone();
+			clifford.angle.negate();
+			PauliExp::from(clifford)
+		});
+		let output: Vec<PauliExp<PauliAngle>> = diagonalisation
+			.cliffords
+			.iter()
+			.cloned()
+			.map(PauliExp::from)
+			.chain(circuit.to_exps())
+			.chain(inverse)
+			.collect();
+		verify_equivalence(&group, &output).unwrap();
+	}
+}
@@ -0,0 +1,318 @@
+//! Product formulas for time evolution.
+//!
+//! A [PauliSum] $H=\sum_jc_jP_j$ is turned into a sequence of exponentials
+//! that approximates $e^{-iHt}$, which is the input [crate::synthesize]
+//! expects. The exponential of a term for a time $\tau$ is
+//! $e^{-ic_j\tau P_j}$, so its angle is $-\frac{c_j\tau}{\pi}$ as a multiple
+//! of pi. Terms on no qubit only add a global phase and are left out.
+
+use std::f64::consts::PI;
+
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::pauli::{PauliAngle, PauliExp, PauliString, PauliSum};
+
+/// The product formula of one time step $\delta$.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProductFormula {
+	/// First order Trotter, every term once in order.
+	Trotter,
+	/// Second order Trotter, the terms forward for $\frac{\delta}{2}$ and
+	/// then backward. The last term is only applied once for $\delta$.
+	SecondOrderTrotter,
+	/// Suzuki's formula of the given even order, built recursively from
+	/// [ProductFormula::SecondOrderTrotter] as
+	/// $S_{2k}(\delta)=S_{2k-2}(p\delta)^2S_{2k-2}((1-4p)\delta)S_{2k-2}(p\delta)^2$
+	/// with $p=\frac{1}{4-4^{1/(2k-1)}}$.
+	Suzuki(usize),
+	/// qDRIFT, `samples` terms per step drawn with a probability
+	/// proportional to $|c_j|$. Every drawn term is applied for
+	/// $\frac{\lambda\delta}{\text{samples}}$ with $\lambda=\sum_j|c_j|$.
+	QDrift { samples: usize, seed: u64 },
+}
+
+/// # Evolution
+///
+/// Applies a [ProductFormula] `steps` times with a time step of `time_step`,
+/// so for a total time of `steps * time_step`.
+///
+/// With grouping the terms are first sorted into groups of terms that
+/// commute with each other, in order of their first term. The formulas then
+/// treat a group like a single term, since it has no Trotter error of its
+/// own. qDRIFT always draws single terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evolution {
+	pub formula: ProductFormula,
+	pub time_step: f64,
+	pub steps: usize,
+	pub group_commuting: bool,
+}
+
+impl Evolution {
+	pub fn new(formula: ProductFormula, time_step: f64, steps: usize) -> Self {
+		Self {
+			formula,
+			time_step,
+			steps,
+			group_commuting: false,
+		}
+	}
+
+	pub fn with_grouping(mut self, group_commuting: bool) -> Self {
+		self.group_commuting = group_commuting;
+		self
+	}
+
+	/// The exponentials for `hamiltonian`, index 0 first.
+	///
+	/// # Panics
+	/// Panics if a [ProductFormula::Suzuki] order is not even and positive.
+	pub fn exponentials(&self, hamiltonian: &PauliSum) -> Vec<PauliExp<PauliAngle>> {
+		let terms: Vec<&(f64, PauliString)> = hamiltonian
+			.terms
+			.iter()
+			.filter(|(coefficient, string)| *coefficient != 0.0 && !string.is_empty())
+			.collect();
+		if terms.is_empty() {
+			return Vec::new();
+		}
+
+		let exponential = |(coefficient, string): &(f64, PauliString), time: f64| PauliExp {
+			string: string.clone(),
+			angle: PauliAngle::MultipleOfPi(-coefficient * time / PI),
+		};
+
+		if let ProductFormula::QDrift { samples, seed } = self.formula {
+			let lambda: f64 = terms.iter().map(|(coefficient, _)| coefficient.abs()).sum();
+			let time = lambda * self.time_step / samples as f64;
+			let mut rng = ChaCha8Rng::seed_from_u64(seed);
+			return (0..self.steps * samples)
+				.map(|_| {
+					let (coefficient, string) = terms[draw(&terms, lambda, &mut rng)];
+					exponential(&(coefficient.signum(), string.clone()), time)
+				})
+				.collect();
+		}
+
+		let groups = if self.group_commuting {
+			commuting_groups(&terms)
+		} else {
+			(0..terms.len()).map(|i| vec![i]).collect()
+		};
+
+		let step = match self.formula {
+			ProductFormula::Trotter => (0..groups.len()).map(|group| (group, 1.0)).collect(),
+			ProductFormula::SecondOrderTrotter => suzuki(groups.len(), 2, 1.0),
+			ProductFormula::Suzuki(order) => {
+				assert!(
+					order >= 2 && order.is_multiple_of(2),
+					"Suzuki formulas have an even order, not {order}"
+				);
+				suzuki(groups.len(), order, 1.0)
+			}
+			ProductFormula::QDrift { .. } => unreachable!(),
+		};
+		let schedule = merge_neighbours((0..self.steps).flat_map(|_| step.iter().copied()));
+
+		let mut exps = Vec::new();
+		for (group, fraction) in schedule {
+			for term in groups[group].iter() {
+				exps.push(exponential(terms[*term], fraction * self.time_step));
+			}
+		}
+		exps
+	}
+}
+
+/// Draws index `j` with probability $\frac{|c_j|}{\lambda}$.
+fn draw(terms: &[&(f64, PauliString)], lambda: f64, rng: &mut ChaCha8Rng) -> usize {
+	let mut r = rng.random::<f64>() * lambda;
+	for (i, (coefficient, _)) in terms.iter().enumerate() {
+		r -= coefficient.abs();
+		if r < 0.0 {
+			return i;
+		}
+	}
+	terms.len() - 1
+}
+
+/// Greedily puts every term into the first group it commutes with.
+fn commuting_groups(terms: &[&(f64, PauliString)]) -> Vec<Vec<usize>> {
+	let mut groups: Vec<Vec<usize>> = Vec::new();
+	for (i, (_, string)) in terms.iter().enumerate() {
+		let group = groups
+			.iter_mut()
+			.find(|group| group.iter().all(|j| terms[*j].1.commutes_with(string)));
+		match group {
+			Some(group) => group.push(i),
+			None => groups.push(vec![i]),
+		}
+	}
+	groups
+}
+
+/// One step of the Suzuki formula of `order` for `fraction` of a time step,
+/// as (group, fraction) pairs.
+fn suzuki(groups: usize, order: usize, fraction: f64) -> Vec<(usize, f64)> {
+	if order == 2 {
+		let half = fraction / 2.0;
+		let mut step: Vec<(usize, f64)> = (0..groups - 1).map(|group| (group, half)).collect();
+		step.push((groups - 1, fraction));
+		step.extend((0..groups - 1).rev().map(|group| (group, half)));
+		return step;
+	}
+
+	let p = 1.0 / (4.0 - 4f64.powf(1.0 / (order - 1) as f64));
+	let outer = suzuki(groups, order - 2, p * fraction);
+	let inner = suzuki(groups, order - 2, (1.0 - 4.0 * p) * fraction);
+	[&outer, &outer, &inner, &outer, &outer]
+		.into_iter()
+		.flatten()
+		.copied()
+		.collect()
+}
+
+/// Adds up neighbouring applications of the same group.
+fn merge_neighbours<I: Iterator<Item = (usize, f64)>>(schedule: I) -> Vec<(usize, f64)> {
+	let mut merged: Vec<(usize, f64)> = Vec::new();
+	for (group, fraction) in schedule {
+		match merged.last_mut() {
+			Some((last, total)) if *last == group => *total += fraction,
+			_ => merged.push((group, fraction)),
+		}
+	}
+	merged
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{pauli::PauliLetter, pauli_string, simulate::ApplyExp};
+	use simulator::Statevector;
+
+	/// An open Heisenberg chain with a field, which has terms that do not
+	/// commute.
+	fn hamiltonian() -> PauliSum {
+		let mut sum = PauliSum::new();
+		for i in 0..3 {
+			for (coefficient, letter) in [(1.0, PauliLetter::X), (0.5, PauliLetter::Y)] {
+				let mut string = PauliString::default();
+				string.set(i, letter);
+				string.set(i + 1, letter);
+				sum.push(coefficient, string);
+			}
+			sum.push(0.3, PauliString::z(i));
+		}
+		sum.push(2.0, PauliString::default());
+		sum
+	}
+
+	fn evolve(exps: &[PauliExp<PauliAngle>]) -> Statevector<f64> {
+		let mut state = Statevector::new(4);
+		state.values.swap(0, 0b0101);
+		state.apply_exps(exps);
+		state
+	}
+
+	fn distance(a: &Statevector<f64>, b: &Statevector<f64>) -> f64 {
+		a.values
+			.iter()
+			.zip(b.values.iter())
+			.map(|(a, b)| (a.re - b.re).powi(2) + (a.im - b.im).powi(2))
+			.sum::<f64>()
+			.sqrt()
+	}
+
+	#[test]
+	fn orders_converge() {
+		let hamiltonian = hamiltonian();
+		let time = 1.0;
+		let exact = evolve(
+			&Evolution::new(ProductFormula::Suzuki(6), time / 32.0, 32).exponentials(&hamiltonian),
+		);
+		let error = |formula, steps: usize| {
+			let exps =
+				Evolution::new(formula, time / steps as f64, steps).exponentials(&hamiltonian);
+			distance(&evolve(&exps), &exact)
+		};
+
+		let first = error(ProductFormula::Trotter, 16) / error(ProductFormula::Trotter, 32);
+		assert!(first > 1.8 && first < 2.2, "{first}");
+		let second = error(ProductFormula::SecondOrderTrotter, 8)
+			/ error(ProductFormula::SecondOrderTrotter, 16);
+		assert!(second > 3.6 && second < 4.4, "{second}");
+		let fourth = error(ProductFormula::Suzuki(4), 4) / error(ProductFormula::Suzuki(4), 8);
+		assert!(fourth > 12.0, "{fourth}");
+
+		// The identity term is dropped and the halves between steps merged
+		let exps =
+			Evolution::new(ProductFormula::SecondOrderTrotter, 0.1, 3).exponentials(&hamiltonian);
+		assert_eq!(exps.len(), 3 * (2 * 9 - 1) - 2);
+	}
+
+	#[test]
+	fn grouping_commuting_terms() {
+		let hamiltonian = hamiltonian();
+		let evolution = Evolution::new(ProductFormula::Trotter, 0.1, 1).with_grouping(true);
+		let exps = evolution.exponentials(&hamiltonian);
+		assert_eq!(exps.len(), 9);
+
+		// Splitting the sequence at every anticommuting neighbour gives
+		// groups that commute within
+		let mut groups = 1;
+		for (i, exp) in exps.iter().enumerate().skip(1) {
+			if exps[i - 1].string.anticommutes_with(&exp.string) {
+				groups += 1;
+			}
+		}
+		assert!(groups < 9);
+
+		// A single group of commuting terms is exact after one step
+		let commuting: PauliSum = [
+			(0.4, pauli_string!("XXII")),
+			(-0.7, pauli_string!("YYII")),
+			(1.1, pauli_string!("ZZII")),
+			(0.2, pauli_string!("IIXX")),
+		]
+		.into_iter()
+		.collect();
+		let one = Evolution::new(ProductFormula::Trotter, 0.8, 1).exponentials(&commuting);
+		let many = Evolution::new(ProductFormula::Trotter, 0.1, 8).exponentials(&commuting);
+		assert!(distance(&evolve(&one), &evolve(&many)) < 1e-12);
+	}
+
+	#[test]
+	fn qdrift_is_seeded() {
+		let hamiltonian = hamiltonian();
+		let formula = ProductFormula::QDrift {
+			samples: 50,
+			seed: 7,
+		};
+		let exps = Evolution::new(formula, 0.2, 4).exponentials(&hamiltonian);
+		assert_eq!(exps.len(), 200);
+		let again = Evolution::new(formula, 0.2, 4).exponentials(&hamiltonian);
+		for (a, b) in exps.iter().zip(again.iter()) {
+			assert_eq!((&a.string, &a.angle), (&b.string, &b.angle));
+		}
+
+		// $\lambda=5.4$ without the identity
+		let angle = 5.4 * 0.2 / 50.0 / PI;
+		for exp in exps.iter() {
+			let PauliAngle::MultipleOfPi(value) = exp.angle else {
+				panic!("{:?}", exp.angle);
+			};
+			assert!((value.abs() - angle).abs() < 1e-12);
+		}
+		// Terms with larger coefficients are drawn more often
+		let xx = exps
+			.iter()
+			.filter(|exp| exp.string == hamiltonian.terms[0].1)
+			.count();
+		let z = exps
+			.iter()
+			.filter(|exp| exp.string == hamiltonian.terms[2].1)
+			.count();
+		assert!(xx > z);
+	}
+}
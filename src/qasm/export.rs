@@ -0,0 +1,348 @@
+use std::{collections::BTreeSet, error::Error, fmt, fmt::Write};
+
+use circuit::gates::{H, Rx, Ry, Rz, S, Sdg, X, Y, Z};
+
+use crate::{
+	lower::{Gate, lower, rotation_angle, z_basis_change},
+	pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QasmVersion {
+	V2,
+	V3,
+}
+
+#[derive(Debug, Clone)]
+pub struct QasmOptions {
+	pub version: QasmVersion,
+	/// Emits the $ZZ\cdots Z$ rotations as custom `gate` definitions instead
+	/// of writing out the CNOT ladder every time.
+	pub native_rotations: bool,
+	/// Size of the quantum register. When smaller than the qubits used by
+	/// the exponentials the register is grown to fit them.
+	pub qubits: usize,
+}
+
+impl Default for QasmOptions {
+	fn default() -> Self {
+		Self {
+			version: QasmVersion::V3,
+			native_rotations: false,
+			qubits: 0,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QasmExportError {
+	/// OpenQASM 2 has no free parameters, so only numeric angles can be written.
+	/// Holds the parameters of the angle, separated by commas.
+	SymbolicAngle(String),
+}
+
+impl fmt::Display for QasmExportError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			QasmExportError::SymbolicAngle(parameters) => {
+				write!(
+					f,
+					"OpenQASM 2 can not contain angles with the parameters '{parameters}'"
+				)
+			}
+		}
+	}
+}
+
+impl Error for QasmExportError {}
+
+/// # To QASM
+///
+/// Writes the exponentials as an OpenQASM program. The first exponential is
+/// applied first. Parameters become `input` variables in OpenQASM 3.
+///
+/// Works on the output of [crate::synthesize::synthesize] and
+/// [crate::clifford_tableau::CliffordTableau::decompose] alike, as long as the
+/// Clifford part is appended after the circuit.
+pub fn to_qasm<T: Negate + Clone + Into<PauliAngle>>(
+	exps: &[PauliExp<T>],
+	options: &QasmOptions,
+) -> Result<String, QasmExportError> {
+	let qubits = exps
+		.iter()
+		.map(|exp| exp.string.size())
+		.max()
+		.unwrap_or_default()
+		.max(options.qubits);
+
+	let mut lines = Vec::new();
+	let mut parameters: BTreeSet<String> = BTreeSet::new();
+	let mut rotation_sizes: BTreeSet<usize> = BTreeSet::new();
+	for gate in lower(exps, options.native_rotations).iter() {
+		if let Gate::PauliRotation(exp) = gate {
+			rotation_sizes.insert(exp.string.len());
+		}
+		write_gate(gate, options.version, &mut parameters, &mut lines)?;
+	}
+
+	let mut res = String::new();
+	match options.version {
+		QasmVersion::V2 => {
+			res += "OPENQASM 2.0;\ninclude \"qelib1.inc\";\n";
+		}
+		QasmVersion::V3 => {
+			res += "OPENQASM 3.0;\ninclude \"stdgates.inc\";\n";
+		}
+	}
+
+	for n in rotation_sizes {
+		res += &rotation_definition(n);
+	}
+
+	match options.version {
+		QasmVersion::V2 => writeln!(res, "qreg q[{qubits}];").unwrap(),
+		QasmVersion::V3 => {
+			for parameter in parameters {
+				writeln!(res, "input float[64] {parameter};").unwrap();
+			}
+			writeln!(res, "qubit[{qubits}] q;").unwrap();
+		}
+	}
+
+	for line in lines {
+		res += &line;
+		res.push('\n');
+	}
+
+	Ok(res)
+}
+
+/// The rotation angle $\phi$ of a [Gate] in radians, that is $\phi\pi$.
+fn format_angle(
+	angle: &PauliAngle,
+	version: QasmVersion,
+	parameters: &mut BTreeSet<String>,
+) -> Result<String, QasmExportError> {
+	// The angle is already lowered, so the error only names the parameters
+	if version == QasmVersion::V2 && !angle.parameters().is_empty() {
+		return Err(QasmExportError::SymbolicAngle(
+			angle.parameters().join(", "),
+		));
+	}
+
+	let angle = match angle {
+		PauliAngle::MultipleOfPi(v) => format_multiple_of_pi(*v),
+		PauliAngle::Clifford(CliffordPauliAngle::Zero) => String::from("0"),
+		PauliAngle::Clifford(CliffordPauliAngle::PiOver4) => String::from("pi/4"),
+		PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4) => String::from("-pi/4"),
+		PauliAngle::Clifford(CliffordPauliAngle::PiOver2) => String::from("pi/2"),
+		PauliAngle::Clifford(CliffordPauliAngle::NegPiOver2) => String::from("-pi/2"),
+		PauliAngle::Parameter { neg, name } => {
+			parameters.insert(name.clone());
+			match neg {
+				true => format!("-pi*{name}"),
+				false => format!("pi*{name}"),
+			}
+		}
+		PauliAngle::Expression(expression) => {
+			for (_, name) in expression.terms() {
+				parameters.insert(name.clone());
+			}
+			format!("pi*({expression})")
+		}
+	};
+
+	Ok(angle)
+}
+
+fn format_multiple_of_pi(v: f64) -> String {
+	if v == 0.0 {
+		String::from("0")
+	} else if v == 1.0 {
+		String::from("pi")
+	} else if v == -1.0 {
+		String::from("-pi")
+	} else {
+		format!("{v}*pi")
+	}
+}
+
+fn write_gate(
+	gate: &Gate,
+	version: QasmVersion,
+	parameters: &mut BTreeSet<String>,
+	lines: &mut Vec<String>,
+) -> Result<(), QasmExportError> {
+	let line = match gate {
+		Gate::X(X { target }) => format!("x q[{target}];"),
+		Gate::Y(Y { target }) => format!("y q[{target}];"),
+		Gate::Z(Z { target }) => format!("z q[{target}];"),
+		Gate::H(H { target }) => format!("h q[{target}];"),
+		Gate::S(S { target }) => format!("s q[{target}];"),
+		Gate::Sdg(Sdg { target }) => format!("sdg q[{target}];"),
+		Gate::Rx(Rx { angle, target }) => {
+			format!(
+				"rx({}) q[{target}];",
+				format_angle(angle, version, parameters)?
+			)
+		}
+		Gate::Ry(Ry { angle, target }) => {
+			format!(
+				"ry({}) q[{target}];",
+				format_angle(angle, version, parameters)?
+			)
+		}
+		Gate::Rz(Rz { angle, target }) => {
+			format!(
+				"rz({}) q[{target}];",
+				format_angle(angle, version, parameters)?
+			)
+		}
+		Gate::CNot(cnot) => format!("cx q[{}], q[{}];", cnot.control(), cnot.target()),
+		Gate::PauliRotation(exp) => {
+			let (before, after) = z_basis_change(&exp.string);
+			for gate in before.iter() {
+				write_gate(gate, version, parameters, lines)?;
+			}
+			let targets = exp.string.targets();
+			let qubits = targets
+				.iter()
+				.map(|i| format!("q[{i}]"))
+				.collect::<Vec<_>>()
+				.join(", ");
+			let angle = format_angle(&rotation_angle(&exp.angle), version, parameters)?;
+			lines.push(format!(
+				"{}({angle}) {qubits};",
+				rotation_name(targets.len())
+			));
+			for gate in after.iter() {
+				write_gate(gate, version, parameters, lines)?;
+			}
+			return Ok(());
+		}
+	};
+	lines.push(line);
+	Ok(())
+}
+
+fn rotation_name(n: usize) -> String {
+	format!("rz_{n}")
+}
+
+/// A gate `rz_n(theta)` that applies $e^{-i\frac{\theta}{2}Z^{\otimes n}}$.
+fn rotation_definition(n: usize) -> String {
+	let arguments = (0..n)
+		.map(|i| format!("a{i}"))
+		.collect::<Vec<_>>()
+		.join(", ");
+
+	let mut res = format!("gate {}(theta) {arguments} {{\n", rotation_name(n));
+	for i in 0..(n - 1) {
+		writeln!(res, "\tcx a{}, a{};", i, i + 1).unwrap();
+	}
+	writeln!(res, "\trz(theta) a{};", n - 1).unwrap();
+	for i in (0..(n - 1)).rev() {
+		writeln!(res, "\tcx a{}, a{};", i, i + 1).unwrap();
+	}
+	res += "}\n";
+	res
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{pauli::PauliString, pauli_string};
+
+	#[test]
+	fn ladder() {
+		let exps = vec![PauliExp {
+			string: pauli_string!("XIYZ"),
+			angle: PauliAngle::MultipleOfPi(0.125),
+		}];
+
+		let qasm = to_qasm(
+			&exps,
+			&QasmOptions {
+				version: QasmVersion::V2,
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		assert_eq!(
+			qasm,
+			"OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[4];
+h q[0];
+sdg q[2];
+h q[2];
+cx q[0], q[2];
+cx q[2], q[3];
+rz(-pi/4) q[3];
+cx q[2], q[3];
+cx q[0], q[2];
+h q[0];
+h q[2];
+s q[2];
+"
+		);
+	}
+
+	#[test]
+	fn native_rotations_and_parameters() {
+		let exps = vec![
+			PauliExp {
+				string: pauli_string!("ZZ"),
+				angle: PauliAngle::Parameter {
+					neg: true,
+					name: String::from("theta"),
+				},
+			},
+			PauliExp {
+				string: PauliString::z(2),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			},
+			PauliExp {
+				string: PauliString::x(0),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::Zero),
+			},
+		];
+
+		let qasm = to_qasm(
+			&exps,
+			&QasmOptions {
+				native_rotations: true,
+				qubits: 5,
+				..Default::default()
+			},
+		)
+		.unwrap();
+
+		assert_eq!(
+			qasm,
+			"OPENQASM 3.0;
+include \"stdgates.inc\";
+gate rz_2(theta) a0, a1 {
+	cx a0, a1;
+	rz(theta) a1;
+	cx a0, a1;
+}
+input float[64] theta;
+qubit[5] q;
+rz_2(pi*(2*theta)) q[0], q[1];
+sdg q[2];
+"
+		);
+
+		let err = to_qasm(
+			&exps,
+			&QasmOptions {
+				version: QasmVersion::V2,
+				..Default::default()
+			},
+		)
+		.unwrap_err();
+		assert_eq!(err, QasmExportError::SymbolicAngle(String::from("theta")));
+	}
+}
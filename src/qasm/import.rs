@@ -0,0 +1,1136 @@
+use std::{
+	collections::{BTreeMap, HashMap},
+	error::Error,
+	f64::consts::PI,
+	fmt, fs, io,
+	path::Path,
+};
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{
+		AngleExpression, CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliLetter, PauliString,
+	},
+};
+
+/// Gates from `qelib1.inc` and `stdgates.inc` that are not handled directly,
+/// written in terms of the ones that are.
+const PRELUDE: &str = "
+gate u2(phi, lambda) a { u3(pi/2, phi, lambda) a; }
+gate cy a, b { sdg b; cx a, b; s b; }
+gate swap a, b { cx a, b; cx b, a; cx a, b; }
+gate ch a, b { h b; sdg b; cx a, b; h b; t b; cx a, b; t b; h b; s b; x b; s a; }
+gate ccx a, b, c {
+	h c; cx b, c; tdg c; cx a, c; t c; cx b, c; tdg c; cx a, c;
+	t b; t c; h c; cx a, b; t a; tdg b; cx a, b;
+}
+gate cswap a, b, c { cx c, b; ccx a, b, c; cx c, b; }
+gate cp(lambda) a, b { p(lambda/2) a; cx a, b; p(-lambda/2) b; cx a, b; p(lambda/2) b; }
+gate cu1(lambda) a, b { cp(lambda) a, b; }
+gate cphase(lambda) a, b { cp(lambda) a, b; }
+gate crz(lambda) a, b { rz(lambda/2) b; cx a, b; rz(-lambda/2) b; cx a, b; }
+gate cry(lambda) a, b { ry(lambda/2) b; cx a, b; ry(-lambda/2) b; cx a, b; }
+gate crx(lambda) a, b {
+	u1(pi/2) b; cx a, b; u3(-lambda/2, 0, 0) b; cx a, b; u3(lambda/2, -pi/2, 0) b;
+}
+gate cu3(theta, phi, lambda) c, t {
+	u1((lambda+phi)/2) c; u1((lambda-phi)/2) t; cx c, t;
+	u3(-theta/2, 0, -(phi+lambda)/2) t; cx c, t; u3(theta/2, phi, 0) t;
+}
+";
+
+/// A circuit read from OpenQASM as $C\prod_i e^{i\theta_i P_i}$. The
+/// exponentials come first and `clifford` is applied after them.
+#[derive(Debug, Clone)]
+pub struct QasmCircuit {
+	pub exps: Vec<PauliExp<PauliAngle>>,
+	pub clifford: CliffordTableau,
+	pub qubits: usize,
+	/// How many times each gate is used in the program, without expanding
+	/// gate definitions.
+	pub gate_counts: BTreeMap<String, usize>,
+}
+
+#[derive(Debug)]
+pub enum QasmParseErrorReason {
+	Io(io::Error),
+	UnexpectedCharacter(char),
+	UnexpectedToken(String),
+	UnexpectedEnd,
+	UnknownGate(String),
+	UnknownRegister(String),
+	UnknownIdentifier(String),
+	IndexOutOfRange {
+		register: String,
+		index: usize,
+	},
+	WrongArgumentCount {
+		gate: String,
+		expected: usize,
+		found: usize,
+	},
+	WrongParameterCount {
+		gate: String,
+		expected: usize,
+		found: usize,
+	},
+	DuplicateQubit(usize),
+	RegisterSizeMismatch,
+	/// Statements like `reset` or `if` that are not unitary or not supported.
+	Unsupported(String),
+}
+
+/// An error from [from_qasm]. `line` is 1-based, or 0 when not tied to a line.
+#[derive(Debug)]
+pub struct QasmParseError {
+	pub line: usize,
+	pub reason: QasmParseErrorReason,
+}
+
+impl QasmParseError {
+	fn new(line: usize, reason: QasmParseErrorReason) -> Self {
+		Self { line, reason }
+	}
+}
+
+impl fmt::Display for QasmParseError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "line {}: ", self.line)?;
+		match &self.reason {
+			QasmParseErrorReason::Io(err) => write!(f, "{err}"),
+			QasmParseErrorReason::UnexpectedCharacter(c) => write!(f, "unexpected character '{c}'"),
+			QasmParseErrorReason::UnexpectedToken(token) => write!(f, "unexpected '{token}'"),
+			QasmParseErrorReason::UnexpectedEnd => write!(f, "unexpected end of file"),
+			QasmParseErrorReason::UnknownGate(name) => write!(f, "unknown gate '{name}'"),
+			QasmParseErrorReason::UnknownRegister(name) => write!(f, "unknown register '{name}'"),
+			QasmParseErrorReason::UnknownIdentifier(name) => {
+				write!(f, "unknown identifier '{name}'")
+			}
+			QasmParseErrorReason::IndexOutOfRange { register, index } => {
+				write!(f, "index {index} is out of range for register '{register}'")
+			}
+			QasmParseErrorReason::WrongArgumentCount {
+				gate,
+				expected,
+				found,
+			} => write!(f, "gate '{gate}' takes {expected} qubits, got {found}"),
+			QasmParseErrorReason::WrongParameterCount {
+				gate,
+				expected,
+				found,
+			} => write!(f, "gate '{gate}' takes {expected} parameters, got {found}"),
+			QasmParseErrorReason::DuplicateQubit(qubit) => {
+				write!(f, "qubit {qubit} is used twice in one gate")
+			}
+			QasmParseErrorReason::RegisterSizeMismatch => {
+				write!(f, "registers of a broadcast gate have different sizes")
+			}
+			QasmParseErrorReason::Unsupported(what) => write!(f, "'{what}' is not supported"),
+		}
+	}
+}
+
+impl Error for QasmParseError {}
+
+impl From<io::Error> for QasmParseError {
+	fn from(value: io::Error) -> Self {
+		Self::new(0, QasmParseErrorReason::Io(value))
+	}
+}
+
+/// Reads an OpenQASM file, see [from_qasm].
+pub fn read_qasm_file<P: AsRef<Path>>(path: P) -> Result<QasmCircuit, QasmParseError> {
+	from_qasm(&fs::read_to_string(path)?)
+}
+
+/// # From QASM
+///
+/// Reads an OpenQASM 2 or 3 program made of Clifford gates and rotations
+/// (`rz`, `rx`, `ry`, `u`, `t`, ...) into Pauli exponentials and a Clifford
+/// tableau. Gate definitions are expanded and the Clifford gates are moved
+/// to the end. Each rotation is conjugated once with the inverse of the
+/// Cliffords before it, see [CliffordTableau::conjugate], which gives the
+/// same as pushing the Cliffords through it with [PauliExp::push_pi_over_4].
+/// The result is equivalent to the program up to a global phase.
+///
+/// `measure` and `barrier` statements are ignored and classical registers
+/// are skipped. Angles have to be constant.
+pub fn from_qasm(source: &str) -> Result<QasmCircuit, QasmParseError> {
+	let mut importer = Importer::default();
+
+	let prelude = Parser::new(tokenize(PRELUDE)?);
+	importer.run(prelude, false)?;
+
+	let parser = Parser::new(tokenize(source)?);
+	importer.run(parser, true)?;
+
+	Ok(QasmCircuit {
+		exps: importer.exps,
+		clifford: importer.tableau,
+		qubits: importer.qubits,
+		gate_counts: importer.gate_counts,
+	})
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Ident(String),
+	Number(f64),
+	Str(String),
+	Symbol(char),
+	Arrow,
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Token::Ident(name) => write!(f, "{name}"),
+			Token::Number(v) => write!(f, "{v}"),
+			Token::Str(s) => write!(f, "\"{s}\""),
+			Token::Symbol(c) => write!(f, "{c}"),
+			Token::Arrow => write!(f, "->"),
+		}
+	}
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, QasmParseError> {
+	let chars: Vec<char> = source.chars().collect();
+	let mut tokens = Vec::new();
+	let mut line = 1;
+	let mut i = 0;
+	while i < chars.len() {
+		let c = chars[i];
+		match c {
+			'\n' => line += 1,
+			c if c.is_whitespace() => {}
+			'/' if chars.get(i + 1) == Some(&'/') => {
+				while i < chars.len() && chars[i] != '\n' {
+					i += 1;
+				}
+				continue;
+			}
+			'/' if chars.get(i + 1) == Some(&'*') => {
+				i += 2;
+				while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+					if chars[i] == '\n' {
+						line += 1;
+					}
+					i += 1;
+				}
+				i += 1;
+			}
+			'-' if chars.get(i + 1) == Some(&'>') => {
+				tokens.push((line, Token::Arrow));
+				i += 1;
+			}
+			'"' => {
+				let start = i + 1;
+				i += 1;
+				while i < chars.len() && chars[i] != '"' {
+					i += 1;
+				}
+				tokens.push((line, Token::Str(chars[start..i].iter().collect())));
+			}
+			c if c.is_ascii_digit() || c == '.' => {
+				let start = i;
+				while i + 1 < chars.len()
+					&& (chars[i + 1].is_ascii_digit()
+						|| chars[i + 1] == '.'
+						|| matches!(chars[i + 1], 'e' | 'E')
+						|| (matches!(chars[i + 1], '+' | '-') && matches!(chars[i], 'e' | 'E')))
+				{
+					i += 1;
+				}
+				let text: String = chars[start..=i].iter().collect();
+				let value = text.parse::<f64>().map_err(|_| {
+					QasmParseError::new(line, QasmParseErrorReason::UnexpectedToken(text))
+				})?;
+				tokens.push((line, Token::Number(value)));
+			}
+			c if c.is_alphabetic() || c == '_' => {
+				let start = i;
+				while i + 1 < chars.len() && (chars[i + 1].is_alphanumeric() || chars[i + 1] == '_')
+				{
+					i += 1;
+				}
+				tokens.push((line, Token::Ident(chars[start..=i].iter().collect())));
+			}
+			';' | ',' | '(' | ')' | '[' | ']' | '{' | '}' | '+' | '-' | '*' | '/' | '^' | '='
+			| '<' | '>' | '!' | '&' | '|' | '@' | ':' | '%' | '~' => tokens.push((line, Token::Symbol(c))),
+			c => {
+				return Err(QasmParseError::new(
+					line,
+					QasmParseErrorReason::UnexpectedCharacter(c),
+				));
+			}
+		}
+		i += 1;
+	}
+
+	Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+	Number(f64),
+	Variable(String),
+	Neg(Box<Expr>),
+	Binary(char, Box<Expr>, Box<Expr>),
+	Function(String, Box<Expr>),
+}
+
+impl Expr {
+	fn evaluate(&self, env: &HashMap<String, f64>, line: usize) -> Result<f64, QasmParseError> {
+		Ok(match self {
+			Expr::Number(v) => *v,
+			Expr::Variable(name) => match (env.get(name), name.as_str()) {
+				(Some(v), _) => *v,
+				(None, "pi" | "π") => PI,
+				(None, "tau" | "τ") => 2.0 * PI,
+				(None, "euler" | "ℇ") => std::f64::consts::E,
+				(None, _) => {
+					return Err(QasmParseError::new(
+						line,
+						QasmParseErrorReason::UnknownIdentifier(name.clone()),
+					));
+				}
+			},
+			Expr::Neg(expr) => -expr.evaluate(env, line)?,
+			Expr::Binary(op, a, b) => {
+				let a = a.evaluate(env, line)?;
+				let b = b.evaluate(env, line)?;
+				match op {
+					'+' => a + b,
+					'-' => a - b,
+					'*' => a * b,
+					'/' => a / b,
+					_ => a.powf(b),
+				}
+			}
+			Expr::Function(name, expr) => {
+				let v = expr.evaluate(env, line)?;
+				match name.as_str() {
+					"sin" => v.sin(),
+					"cos" => v.cos(),
+					"tan" => v.tan(),
+					"exp" => v.exp(),
+					"ln" => v.ln(),
+					"sqrt" => v.sqrt(),
+					"arcsin" => v.asin(),
+					"arccos" => v.acos(),
+					"arctan" => v.atan(),
+					_ => {
+						return Err(QasmParseError::new(
+							line,
+							QasmParseErrorReason::UnknownIdentifier(name.clone()),
+						));
+					}
+				}
+			}
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Argument {
+	name: String,
+	index: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct GateCall {
+	line: usize,
+	name: String,
+	parameters: Vec<Expr>,
+	arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone)]
+struct GateDefinition {
+	parameters: Vec<String>,
+	qubits: Vec<String>,
+	body: Vec<GateCall>,
+}
+
+struct Parser {
+	tokens: Vec<(usize, Token)>,
+	position: usize,
+}
+
+impl Parser {
+	fn new(tokens: Vec<(usize, Token)>) -> Self {
+		Self {
+			tokens,
+			position: 0,
+		}
+	}
+
+	fn line(&self) -> usize {
+		self.tokens
+			.get(self.position)
+			.or(self.tokens.last())
+			.map(|(line, _)| *line)
+			.unwrap_or_default()
+	}
+
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.position).map(|(_, token)| token)
+	}
+
+	fn next(&mut self) -> Result<Token, QasmParseError> {
+		let token = self
+			.tokens
+			.get(self.position)
+			.map(|(_, token)| token.clone())
+			.ok_or_else(|| QasmParseError::new(self.line(), QasmParseErrorReason::UnexpectedEnd))?;
+		self.position += 1;
+		Ok(token)
+	}
+
+	fn unexpected(&self, token: &Token) -> QasmParseError {
+		QasmParseError::new(
+			self.tokens[self.position - 1].0,
+			QasmParseErrorReason::UnexpectedToken(format!("{token}")),
+		)
+	}
+
+	fn is_symbol(&self, symbol: char) -> bool {
+		self.peek() == Some(&Token::Symbol(symbol))
+	}
+
+	fn expect_symbol(&mut self, symbol: char) -> Result<(), QasmParseError> {
+		match self.next()? {
+			Token::Symbol(c) if c == symbol => Ok(()),
+			token => Err(self.unexpected(&token)),
+		}
+	}
+
+	fn ident(&mut self) -> Result<String, QasmParseError> {
+		match self.next()? {
+			Token::Ident(name) => Ok(name),
+			token => Err(self.unexpected(&token)),
+		}
+	}
+
+	fn index(&mut self) -> Result<usize, QasmParseError> {
+		match self.next()? {
+			Token::Number(v) if v >= 0.0 && v.fract() == 0.0 => Ok(v as usize),
+			token => Err(self.unexpected(&token)),
+		}
+	}
+
+	/// Skips the rest of the statement including the `;`.
+	fn skip_statement(&mut self) -> Result<(), QasmParseError> {
+		while self.next()? != Token::Symbol(';') {}
+		Ok(())
+	}
+
+	/// Whether the statement that starts at the current position contains
+	/// the identifier.
+	fn statement_contains(&self, ident: &str) -> bool {
+		self.tokens[self.position..]
+			.iter()
+			.map(|(_, token)| token)
+			.take_while(|token| !matches!(token, Token::Symbol(';' | '{')))
+			.any(|token| matches!(token, Token::Ident(name) if name == ident))
+	}
+
+	fn expression(&mut self) -> Result<Expr, QasmParseError> {
+		let mut expr = self.term()?;
+		while let Some(Token::Symbol(op @ ('+' | '-'))) = self.peek() {
+			let op = *op;
+			self.position += 1;
+			expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+		}
+		Ok(expr)
+	}
+
+	fn term(&mut self) -> Result<Expr, QasmParseError> {
+		let mut expr = self.unary()?;
+		while let Some(Token::Symbol(op @ ('*' | '/'))) = self.peek() {
+			let op = *op;
+			self.position += 1;
+			expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+		}
+		Ok(expr)
+	}
+
+	fn unary(&mut self) -> Result<Expr, QasmParseError> {
+		match self.peek() {
+			Some(Token::Symbol('-')) => {
+				self.position += 1;
+				Ok(Expr::Neg(Box::new(self.unary()?)))
+			}
+			Some(Token::Symbol('+')) => {
+				self.position += 1;
+				self.unary()
+			}
+			_ => self.power(),
+		}
+	}
+
+	fn power(&mut self) -> Result<Expr, QasmParseError> {
+		let base = self.primary()?;
+		if self.is_symbol('^') {
+			self.position += 1;
+			return Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)));
+		}
+		Ok(base)
+	}
+
+	fn primary(&mut self) -> Result<Expr, QasmParseError> {
+		match self.next()? {
+			Token::Number(v) => Ok(Expr::Number(v)),
+			Token::Ident(name) => {
+				if self.is_symbol('(') {
+					self.position += 1;
+					let expr = self.expression()?;
+					self.expect_symbol(')')?;
+					Ok(Expr::Function(name, Box::new(expr)))
+				} else {
+					Ok(Expr::Variable(name))
+				}
+			}
+			Token::Symbol('(') => {
+				let expr = self.expression()?;
+				self.expect_symbol(')')?;
+				Ok(expr)
+			}
+			token => Err(self.unexpected(&token)),
+		}
+	}
+
+	/// Parses `name(parameters) arguments;` where the name is already read.
+	fn gate_call(&mut self, name: String, line: usize) -> Result<GateCall, QasmParseError> {
+		let mut parameters = Vec::new();
+		if self.is_symbol('(') {
+			self.position += 1;
+			if !self.is_symbol(')') {
+				loop {
+					parameters.push(self.expression()?);
+					if self.is_symbol(',') {
+						self.position += 1;
+					} else {
+						break;
+					}
+				}
+			}
+			self.expect_symbol(')')?;
+		}
+
+		let mut arguments = Vec::new();
+		loop {
+			let name = self.ident()?;
+			let index = if self.is_symbol('[') {
+				self.position += 1;
+				let index = self.index()?;
+				self.expect_symbol(']')?;
+				Some(index)
+			} else {
+				None
+			};
+			arguments.push(Argument { name, index });
+
+			match self.next()? {
+				Token::Symbol(',') => {}
+				Token::Symbol(';') => break,
+				token => return Err(self.unexpected(&token)),
+			}
+		}
+
+		Ok(GateCall {
+			line,
+			name,
+			parameters,
+			arguments,
+		})
+	}
+
+	/// Parses the rest of `gate name(parameters) qubits { body }`.
+	fn gate_definition(&mut self) -> Result<(String, GateDefinition), QasmParseError> {
+		let name = self.ident()?;
+
+		let mut parameters = Vec::new();
+		if self.is_symbol('(') {
+			self.position += 1;
+			while !self.is_symbol(')') {
+				parameters.push(self.ident()?);
+				if self.is_symbol(',') {
+					self.position += 1;
+				}
+			}
+			self.position += 1;
+		}
+
+		let mut qubits = Vec::new();
+		while !self.is_symbol('{') {
+			qubits.push(self.ident()?);
+			if self.is_symbol(',') {
+				self.position += 1;
+			}
+		}
+		self.position += 1;
+
+		let mut body = Vec::new();
+		loop {
+			let line = self.line();
+			match self.next()? {
+				Token::Symbol('}') => break,
+				Token::Ident(name) if name == "barrier" => self.skip_statement()?,
+				Token::Ident(name) => body.push(self.gate_call(name, line)?),
+				token => return Err(self.unexpected(&token)),
+			}
+		}
+
+		Ok((
+			name,
+			GateDefinition {
+				parameters,
+				qubits,
+				body,
+			},
+		))
+	}
+}
+
+#[derive(Default)]
+struct Importer {
+	/// (name, offset, size)
+	registers: Vec<(String, usize, usize)>,
+	definitions: HashMap<String, GateDefinition>,
+	qubits: usize,
+	exps: Vec<PauliExp<PauliAngle>>,
+	tableau: CliffordTableau,
+	/// The inverse of `tableau`, kept up to date with it
+	inverse: CliffordTableau,
+	gate_counts: BTreeMap<String, usize>,
+}
+
+/// The gates that are applied directly, with their parameter and qubit counts.
+fn primitive(name: &str) -> Option<(usize, usize)> {
+	match name {
+		"id" | "i" | "x" | "y" | "z" | "h" | "s" | "sdg" | "sx" | "sxdg" | "t" | "tdg" => {
+			Some((0, 1))
+		}
+		"rx" | "ry" | "rz" | "p" | "u1" | "phase" => Some((1, 1)),
+		"u3" | "u" | "U" => Some((3, 1)),
+		"cx" | "CX" | "cnot" | "cz" => Some((0, 2)),
+		"rxx" | "ryy" | "rzz" => Some((1, 2)),
+		_ => None,
+	}
+}
+
+impl Importer {
+	fn run(&mut self, mut parser: Parser, count: bool) -> Result<(), QasmParseError> {
+		while parser.peek().is_some() {
+			let line = parser.line();
+			let token = parser.next()?;
+			let Token::Ident(keyword) = token else {
+				return Err(parser.unexpected(&token));
+			};
+
+			match keyword.as_str() {
+				"OPENQASM" | "include" | "creg" | "bit" | "measure" | "barrier" => {
+					parser.skip_statement()?
+				}
+				"qreg" => {
+					let name = parser.ident()?;
+					parser.expect_symbol('[')?;
+					let size = parser.index()?;
+					parser.expect_symbol(']')?;
+					parser.expect_symbol(';')?;
+					self.add_register(name, size);
+				}
+				"qubit" => {
+					let size = if parser.is_symbol('[') {
+						parser.position += 1;
+						let size = parser.index()?;
+						parser.expect_symbol(']')?;
+						size
+					} else {
+						1
+					};
+					let name = parser.ident()?;
+					parser.expect_symbol(';')?;
+					self.add_register(name, size);
+				}
+				"gate" => {
+					let (name, definition) = parser.gate_definition()?;
+					for call in definition.body.iter() {
+						if primitive(&call.name).is_none()
+							&& !self.definitions.contains_key(&call.name)
+						{
+							return Err(QasmParseError::new(
+								call.line,
+								QasmParseErrorReason::UnknownGate(call.name.clone()),
+							));
+						}
+					}
+					self.definitions.insert(name, definition);
+				}
+				"reset" | "if" | "opaque" | "input" | "output" | "ctrl" | "negctrl" | "inv"
+				| "pow" | "for" | "while" | "def" | "let" | "const" | "float" | "angle" | "int"
+				| "uint" | "bool" | "gphase" => {
+					return Err(QasmParseError::new(
+						line,
+						QasmParseErrorReason::Unsupported(keyword),
+					));
+				}
+				_ if parser.statement_contains("measure") => parser.skip_statement()?,
+				_ => {
+					let call = parser.gate_call(keyword, line)?;
+					let n = self.apply_top_level(&call)?;
+					if count {
+						*self.gate_counts.entry(call.name).or_default() += n;
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	fn add_register(&mut self, name: String, size: usize) {
+		self.registers.push((name, self.qubits, size));
+		self.qubits += size;
+	}
+
+	/// Applies a gate from the program, broadcasting over whole registers.
+	/// Returns the amount of gates applied.
+	fn apply_top_level(&mut self, call: &GateCall) -> Result<usize, QasmParseError> {
+		let env = HashMap::new();
+		let parameters = call
+			.parameters
+			.iter()
+			.map(|expr| expr.evaluate(&env, call.line))
+			.collect::<Result<Vec<f64>, QasmParseError>>()?;
+
+		// Either one qubit or a whole register for each argument
+		let mut arguments: Vec<(usize, Option<usize>)> = Vec::new();
+		let mut broadcast: Option<usize> = None;
+		for argument in call.arguments.iter() {
+			let (_, offset, size) = self
+				.registers
+				.iter()
+				.find(|(name, _, _)| *name == argument.name)
+				.ok_or_else(|| {
+					QasmParseError::new(
+						call.line,
+						QasmParseErrorReason::UnknownRegister(argument.name.clone()),
+					)
+				})?;
+
+			match argument.index {
+				Some(index) if index >= *size => {
+					return Err(QasmParseError::new(
+						call.line,
+						QasmParseErrorReason::IndexOutOfRange {
+							register: argument.name.clone(),
+							index,
+						},
+					));
+				}
+				Some(index) => arguments.push((offset + index, None)),
+				None => {
+					if broadcast.is_some_and(|n| n != *size) {
+						return Err(QasmParseError::new(
+							call.line,
+							QasmParseErrorReason::RegisterSizeMismatch,
+						));
+					}
+					broadcast = Some(*size);
+					arguments.push((*offset, Some(*size)));
+				}
+			}
+		}
+
+		let n = broadcast.unwrap_or(1);
+		for i in 0..n {
+			let qubits: Vec<usize> = arguments
+				.iter()
+				.map(|(qubit, size)| match size {
+					Some(_) => qubit + i,
+					None => *qubit,
+				})
+				.collect();
+			self.apply(&call.name, &parameters, &qubits, call.line)?;
+		}
+
+		Ok(n)
+	}
+
+	fn apply(
+		&mut self,
+		name: &str,
+		parameters: &[f64],
+		qubits: &[usize],
+		line: usize,
+	) -> Result<(), QasmParseError> {
+		for (i, qubit) in qubits.iter().enumerate() {
+			if qubits[..i].contains(qubit) {
+				return Err(QasmParseError::new(
+					line,
+					QasmParseErrorReason::DuplicateQubit(*qubit),
+				));
+			}
+		}
+
+		let (n_parameters, n_qubits) = match primitive(name) {
+			Some(counts) => counts,
+			None => match self.definitions.get(name) {
+				Some(definition) => (definition.parameters.len(), definition.qubits.len()),
+				None => {
+					return Err(QasmParseError::new(
+						line,
+						QasmParseErrorReason::UnknownGate(String::from(name)),
+					));
+				}
+			},
+		};
+		if parameters.len() != n_parameters {
+			return Err(QasmParseError::new(
+				line,
+				QasmParseErrorReason::WrongParameterCount {
+					gate: String::from(name),
+					expected: n_parameters,
+					found: parameters.len(),
+				},
+			));
+		}
+		if qubits.len() != n_qubits {
+			return Err(QasmParseError::new(
+				line,
+				QasmParseErrorReason::WrongArgumentCount {
+					gate: String::from(name),
+					expected: n_qubits,
+					found: qubits.len(),
+				},
+			));
+		}
+
+		if primitive(name).is_none() {
+			return self.apply_definition(name, parameters, qubits);
+		}
+
+		let z = |i: usize| PauliString::z(qubits[i]);
+		let x = |i: usize| PauliString::x(qubits[i]);
+		let y = |i: usize| PauliString::y(qubits[i]);
+		let two = |a: PauliLetter, b: PauliLetter| {
+			let mut string = PauliString::id();
+			string.set(qubits[0], a);
+			string.set(qubits[1], b);
+			string
+		};
+
+		match name {
+			"id" | "i" => {}
+			"x" => self.clifford(x(0), CliffordPauliAngle::PiOver2),
+			"y" => self.clifford(y(0), CliffordPauliAngle::PiOver2),
+			"z" => self.clifford(z(0), CliffordPauliAngle::PiOver2),
+			// $H\propto e^{-i\frac{\pi}{4}Z}e^{-i\frac{\pi}{4}X}e^{-i\frac{\pi}{4}Z}$
+			"h" => {
+				self.clifford(z(0), CliffordPauliAngle::NegPiOver4);
+				self.clifford(x(0), CliffordPauliAngle::NegPiOver4);
+				self.clifford(z(0), CliffordPauliAngle::NegPiOver4);
+			}
+			"s" => self.clifford(z(0), CliffordPauliAngle::NegPiOver4),
+			"sdg" => self.clifford(z(0), CliffordPauliAngle::PiOver4),
+			"sx" => self.clifford(x(0), CliffordPauliAngle::NegPiOver4),
+			"sxdg" => self.clifford(x(0), CliffordPauliAngle::PiOver4),
+			"t" => self.rotation(z(0), -0.125),
+			"tdg" => self.rotation(z(0), 0.125),
+			"rx" => self.rotation_radians(x(0), parameters[0]),
+			"ry" => self.rotation_radians(y(0), parameters[0]),
+			"rz" | "p" | "u1" | "phase" => self.rotation_radians(z(0), parameters[0]),
+			// $U(\theta,\phi,\lambda)\propto R_Z(\phi)R_Y(\theta)R_Z(\lambda)$
+			"u3" | "u" | "U" => {
+				self.rotation_radians(z(0), parameters[2]);
+				self.rotation_radians(y(0), parameters[0]);
+				self.rotation_radians(z(0), parameters[1]);
+			}
+			// $CX\propto e^{-i\frac{\pi}{4}Z_c}e^{-i\frac{\pi}{4}X_t}e^{i\frac{\pi}{4}Z_cX_t}$
+			"cx" | "CX" | "cnot" => {
+				self.clifford(z(0), CliffordPauliAngle::NegPiOver4);
+				self.clifford(x(1), CliffordPauliAngle::NegPiOver4);
+				self.clifford(
+					two(PauliLetter::Z, PauliLetter::X),
+					CliffordPauliAngle::PiOver4,
+				);
+			}
+			"cz" => {
+				self.clifford(z(0), CliffordPauliAngle::NegPiOver4);
+				self.clifford(z(1), CliffordPauliAngle::NegPiOver4);
+				self.clifford(
+					two(PauliLetter::Z, PauliLetter::Z),
+					CliffordPauliAngle::PiOver4,
+				);
+			}
+			"rxx" => self.rotation_radians(two(PauliLetter::X, PauliLetter::X), parameters[0]),
+			"ryy" => self.rotation_radians(two(PauliLetter::Y, PauliLetter::Y), parameters[0]),
+			"rzz" => self.rotation_radians(two(PauliLetter::Z, PauliLetter::Z), parameters[0]),
+			_ => unreachable!(),
+		}
+
+		Ok(())
+	}
+
+	fn apply_definition(
+		&mut self,
+		name: &str,
+		parameters: &[f64],
+		qubits: &[usize],
+	) -> Result<(), QasmParseError> {
+		let definition = self.definitions.get(name).unwrap().clone();
+		let env: HashMap<String, f64> = definition
+			.parameters
+			.iter()
+			.cloned()
+			.zip(parameters.iter().copied())
+			.collect();
+
+		for call in definition.body.iter() {
+			let parameters = call
+				.parameters
+				.iter()
+				.map(|expr| expr.evaluate(&env, call.line))
+				.collect::<Result<Vec<f64>, QasmParseError>>()?;
+
+			let mut call_qubits = Vec::new();
+			for argument in call.arguments.iter() {
+				let index = definition
+					.qubits
+					.iter()
+					.position(|qubit| *qubit == argument.name)
+					.filter(|_| argument.index.is_none())
+					.ok_or_else(|| {
+						QasmParseError::new(
+							call.line,
+							QasmParseErrorReason::UnknownRegister(argument.name.clone()),
+						)
+					})?;
+				call_qubits.push(qubits[index]);
+			}
+
+			self.apply(&call.name, &parameters, &call_qubits, call.line)?;
+		}
+
+		Ok(())
+	}
+
+	/// Adds $R_P(\theta)=e^{-i\frac{\theta}{2}P}$.
+	fn rotation_radians(&mut self, string: PauliString, theta: f64) {
+		self.rotation(string, -theta / (2.0 * PI));
+	}
+
+	/// Adds $e^{i\theta\pi P}$, Clifford angles go to the tableau.
+	fn rotation(&mut self, string: PauliString, theta: f64) {
+		let angle = AngleExpression::constant(theta).simplify();
+		if let PauliAngle::Clifford(angle) = angle {
+			self.clifford(string, angle);
+			return;
+		}
+
+		// The rotation happens after the Cliffords $C$, so $e^{i\theta P}C=Ce^{i\theta C^\dagger PC}$
+		let (sign, string) = self.inverse.conjugate(&string);
+		let mut exp = PauliExp { string, angle };
+		if sign {
+			exp.angle.negate();
+		}
+		self.exps.push(exp);
+	}
+
+	fn clifford(&mut self, string: PauliString, angle: CliffordPauliAngle) {
+		if angle == CliffordPauliAngle::Zero {
+			return;
+		}
+
+		// $(EC)^\dagger=C^\dagger E^\dagger$
+		let mut exp = PauliExp { string, angle };
+		self.tableau.merge_clifford(exp.clone());
+		exp.angle.negate();
+		self.inverse.prepend_clifford(exp);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::qasm::{QasmOptions, QasmVersion, to_qasm};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+
+	#[test]
+	fn cliffords_are_pushed_through() {
+		let circuit = from_qasm(
+			"OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[2];
+creg c[2];
+h q[0];
+s q[1];
+rz(0.3) q[0];
+barrier q;
+measure q -> c;
+",
+		)
+		.unwrap();
+
+		assert_eq!(circuit.qubits, 2);
+		assert_eq!(circuit.gate_counts.get("h"), Some(&1));
+		assert_eq!(circuit.exps.len(), 1);
+		assert_eq!(circuit.exps[0].string, PauliString::x(0));
+		match circuit.exps[0].angle {
+			PauliAngle::MultipleOfPi(v) => assert!((v + 0.3 / (2.0 * PI)).abs() < 1e-12),
+			_ => panic!("Wrong angle"),
+		}
+
+		// H swaps X and Z, S maps X to Y
+		let tableau = circuit.clifford;
+		assert_eq!(tableau.get_x_row(0), PauliString::z(0));
+		assert_eq!(tableau.get_z_row(0), PauliString::x(0));
+		assert_eq!(tableau.get_x_row(1), PauliString::y(1));
+		assert_eq!(tableau.get_z_row(1), PauliString::z(1));
+		assert!(tableau.get_x_signs().last_one().is_none());
+		assert!(tableau.get_z_signs().last_one().is_none());
+	}
+
+	#[test]
+	fn matches_pushing_through_every_clifford() {
+		let mut rng = ChaCha8Rng::seed_from_u64(3);
+		let n_qubits = 4;
+		let mut program = format!("OPENQASM 2.0;\nqreg q[{n_qubits}];\n");
+		let mut cliffords: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
+		let mut exps: Vec<PauliExp<PauliAngle>> = Vec::new();
+		let clifford = |string, angle| PauliExp { string, angle };
+		for _ in 0..200 {
+			let target = rng.random_range(0..n_qubits);
+			match rng.random_range(0..4) {
+				0 => {
+					program += &format!("h q[{target}];\n");
+					for string in [
+						PauliString::z(target),
+						PauliString::x(target),
+						PauliString::z(target),
+					] {
+						cliffords.push(clifford(string, CliffordPauliAngle::NegPiOver4));
+					}
+				}
+				1 => {
+					program += &format!("s q[{target}];\n");
+					cliffords.push(clifford(
+						PauliString::z(target),
+						CliffordPauliAngle::NegPiOver4,
+					));
+				}
+				2 => {
+					let control = (target + rng.random_range(1..n_qubits)) % n_qubits;
+					program += &format!("cx q[{control}], q[{target}];\n");
+					let mut both = PauliString::z(control);
+					both.set(target, PauliLetter::X);
+					cliffords.push(clifford(
+						PauliString::z(control),
+						CliffordPauliAngle::NegPiOver4,
+					));
+					cliffords.push(clifford(
+						PauliString::x(target),
+						CliffordPauliAngle::NegPiOver4,
+					));
+					cliffords.push(clifford(both, CliffordPauliAngle::PiOver4));
+				}
+				_ => {
+					let angle: f64 = rng.random();
+					program += &format!("rz({angle}) q[{target}];\n");
+					let mut exp = PauliExp {
+						string: PauliString::z(target),
+						angle: AngleExpression::constant(-angle / (2.0 * PI)).simplify(),
+					};
+					for clifford in cliffords.iter().rev() {
+						exp.move_clifford_past(clifford);
+					}
+					exps.push(exp);
+				}
+			}
+		}
+
+		let circuit = from_qasm(&program).unwrap();
+		assert_eq!(circuit.exps, exps);
+		let mut tableau = CliffordTableau::id();
+		for clifford in cliffords {
+			tableau.merge_clifford(clifford);
+		}
+		assert_eq!(circuit.clifford, tableau);
+	}
+
+	#[test]
+	fn export_round_trip() {
+		let mut string = PauliString::x(0);
+		string.set(2, PauliLetter::Y);
+		string.set(3, PauliLetter::Z);
+		let exps = vec![
+			PauliExp {
+				string,
+				angle: PauliAngle::MultipleOfPi(0.1),
+			},
+			PauliExp {
+				string: PauliString::y(1),
+				angle: PauliAngle::MultipleOfPi(-0.3),
+			},
+		];
+
+		for native_rotations in [false, true] {
+			let qasm = to_qasm(
+				&exps,
+				&QasmOptions {
+					version: QasmVersion::V2,
+					native_rotations,
+					..Default::default()
+				},
+			)
+			.unwrap();
+			let mut circuit = from_qasm(&qasm).unwrap();
+
+			assert!(circuit.clifford.is_identity());
+			assert_eq!(circuit.exps.len(), exps.len());
+			for (a, b) in circuit.exps.iter().zip(exps.iter()) {
+				assert_eq!(a.string, b.string);
+				match (&a.angle, &b.angle) {
+					(PauliAngle::MultipleOfPi(a), PauliAngle::MultipleOfPi(b)) => {
+						assert!((a - b).abs() < 1e-12)
+					}
+					_ => panic!("Wrong angle"),
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn definitions_and_errors() {
+		let circuit = from_qasm(
+			"OPENQASM 3.0;
+qubit[3] q;
+gate mine(a) x, y { cx x, y; rz(a/2) y; }
+mine(pi/2) q[0], q[1];
+ccx q[0], q[1], q[2];
+rz(pi/2) q;
+",
+		)
+		.unwrap();
+		// rz(pi/2) is Clifford, mine(pi/2) gives a T and the Toffoli 7 Ts
+		assert_eq!(circuit.exps.len(), 8);
+		assert_eq!(circuit.gate_counts.get("rz"), Some(&3));
+
+		let err = from_qasm("qreg q[2];\nfoo q[0];").unwrap_err();
+		assert_eq!(err.line, 2);
+		assert!(matches!(err.reason, QasmParseErrorReason::UnknownGate(_)));
+
+		let err = from_qasm("qreg q[2];\n\ncx q[0], q[2];").unwrap_err();
+		assert_eq!(err.line, 3);
+		assert!(matches!(
+			err.reason,
+			QasmParseErrorReason::IndexOutOfRange { index: 2, .. }
+		));
+
+		let err = from_qasm("qreg q[2];\nreset q[0];").unwrap_err();
+		assert!(matches!(err.reason, QasmParseErrorReason::Unsupported(_)));
+	}
+}
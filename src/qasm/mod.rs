@@ -0,0 +1,11 @@
+//! This module converts Pauli exponentials to and from OpenQASM.
+//!
+//! An exponential $e^{i\theta\pi P}$ is lowered by changing the basis of every
+//! letter of $P$ to $Z$, computing the parity with a ladder of CNOTs and
+//! applying $R_Z(-2\theta\pi)$ to the last qubit.
+
+mod export;
+mod import;
+
+pub use export::{QasmExportError, QasmOptions, QasmVersion, to_qasm};
+pub use import::{QasmCircuit, QasmParseError, QasmParseErrorReason, from_qasm, read_qasm_file};
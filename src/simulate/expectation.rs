@@ -0,0 +1,448 @@
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use simulator::{Complex, DensityMatrix, ParallelStatevector, Statevector};
+
+use super::{StabilizerState, times_i_pow};
+use crate::pauli::{PauliString, PauliSum};
+
+/// Larger Hamiltonians are not solved by [ground_state_energy], every
+/// Lanczos vector on 16 qubits already takes 1 MiB.
+pub const MAX_LANCZOS_QUBITS: usize = 16;
+
+/// [ground_state_energy] keeps at most this many Lanczos vectors, 128 MiB on
+/// [MAX_LANCZOS_QUBITS] qubits.
+pub const MAX_LANCZOS_ITERATIONS: usize = 128;
+
+/// States with an expectation value for Pauli strings.
+pub trait Expectation {
+	/// $\langle\psi|P|\psi\rangle$
+	fn pauli_expectation(&self, string: &PauliString) -> f64;
+}
+
+impl PauliSum {
+	/// $\langle\psi|H|\psi\rangle$
+	pub fn expectation<S: Expectation + ?Sized>(&self, state: &S) -> f64 {
+		self.terms
+			.iter()
+			.map(|(coefficient, string)| coefficient * state.pauli_expectation(string))
+			.sum()
+	}
+}
+
+/// The masks of `string`, checking that it fits into `n_qubits`.
+fn masks(string: &PauliString, n_qubits: usize) -> (usize, usize) {
+	assert!(
+		string.size() <= n_qubits,
+		"The string {} does not fit into {} qubits",
+		string.as_string(),
+		n_qubits
+	);
+	string.masks().unwrap()
+}
+
+fn sign(b: usize, z: usize) -> f64 {
+	if (b & z).count_ones().is_multiple_of(2) {
+		1.0
+	} else {
+		-1.0
+	}
+}
+
+/// With $P|b\rangle=i^{n_Y}(-1)^{|b\wedge z|}|b\oplus x\rangle$ this is
+/// $\sum_b\overline{\psi_{b\oplus x}}i^{n_Y}(-1)^{|b\wedge z|}\psi_b$.
+fn vector_expectation<F: Fn(usize) -> Complex<f64>>(
+	len: usize,
+	x: usize,
+	z: usize,
+	amplitude: F,
+) -> f64 {
+	let mut sum = Complex { re: 0.0, im: 0.0 };
+	for b in 0..len {
+		let value = amplitude(b);
+		let other = amplitude(b ^ x);
+		let s = sign(b, z);
+		sum.re += s * (other.re * value.re + other.im * value.im);
+		sum.im += s * (other.re * value.im - other.im * value.re);
+	}
+	times_i_pow(sum, (x & z).count_ones()).re
+}
+
+impl Expectation for Statevector<f64> {
+	/// # Panics
+	/// Panics if the string does not fit into the state.
+	fn pauli_expectation(&self, string: &PauliString) -> f64 {
+		let (x, z) = masks(string, self.n_qubits);
+		vector_expectation(self.values.len(), x, z, |b| self.values[b])
+	}
+}
+
+impl Expectation for ParallelStatevector {
+	/// # Panics
+	/// Panics if the string does not fit into the state.
+	fn pauli_expectation(&self, string: &PauliString) -> f64 {
+		let (x, z) = masks(string, self.n_qubits());
+		vector_expectation(self.re().len(), x, z, |b| self.get(b))
+	}
+}
+
+impl Expectation for DensityMatrix {
+	/// $\text{Tr}(\rho P)=\sum_r\rho_{r,r\oplus x}i^{n_Y}(-1)^{|r\wedge z|}$
+	///
+	/// # Panics
+	/// Panics if the string does not fit into the matrix.
+	fn pauli_expectation(&self, string: &PauliString) -> f64 {
+		let (x, z) = masks(string, self.n_qubits());
+		let mut sum = Complex { re: 0.0, im: 0.0 };
+		for r in 0..1 << self.n_qubits() {
+			let value = self.get(r, r ^ x);
+			let s = sign(r, z);
+			sum.re += s * value.re;
+			sum.im += s * value.im;
+		}
+		times_i_pow(sum, (x & z).count_ones()).re
+	}
+}
+
+impl Expectation for StabilizerState {
+	fn pauli_expectation(&self, string: &PauliString) -> f64 {
+		self.expectation(string)
+	}
+}
+
+/// `out` $=H$`v`
+fn apply_hamiltonian(terms: &[(f64, usize, usize)], v: &[Complex<f64>], out: &mut [Complex<f64>]) {
+	for value in out.iter_mut() {
+		*value = Complex { re: 0.0, im: 0.0 };
+	}
+	for &(coefficient, x, z) in terms.iter() {
+		let n_y = (x & z).count_ones();
+		for (b, value) in v.iter().enumerate() {
+			let factor = coefficient * sign(b, z);
+			let term = times_i_pow(
+				Complex {
+					re: value.re * factor,
+					im: value.im * factor,
+				},
+				n_y,
+			);
+			out[b ^ x] = out[b ^ x] + term;
+		}
+	}
+}
+
+/// $\langle a|b\rangle$
+fn inner_product(a: &[Complex<f64>], b: &[Complex<f64>]) -> Complex<f64> {
+	let mut sum = Complex { re: 0.0, im: 0.0 };
+	for (a, b) in a.iter().zip(b.iter()) {
+		sum.re += a.re * b.re + a.im * b.im;
+		sum.im += a.re * b.im - a.im * b.re;
+	}
+	sum
+}
+
+/// `a` $\mathrel{-}=c$`b`
+fn subtract_scaled(a: &mut [Complex<f64>], c: Complex<f64>, b: &[Complex<f64>]) {
+	for (a, b) in a.iter_mut().zip(b.iter()) {
+		let scaled = c * *b;
+		a.re -= scaled.re;
+		a.im -= scaled.im;
+	}
+}
+
+fn norm(v: &[Complex<f64>]) -> f64 {
+	inner_product(v, v).re.sqrt()
+}
+
+/// The smallest eigenvalue of the symmetric tridiagonal matrix with
+/// diagonal `alpha` and off-diagonal `beta`, by bisection with Sturm
+/// sequences.
+fn smallest_tridiagonal_eigenvalue(alpha: &[f64], beta: &[f64]) -> f64 {
+	let off = |i: usize| {
+		let left = if i > 0 { beta[i - 1].abs() } else { 0.0 };
+		let right = beta.get(i).map(|b| b.abs()).unwrap_or(0.0);
+		left + right
+	};
+	// Gershgorin bounds
+	let mut lo = (0..alpha.len())
+		.map(|i| alpha[i] - off(i))
+		.fold(f64::INFINITY, f64::min);
+	let mut hi = (0..alpha.len())
+		.map(|i| alpha[i] + off(i))
+		.fold(f64::NEG_INFINITY, f64::max);
+
+	// The amount of eigenvalues below `mu`
+	let count_below = |mu: f64| {
+		let mut count = 0;
+		let mut d = 1.0;
+		for i in 0..alpha.len() {
+			let coupling = if i > 0 {
+				beta[i - 1] * beta[i - 1]
+			} else {
+				0.0
+			};
+			d = alpha[i] - mu - coupling / d;
+			if d == 0.0 {
+				d = f64::EPSILON * (mu.abs() + 1.0);
+			}
+			if d < 0.0 {
+				count += 1;
+			}
+		}
+		count
+	};
+
+	for _ in 0..200 {
+		let mid = 0.5 * (lo + hi);
+		if mid <= lo || mid >= hi {
+			break;
+		}
+		if count_below(mid) >= 1 {
+			hi = mid;
+		} else {
+			lo = mid;
+		}
+	}
+	0.5 * (lo + hi)
+}
+
+/// The last component of the normalised eigenvector for `eigenvalue` of the
+/// symmetric tridiagonal matrix with diagonal `alpha` and off-diagonal `beta`,
+/// by inverse iteration.
+fn last_eigenvector_component(alpha: &[f64], beta: &[f64], eigenvalue: f64) -> f64 {
+	let n = alpha.len();
+	let tiny = f64::EPSILON * (eigenvalue.abs() + 1.0);
+	let mut x = vec![1.0; n];
+	let mut pivots = vec![0.0; n];
+	for _ in 0..3 {
+		// Thomas algorithm on the (nearly singular) shifted matrix
+		pivots[0] = alpha[0] - eigenvalue;
+		for i in 1..n {
+			if pivots[i - 1].abs() < tiny {
+				pivots[i - 1] = tiny;
+			}
+			let factor = beta[i - 1] / pivots[i - 1];
+			pivots[i] = alpha[i] - eigenvalue - factor * beta[i - 1];
+			x[i] -= factor * x[i - 1];
+		}
+		if pivots[n - 1].abs() < tiny {
+			pivots[n - 1] = tiny;
+		}
+		x[n - 1] /= pivots[n - 1];
+		for i in (0..n - 1).rev() {
+			x[i] = (x[i] - beta[i] * x[i + 1]) / pivots[i];
+		}
+
+		let length = x.iter().map(|value| value * value).sum::<f64>().sqrt();
+		for value in x.iter_mut() {
+			*value /= length;
+		}
+	}
+	x[n - 1]
+}
+
+/// # Ground state energy
+///
+/// The smallest eigenvalue of `hamiltonian`, by the Lanczos method on the
+/// full $2^n$ dimensional space. Every product with the Hamiltonian only
+/// uses the masks of the strings, the matrix is never stored. The Lanczos
+/// vectors are fully reorthogonalised, and the iteration stops once the
+/// residual $\beta_k|s_k|$ of the Ritz vector is negligible, which bounds
+/// the error of the energy. After [MAX_LANCZOS_ITERATIONS] vectors the
+/// current estimate, an upper bound, is returned.
+///
+/// # Panics
+/// Panics with more than [MAX_LANCZOS_QUBITS] qubits.
+pub fn ground_state_energy(hamiltonian: &PauliSum) -> f64 {
+	let n_qubits = hamiltonian.n_qubits();
+	assert!(
+		n_qubits <= MAX_LANCZOS_QUBITS,
+		"A Hamiltonian on {n_qubits} qubits is too large for Lanczos"
+	);
+	let terms: Vec<(f64, usize, usize)> = hamiltonian
+		.terms
+		.iter()
+		.map(|(coefficient, string)| {
+			let (x, z) = string.masks().unwrap();
+			(*coefficient, x, z)
+		})
+		.collect();
+	let dim = 1 << n_qubits;
+
+	// A random start has overlap with the ground state
+	let mut rng = ChaCha8Rng::seed_from_u64(0);
+	let mut start: Vec<Complex<f64>> = (0..dim)
+		.map(|_| Complex {
+			re: rng.random::<f64>() - 0.5,
+			im: rng.random::<f64>() - 0.5,
+		})
+		.collect();
+	let length = norm(&start);
+	for value in start.iter_mut() {
+		value.re /= length;
+		value.im /= length;
+	}
+
+	let mut vectors = vec![start];
+	let mut alpha: Vec<f64> = Vec::new();
+	let mut beta: Vec<f64> = Vec::new();
+	let mut w = vec![Complex { re: 0.0, im: 0.0 }; dim];
+	loop {
+		let v = vectors.last().unwrap();
+		apply_hamiltonian(&terms, v, &mut w);
+		alpha.push(inner_product(v, &w).re);
+
+		// Twice is enough to stay orthogonal
+		for _ in 0..2 {
+			for v in vectors.iter() {
+				let overlap = inner_product(v, &w);
+				subtract_scaled(&mut w, overlap, v);
+			}
+		}
+
+		let energy = smallest_tridiagonal_eigenvalue(&alpha, &beta);
+		let b = norm(&w);
+		let residual = b * last_eigenvector_component(&alpha, &beta, energy).abs();
+		if residual <= 1e-10 * energy.abs().max(1.0)
+			|| vectors.len() == dim.min(MAX_LANCZOS_ITERATIONS)
+		{
+			return energy;
+		}
+
+		beta.push(b);
+		vectors.push(
+			w.iter()
+				.map(|value| Complex {
+					re: value.re / b,
+					im: value.im / b,
+				})
+				.collect(),
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		misc::NonZeroEvenUsize,
+		pauli::{PauliAngle, PauliExp, PauliLetter},
+		pauli_string,
+		simulate::{ApplyExp, apply_pauli_rotation},
+		synthesize::synthesize,
+	};
+
+	/// $\sum_i X_iX_{i+1}+Y_iY_{i+1}+Z_iZ_{i+1}$ on an open chain
+	fn heisenberg(n_qubits: usize) -> PauliSum {
+		let mut sum = PauliSum::new();
+		for i in 0..n_qubits - 1 {
+			for letter in [PauliLetter::X, PauliLetter::Y, PauliLetter::Z] {
+				let mut string = PauliString::default();
+				string.set(i, letter);
+				string.set(i + 1, letter);
+				sum.push(1.0, string);
+			}
+		}
+		sum
+	}
+
+	#[test]
+	fn expectations_agree() {
+		let hamiltonian: PauliSum = [
+			(0.5, pauli_string!("XYZ")),
+			(-0.3, pauli_string!("ZZI")),
+			(0.7, pauli_string!("IYI")),
+			(1.5, pauli_string!("III")),
+		]
+		.into_iter()
+		.collect();
+
+		let mut state = Statevector::new(3);
+		for i in 0..3 {
+			apply_pauli_rotation(&mut state, &PauliString::y(i), 0.1 + 0.2 * i as f64);
+			apply_pauli_rotation(&mut state, &PauliString::x(i), 0.3 - 0.1 * i as f64);
+		}
+		let expected = hamiltonian.expectation(&state);
+		let parallel = ParallelStatevector::from(state.clone());
+		assert!((hamiltonian.expectation(&parallel) - expected).abs() < 1e-12);
+		let matrix = DensityMatrix::from_statevector(&state);
+		assert!((hamiltonian.expectation(&matrix) - expected).abs() < 1e-12);
+
+		// $|000\rangle$ is stabilized by ZZI
+		let stabilizer = StabilizerState::new(3, 0);
+		assert!((hamiltonian.expectation(&stabilizer) - 1.2).abs() < 1e-12);
+		assert!((hamiltonian.expectation(&Statevector::new(3)) - 1.2).abs() < 1e-12);
+	}
+
+	#[test]
+	fn lanczos_ground_state() {
+		// Singlet
+		assert!((ground_state_energy(&heisenberg(2)) + 3.0).abs() < 1e-10);
+		let energy = ground_state_energy(&heisenberg(4));
+		assert!((energy + 3.0 + 2.0 * 3f64.sqrt()).abs() < 1e-10);
+
+		let constant: PauliSum = [(0.25, PauliString::default())].into_iter().collect();
+		assert!((ground_state_energy(&constant) - 0.25).abs() < 1e-12);
+	}
+
+	#[test]
+	fn lanczos_free_fermions() {
+		// The XX chain is free fermions with the modes $4\cos\frac{k\pi}{n+1}$,
+		// the ground state fills the negative ones
+		let n_qubits = 12;
+		let mut hamiltonian = PauliSum::new();
+		for i in 0..n_qubits - 1 {
+			for letter in [PauliLetter::X, PauliLetter::Y] {
+				let mut string = PauliString::default();
+				string.set(i, letter);
+				string.set(i + 1, letter);
+				hamiltonian.push(1.0, string);
+			}
+		}
+		let exact: f64 = (1..=n_qubits)
+			.map(|k| 4.0 * (k as f64 * std::f64::consts::PI / (n_qubits + 1) as f64).cos())
+			.filter(|mode| *mode < 0.0)
+			.sum();
+		assert!((ground_state_energy(&hamiltonian) - exact).abs() < 1e-9);
+	}
+
+	#[test]
+	fn tridiagonal_eigenvector() {
+		// The smallest eigenvector is $(1, -\sqrt{2}, 1) / 2$
+		let (alpha, beta) = ([2.0; 3], [1.0; 2]);
+		let eigenvalue = smallest_tridiagonal_eigenvalue(&alpha, &beta);
+		assert!((eigenvalue - (2.0 - 2f64.sqrt())).abs() < 1e-12);
+		let last = last_eigenvector_component(&alpha, &beta, eigenvalue);
+		assert!((last.abs() - 0.5).abs() < 1e-10);
+	}
+
+	#[test]
+	fn trotter_step_keeps_energy() {
+		let hamiltonian = heisenberg(5);
+		let dt = 0.05;
+		let step: Vec<PauliExp<PauliAngle>> = hamiltonian
+			.terms
+			.iter()
+			.map(|(coefficient, string)| PauliExp {
+				string: string.clone(),
+				angle: PauliAngle::MultipleOfPi(coefficient * dt),
+			})
+			.collect();
+
+		let mut initial = Statevector::new(5);
+		for i in 0..5 {
+			apply_pauli_rotation(&mut initial, &PauliString::y(i), 0.2 * i as f64 + 0.1);
+		}
+		// The terms may be reordered, which is another Trotter step
+		let result = synthesize(step, NonZeroEvenUsize::new(4).unwrap(), None);
+		let mut expected = initial.clone();
+		expected.apply_exps(&result.ordered());
+
+		let mut state = initial;
+		state.apply_exps(&result.circuit);
+		state.apply_exps(&result.clifford);
+
+		let energy = hamiltonian.expectation(&expected);
+		assert!((hamiltonian.expectation(&state) - energy).abs() < 1e-10);
+		assert!(energy >= ground_state_energy(&hamiltonian) - 1e-10);
+	}
+}
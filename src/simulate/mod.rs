@@ -0,0 +1,278 @@
+//! Simulation of Pauli exponentials.
+//!
+//! On a statevector the exponentials are applied directly with the x and z
+//! masks of their strings, so there is no decomposition into gates involved.
+//! This makes it the reference to test circuits from [crate::synthesize] and
+//! [crate::clifford_tableau::CliffordTableau::decompose] against.
+//!
+//! Clifford circuits on many qubits can be simulated with a
+//! [StabilizerState] instead. The fidelity of circuits under a
+//! [simulator::NoiseModel] is simulated with a [simulator::DensityMatrix] or
+//! with noisy trajectories.
+//!
+//! For a [crate::pauli::PauliSum] all of these states give an expectation
+//! value, and [ground_state_energy] gives the exact energy to compare against.
+
+mod expectation;
+mod noise;
+mod stabilizer;
+
+use std::f64::consts::PI;
+
+use simulator::{Complex, ParallelStatevector, Statevector};
+
+use crate::pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliString};
+
+pub use expectation::{
+	Expectation, MAX_LANCZOS_ITERATIONS, MAX_LANCZOS_QUBITS, ground_state_energy,
+};
+pub use noise::{density_matrix_fidelity, estimated_fidelity, trajectory_fidelity};
+pub use stabilizer::StabilizerState;
+
+/// Applies Pauli exponentials to a state. Qubit `i` is bit `i` of the
+/// state index.
+pub trait ApplyExp<A: Negate> {
+	/// Applies $e^{i\theta P}$.
+	fn apply_exp(&mut self, exp: &PauliExp<A>);
+
+	/// Applies the exponentials, index 0 first.
+	fn apply_exps(&mut self, exps: &[PauliExp<A>]) {
+		for exp in exps.iter() {
+			self.apply_exp(exp);
+		}
+	}
+}
+
+impl ApplyExp<PauliAngle> for Statevector<f64> {
+	/// # Panics
+	/// Panics if the angle has parameters, see [crate::pauli::bind], or if
+	/// the string does not fit into the state.
+	fn apply_exp(&mut self, exp: &PauliExp<PauliAngle>) {
+		let angle = exp.angle.to_expression();
+		assert!(
+			angle.terms().is_empty(),
+			"Can not simulate the unbound parameters in {angle}"
+		);
+		apply_pauli_rotation(self, &exp.string, angle.constant_part());
+	}
+}
+
+impl ApplyExp<CliffordPauliAngle> for Statevector<f64> {
+	/// # Panics
+	/// Panics if the string does not fit into the state.
+	fn apply_exp(&mut self, exp: &PauliExp<CliffordPauliAngle>) {
+		apply_pauli_rotation(self, &exp.string, exp.angle.as_multiple_of_pi());
+	}
+}
+
+impl ApplyExp<PauliAngle> for ParallelStatevector {
+	/// # Panics
+	/// Panics if the angle has parameters, see [crate::pauli::bind], or if
+	/// the string does not fit into the state.
+	fn apply_exp(&mut self, exp: &PauliExp<PauliAngle>) {
+		let angle = exp.angle.to_expression();
+		assert!(
+			angle.terms().is_empty(),
+			"Can not simulate the unbound parameters in {angle}"
+		);
+		apply_parallel_pauli_rotation(self, &exp.string, angle.constant_part());
+	}
+}
+
+impl ApplyExp<CliffordPauliAngle> for ParallelStatevector {
+	/// # Panics
+	/// Panics if the string does not fit into the state.
+	fn apply_exp(&mut self, exp: &PauliExp<CliffordPauliAngle>) {
+		apply_parallel_pauli_rotation(self, &exp.string, exp.angle.as_multiple_of_pi());
+	}
+}
+
+fn apply_parallel_pauli_rotation(
+	state: &mut ParallelStatevector,
+	string: &PauliString,
+	angle: f64,
+) {
+	assert!(
+		string.size() <= state.n_qubits(),
+		"The string {} does not fit into {} qubits",
+		string.as_string(),
+		state.n_qubits()
+	);
+	let (x, z) = string.masks().unwrap();
+	state.apply_pauli(x, z, angle * PI);
+}
+
+/// Multiplies by $i^k$.
+fn times_i_pow(value: Complex<f64>, k: u32) -> Complex<f64> {
+	match k % 4 {
+		0 => value,
+		1 => Complex {
+			re: -value.im,
+			im: value.re,
+		},
+		2 => Complex {
+			re: -value.re,
+			im: -value.im,
+		},
+		_ => Complex {
+			re: value.im,
+			im: -value.re,
+		},
+	}
+}
+
+fn scale(value: Complex<f64>, factor: f64) -> Complex<f64> {
+	Complex {
+		re: value.re * factor,
+		im: value.im * factor,
+	}
+}
+
+/// # Apply Pauli rotation
+///
+/// Applies $e^{i\theta P}=\cos(\theta)I+i\sin(\theta)P$, where `angle` is
+/// $\theta$ as a multiple of pi.
+///
+/// With $Y=iXZ$ the string acts on a basis state as
+///
+/// $$P|b\rangle=i^{n_Y}(-1)^{|b\wedge z|}|b\oplus x\rangle,$$
+///
+/// so the amplitudes are only mixed in pairs $b$ and $b\oplus x$.
+///
+/// # Panics
+/// Panics if the string acts on qubits outside of the state.
+pub fn apply_pauli_rotation(state: &mut Statevector<f64>, string: &PauliString, angle: f64) {
+	assert!(
+		string.size() <= state.n_qubits(),
+		"The string {} does not fit into {} qubits",
+		string.as_string(),
+		state.n_qubits()
+	);
+	let (x, z) = string.masks().unwrap();
+	let n_y = (x & z).count_ones();
+	let theta = angle * PI;
+	let (cos, sin) = (theta.cos(), theta.sin());
+	let parity = |b: usize| {
+		if (b & z).count_ones().is_multiple_of(2) {
+			1.0
+		} else {
+			-1.0
+		}
+	};
+
+	if x == 0 {
+		for (b, value) in state.values.iter_mut().enumerate() {
+			*value = scale(*value, cos) + times_i_pow(scale(*value, sin * parity(b)), 1);
+		}
+		return;
+	}
+
+	// Every pair once, from the side where the highest bit of x is zero
+	let high = 1 << x.ilog2();
+	for b in (0..state.values.len()).filter(|b| b & high == 0) {
+		let flipped = b ^ x;
+		let a = state.values[b];
+		let c = state.values[flipped];
+		state.values[flipped] = scale(c, cos) + times_i_pow(scale(a, sin * parity(b)), n_y + 1);
+		state.values[b] = scale(a, cos) + times_i_pow(scale(c, sin * parity(flipped)), n_y + 1);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		misc::NonZeroEvenUsize,
+		pauli::PauliLetter,
+		pauli_string,
+		test_utils::{random_cliffords, tableau},
+	};
+	use rand::{RngExt, SeedableRng};
+	use rand_chacha::ChaCha8Rng;
+
+	fn assert_close(a: &Statevector<f64>, b: &Statevector<f64>) {
+		for (a, b) in a.values().iter().zip(b.values().iter()) {
+			assert!((a.re - b.re).abs() < 1e-10 && (a.im - b.im).abs() < 1e-10);
+		}
+	}
+
+	#[test]
+	fn single_qubit_rotations() {
+		// $e^{i\frac{\pi}{2}X}=iX$
+		let mut state = Statevector::new(2);
+		state.apply_exp(&PauliExp {
+			string: pauli_string!("IX"),
+			angle: CliffordPauliAngle::PiOver2,
+		});
+		assert!((state[2].im - 1.0).abs() < 1e-10);
+
+		// $e^{i\frac{\pi}{2}Y}|1\rangle=iY|1\rangle=|0\rangle$
+		let mut state = Statevector::new(1);
+		state.values.swap(0, 1);
+		state.apply_exp(&PauliExp {
+			string: pauli_string!("Y"),
+			angle: CliffordPauliAngle::PiOver2,
+		});
+		assert!((state[0].re - 1.0).abs() < 1e-10);
+
+		// $e^{i\theta Z}|0\rangle=e^{i\theta}|0\rangle$
+		let mut state = Statevector::new(1);
+		state.apply_exp(&PauliExp {
+			string: pauli_string!("Z"),
+			angle: PauliAngle::MultipleOfPi(0.25),
+		});
+		let s = std::f64::consts::FRAC_1_SQRT_2;
+		assert!((state[0].re - s).abs() < 1e-10 && (state[0].im - s).abs() < 1e-10);
+	}
+
+	#[test]
+	fn parallel_matches_statevector() {
+		let n_qubits = simulator::PARALLEL_THRESHOLD + 1;
+		let mut rng = ChaCha8Rng::seed_from_u64(5);
+		let mut state = Statevector::new(n_qubits);
+		let mut parallel = ParallelStatevector::new(n_qubits).with_threads(4);
+		for _ in 0..30 {
+			let mut string = PauliString::default();
+			for i in 0..n_qubits {
+				if rng.random_bool(0.3) {
+					string.set(i, [PauliLetter::X, PauliLetter::Y, PauliLetter::Z][i % 3]);
+				}
+			}
+			let exp = PauliExp {
+				string,
+				angle: PauliAngle::MultipleOfPi(rng.random()),
+			};
+			state.apply_exp(&exp);
+			parallel.apply_exp(&exp);
+		}
+		assert_close(&state, &parallel.into());
+	}
+
+	#[test]
+	fn decompose_matches_tableau() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let n_qubits = 8;
+		let mut rng = ChaCha8Rng::seed_from_u64(3);
+		let cliffords = random_cliffords(n_qubits, 30, &mut rng);
+		let tableau = tableau(&cliffords);
+		let decomposition = tableau.decompose(gate_size, None);
+
+		// Compare up to the global phase on a state with a non-zero first
+		// amplitude
+		let mut initial = Statevector::new(n_qubits);
+		for i in 0..n_qubits {
+			apply_pauli_rotation(&mut initial, &PauliString::y(i), rng.random());
+			apply_pauli_rotation(&mut initial, &PauliString::z(i), rng.random());
+		}
+		let mut expected = initial.clone();
+		expected.apply_exps(&cliffords);
+		let mut state = initial;
+		state.apply_exps(&decomposition);
+
+		let phase = expected[0] / state[0];
+		for value in state.values.iter_mut() {
+			*value = *value * phase;
+		}
+		assert_close(&state, &expected);
+	}
+}
@@ -0,0 +1,203 @@
+use std::f64::consts::PI;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use simulator::{DensityMatrix, NoiseModel, Statevector};
+
+use super::{ApplyExp, apply_pauli_rotation};
+use crate::pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliLetter, PauliString};
+
+impl ApplyExp<PauliAngle> for DensityMatrix {
+	/// # Panics
+	/// Panics if the angle has parameters, see [crate::pauli::bind], or if
+	/// the string does not fit into the matrix.
+	fn apply_exp(&mut self, exp: &PauliExp<PauliAngle>) {
+		let angle = exp.angle.to_expression();
+		assert!(
+			angle.terms().is_empty(),
+			"Can not simulate the unbound parameters in {angle}"
+		);
+		apply_density_pauli_rotation(self, &exp.string, angle.constant_part());
+	}
+}
+
+impl ApplyExp<CliffordPauliAngle> for DensityMatrix {
+	/// # Panics
+	/// Panics if the string does not fit into the matrix.
+	fn apply_exp(&mut self, exp: &PauliExp<CliffordPauliAngle>) {
+		apply_density_pauli_rotation(self, &exp.string, exp.angle.as_multiple_of_pi());
+	}
+}
+
+fn apply_density_pauli_rotation(matrix: &mut DensityMatrix, string: &PauliString, angle: f64) {
+	assert!(
+		string.size() <= matrix.n_qubits(),
+		"The string {} does not fit into {} qubits",
+		string.as_string(),
+		matrix.n_qubits()
+	);
+	let (x, z) = string.masks().unwrap();
+	matrix.apply_pauli(x, z, angle * PI);
+}
+
+/// The qubits a gate acts on, as a mask.
+fn support(string: &PauliString) -> usize {
+	let (x, z) = string.masks().expect("The string has too many qubits");
+	x | z
+}
+
+/// The string with the x and z masks from [NoiseModel::sample_error].
+fn error_string(x: usize, z: usize) -> PauliString {
+	let mut string = PauliString::default();
+	for qubit in 0..(x | z).ilog2() as usize + 1 {
+		let letter = match (x >> qubit & 1, z >> qubit & 1) {
+			(0, 0) => continue,
+			(1, 0) => PauliLetter::X,
+			(1, 1) => PauliLetter::Y,
+			_ => PauliLetter::Z,
+		};
+		string.set(qubit, letter);
+	}
+	string
+}
+
+fn n_qubits<A: Negate>(circuit: &[PauliExp<A>]) -> usize {
+	circuit
+		.iter()
+		.map(|exp| exp.string.size())
+		.max()
+		.unwrap_or(0)
+}
+
+/// # Estimated fidelity
+///
+/// The probability that no error happens in `circuit`, which is a lower
+/// bound on its fidelity. Every exponential counts as one gate on the qubits
+/// of its string. This is cheap for any amount of qubits.
+pub fn estimated_fidelity<A: Negate>(circuit: &[PauliExp<A>], model: &NoiseModel) -> f64 {
+	circuit
+		.iter()
+		.map(|exp| model.gate_fidelity(exp.string.len()))
+		.product()
+}
+
+/// # Density matrix fidelity
+///
+/// The exact fidelity $\langle\psi|\rho|\psi\rangle$ of `circuit` applied to
+/// $|0\dots0\rangle$, where every exponential is followed by the noise of
+/// `model` on the qubits of its string.
+///
+/// # Panics
+/// Panics if the circuit has more than
+/// [simulator::MAX_DENSITY_MATRIX_QUBITS] qubits or unbound parameters.
+pub fn density_matrix_fidelity<A: Negate>(circuit: &[PauliExp<A>], model: &NoiseModel) -> f64
+where
+	DensityMatrix: ApplyExp<A>,
+	Statevector<f64>: ApplyExp<A>,
+{
+	let n_qubits = n_qubits(circuit);
+	let mut ideal = Statevector::new(n_qubits);
+	ideal.apply_exps(circuit);
+
+	let mut matrix = DensityMatrix::new(n_qubits);
+	for exp in circuit.iter() {
+		matrix.apply_exp(exp);
+		matrix.apply_noise(support(&exp.string), model);
+	}
+	matrix.fidelity(&ideal)
+}
+
+/// # Trajectory fidelity
+///
+/// Estimates the fidelity like [density_matrix_fidelity] by averaging
+/// `trajectories` noisy statevectors. The errors of a trajectory are drawn
+/// up front, a trajectory without errors has fidelity 1 and is not
+/// simulated.
+pub fn trajectory_fidelity<A: Negate>(
+	circuit: &[PauliExp<A>],
+	model: &NoiseModel,
+	trajectories: usize,
+	seed: u64,
+) -> f64
+where
+	Statevector<f64>: ApplyExp<A>,
+{
+	let n_qubits = n_qubits(circuit);
+	let mut ideal = Statevector::new(n_qubits);
+	ideal.apply_exps(circuit);
+
+	let mut rng = ChaCha8Rng::seed_from_u64(seed);
+	let mut total = 0.0;
+	for _ in 0..trajectories {
+		let errors: Vec<(usize, usize)> = circuit
+			.iter()
+			.map(|exp| model.sample_error(support(&exp.string), &mut rng))
+			.collect();
+		if errors.iter().all(|(x, z)| x | z == 0) {
+			total += 1.0;
+			continue;
+		}
+
+		let mut state = Statevector::new(n_qubits);
+		for (exp, (x, z)) in circuit.iter().zip(errors) {
+			state.apply_exp(exp);
+			if x | z != 0 {
+				// $e^{i\frac{\pi}{2}P}=iP$
+				apply_pauli_rotation(&mut state, &error_string(x, z), 0.5);
+			}
+		}
+
+		let overlap =
+			ideal
+				.values
+				.iter()
+				.zip(state.values.iter())
+				.fold((0.0, 0.0), |(re, im), (a, b)| {
+					(
+						re + a.re * b.re + a.im * b.im,
+						im + a.re * b.im - a.im * b.re,
+					)
+				});
+		total += overlap.0 * overlap.0 + overlap.1 * overlap.1;
+	}
+
+	total / trajectories as f64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::pauli_string;
+	use simulator::PauliChannel;
+
+	#[test]
+	fn fidelities_agree() {
+		let circuit = vec![
+			PauliExp {
+				string: pauli_string!("XZY"),
+				angle: PauliAngle::MultipleOfPi(0.3),
+			},
+			PauliExp {
+				string: pauli_string!("IYI"),
+				angle: PauliAngle::MultipleOfPi(0.1),
+			},
+			PauliExp {
+				string: pauli_string!("ZZI"),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			},
+		];
+		let model = NoiseModel::new(PauliChannel::new(0.02, 0.01, 0.03), 0.5);
+
+		let exact = density_matrix_fidelity(&circuit, &model);
+		let estimate = estimated_fidelity(&circuit, &model);
+		assert!(exact < 1.0);
+		assert!(estimate <= exact + 1e-12);
+
+		let sampled = trajectory_fidelity(&circuit, &model, 4000, 1);
+		assert!((sampled - exact).abs() < 0.02, "{sampled} vs {exact}");
+
+		let noiseless = NoiseModel::depolarizing(0.0);
+		assert!((density_matrix_fidelity(&circuit, &noiseless) - 1.0).abs() < 1e-10);
+		assert_eq!(trajectory_fidelity(&circuit, &noiseless, 10, 1), 1.0);
+	}
+}
@@ -0,0 +1,310 @@
+use bits::Bits;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::ApplyExp;
+use crate::pauli::{CliffordPauliAngle, PauliExp, PauliString};
+
+/// # Stabilizer state
+///
+/// A state that can only be reached with Clifford operations, simulated with
+/// the method of [Aaronson and Gottesman](https://arxiv.org/abs/quant-ph/0406196).
+/// The state is described by $n$ stabilizers $S_i$ with $S_i|\psi\rangle=|\psi\rangle$,
+/// which fix the state, and $n$ destabilizers $D_i$ that only anticommute with
+/// $S_i$. The memory needed grows quadratically in the amount of qubits
+/// instead of exponentially.
+///
+/// Measurement outcomes are `true` for the $-1$ eigenvalue. The random
+/// outcomes are reproducible with the seed.
+#[derive(Debug, Clone)]
+pub struct StabilizerState {
+	stabilizers: Vec<PauliString>,
+	destabilizers: Vec<PauliString>,
+	/// Ones for the stabilizers with a negative sign.
+	signs: Bits,
+	rng: ChaCha8Rng,
+}
+
+impl StabilizerState {
+	/// The state $|0\dots0\rangle$. Qubits are added in the $|0\rangle$ state
+	/// when an operation needs them.
+	pub fn new(n_qubits: usize, seed: u64) -> Self {
+		let mut state = Self {
+			stabilizers: Vec::new(),
+			destabilizers: Vec::new(),
+			signs: Bits::new(),
+			rng: ChaCha8Rng::seed_from_u64(seed),
+		};
+		state.grow(n_qubits);
+		state
+	}
+
+	pub fn n_qubits(&self) -> usize {
+		self.stabilizers.len()
+	}
+
+	/// The stabilizers with their sign, `true` for a negative one.
+	pub fn stabilizers(&self) -> impl Iterator<Item = (bool, &PauliString)> {
+		self.stabilizers
+			.iter()
+			.enumerate()
+			.map(|(i, string)| (self.signs.get(i), string))
+	}
+
+	fn grow(&mut self, n_qubits: usize) {
+		for i in self.n_qubits()..n_qubits {
+			self.stabilizers.push(PauliString::z(i));
+			self.destabilizers.push(PauliString::x(i));
+		}
+	}
+
+	/// Applies the Clifford by conjugating the stabilizers, $S\to USU^\dagger$.
+	pub fn apply_clifford(&mut self, clifford: &PauliExp<CliffordPauliAngle>) {
+		self.grow(clifford.string.size());
+
+		let conjugate = |string: &mut PauliString| match clifford.angle {
+			CliffordPauliAngle::PiOver4 => string.pi_over_4_sandwitch(false, &clifford.string),
+			CliffordPauliAngle::NegPiOver4 => string.pi_over_4_sandwitch(true, &clifford.string),
+			// $e^{\pm i\frac{\pi}{2}O}=\pm iO$, so $OSO$
+			CliffordPauliAngle::PiOver2 | CliffordPauliAngle::NegPiOver2 => {
+				string.anticommutes_with(&clifford.string)
+			}
+			CliffordPauliAngle::Zero => false,
+		};
+
+		for (i, string) in self.stabilizers.iter_mut().enumerate() {
+			if conjugate(string) {
+				let sign = self.signs.get(i);
+				self.signs.set(i, !sign);
+			}
+		}
+		for string in self.destabilizers.iter_mut() {
+			conjugate(string);
+		}
+	}
+
+	/// $S_i\to S_iS_j$, the stabilizers commute so the phase is a sign.
+	fn multiply_stabilizer(&mut self, i: usize, j: usize) {
+		let other = self.stabilizers[j].clone();
+		let phase = self.stabilizers[i].multiply(&other);
+		let sign = self.signs.get(i) ^ self.signs.get(j) ^ (phase == 2);
+		self.signs.set(i, sign);
+	}
+
+	/// The outcome of measuring `string` if it is certain, that is when
+	/// `string` is $\pm$ a product of stabilizers.
+	fn determined_outcome(&self, string: &PauliString) -> Option<bool> {
+		if self
+			.stabilizers
+			.iter()
+			.any(|stabilizer| stabilizer.anticommutes_with(string))
+		{
+			return None;
+		}
+
+		// The product of the stabilizers whose destabilizer anticommutes
+		let mut product = PauliString::default();
+		let mut phase = 0;
+		for (i, destabilizer) in self.destabilizers.iter().enumerate() {
+			if destabilizer.anticommutes_with(string) {
+				phase += product.multiply(&self.stabilizers[i]);
+				if self.signs.get(i) {
+					phase += 2;
+				}
+			}
+		}
+		debug_assert_eq!(product, *string);
+
+		Some(phase % 4 == 2)
+	}
+
+	/// $\langle\psi|P|\psi\rangle$, which is $\pm1$ or 0 for stabilizer
+	/// states.
+	pub fn expectation(&self, string: &PauliString) -> f64 {
+		if string.size() > self.n_qubits() {
+			let mut state = self.clone();
+			state.grow(string.size());
+			return state.expectation(string);
+		}
+
+		match self.determined_outcome(string) {
+			Some(false) => 1.0,
+			Some(true) => -1.0,
+			None => 0.0,
+		}
+	}
+
+	/// Measures the observable `string` and collapses the state.
+	pub fn measure(&mut self, string: &PauliString) -> bool {
+		self.grow(string.size());
+		if let Some(outcome) = self.determined_outcome(string) {
+			return outcome;
+		}
+
+		let p = self
+			.stabilizers
+			.iter()
+			.position(|stabilizer| stabilizer.anticommutes_with(string))
+			.unwrap();
+		for i in 0..self.n_qubits() {
+			if i != p && self.stabilizers[i].anticommutes_with(string) {
+				self.multiply_stabilizer(i, p);
+			}
+			if i != p && self.destabilizers[i].anticommutes_with(string) {
+				let stabilizer = self.stabilizers[p].clone();
+				self.destabilizers[i].multiply(&stabilizer);
+			}
+		}
+
+		let outcome = self.rng.random::<bool>();
+		self.destabilizers[p] = std::mem::replace(&mut self.stabilizers[p], string.clone());
+		self.signs.set(p, outcome);
+		outcome
+	}
+
+	pub fn measure_x(&mut self, qubit: usize) -> bool {
+		self.measure(&PauliString::x(qubit))
+	}
+
+	pub fn measure_y(&mut self, qubit: usize) -> bool {
+		self.measure(&PauliString::y(qubit))
+	}
+
+	pub fn measure_z(&mut self, qubit: usize) -> bool {
+		self.measure(&PauliString::z(qubit))
+	}
+
+	/// Measures `qubits` in the $Z$ basis `shots` times, without collapsing
+	/// `self`.
+	pub fn sample(&mut self, qubits: &[usize], shots: usize) -> Vec<Vec<bool>> {
+		(0..shots)
+			.map(|_| {
+				let mut shot = self.clone();
+				shot.rng = ChaCha8Rng::seed_from_u64(self.rng.random());
+				qubits.iter().map(|qubit| shot.measure_z(*qubit)).collect()
+			})
+			.collect()
+	}
+
+	/// Whether both describe the same state, which is when all stabilizers of
+	/// one stabilize the other.
+	pub fn is_same_state(&self, other: &Self) -> bool {
+		self.stabilizers()
+			.all(|(sign, string)| other.expectation(string) == if sign { -1.0 } else { 1.0 })
+			&& other
+				.stabilizers()
+				.all(|(sign, string)| self.expectation(string) == if sign { -1.0 } else { 1.0 })
+	}
+}
+
+impl ApplyExp<CliffordPauliAngle> for StabilizerState {
+	fn apply_exp(&mut self, exp: &PauliExp<CliffordPauliAngle>) {
+		self.apply_clifford(exp);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use simulator::Statevector;
+
+	use super::*;
+	use crate::{
+		clifford_tableau::CliffordTableau,
+		misc::NonZeroEvenUsize,
+		pauli_string,
+		simulate::apply_pauli_rotation,
+		test_utils::{random_cliffords, random_string},
+	};
+
+	/// $\langle\psi|P|\psi\rangle$ from $e^{i\frac{\pi}{2}P}=iP$.
+	fn statevector_expectation(state: &Statevector<f64>, string: &PauliString) -> f64 {
+		let mut applied = state.clone();
+		apply_pauli_rotation(&mut applied, string, 0.5);
+		state
+			.values()
+			.iter()
+			.zip(applied.values().iter())
+			.map(|(a, b)| a.re * b.im - a.im * b.re)
+			.sum()
+	}
+
+	#[test]
+	fn matches_statevector() {
+		let n_qubits = 5;
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		for _ in 0..10 {
+			let cliffords = random_cliffords(n_qubits, 20, &mut rng);
+			let mut state = Statevector::new(n_qubits);
+			state.apply_exps(&cliffords);
+			let mut stabilizer = StabilizerState::new(n_qubits, 0);
+			stabilizer.apply_exps(&cliffords);
+
+			for _ in 0..20 {
+				let string = random_string(n_qubits, &mut rng);
+				let expected = statevector_expectation(&state, &string);
+				assert!((stabilizer.expectation(&string) - expected).abs() < 1e-9);
+			}
+		}
+	}
+
+	#[test]
+	fn measurements_collapse() {
+		// $e^{-i\frac{\pi}{4}Y}$ maps $Z$ to $X$, then $e^{i\frac{\pi}{4}ZX}$
+		// and $e^{-i\frac{\pi}{4}X}$ give a Bell state stabilized by $\pm YX$
+		// and $\pm ZZ$
+		let mut state = StabilizerState::new(2, 7);
+		state.apply_clifford(&PauliExp {
+			string: pauli_string!("Y"),
+			angle: CliffordPauliAngle::NegPiOver4,
+		});
+		assert_eq!(state.expectation(&pauli_string!("X")), 1.0);
+		assert!(!state.measure_x(0));
+		state.apply_clifford(&PauliExp {
+			string: pauli_string!("ZX"),
+			angle: CliffordPauliAngle::PiOver4,
+		});
+		state.apply_clifford(&PauliExp {
+			string: pauli_string!("IX"),
+			angle: CliffordPauliAngle::NegPiOver4,
+		});
+		assert_eq!(state.expectation(&pauli_string!("ZZ")).abs(), 1.0);
+		assert_eq!(state.expectation(&pauli_string!("YX")).abs(), 1.0);
+		assert_eq!(state.expectation(&pauli_string!("ZI")), 0.0);
+
+		let parity = state.expectation(&pauli_string!("ZZ")) < 0.0;
+		let shots = state.sample(&[0, 1], 100);
+		assert!(shots.iter().all(|shot| (shot[0] ^ shot[1]) == parity));
+		assert!(shots.iter().any(|shot| shot[0]) && shots.iter().any(|shot| !shot[0]));
+
+		let first = state.measure_z(0);
+		assert_eq!(state.measure_z(1), first ^ parity);
+		assert_eq!(state.measure_z(0), first);
+		assert_eq!(state.expectation(&pauli_string!("YX")), 0.0);
+	}
+
+	#[test]
+	fn decompose_on_many_qubits() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let n_qubits = 100;
+		let mut rng = ChaCha8Rng::seed_from_u64(2);
+		let cliffords = random_cliffords(n_qubits, 200, &mut rng);
+
+		let mut tableau = CliffordTableau::id();
+		for clifford in cliffords.iter() {
+			tableau.merge_clifford(clifford.clone());
+		}
+		let decomposition = tableau.decompose(gate_size, None);
+
+		let mut expected = StabilizerState::new(n_qubits, 0);
+		expected.apply_exps(&cliffords);
+		let mut state = StabilizerState::new(n_qubits, 0);
+		state.apply_exps(&decomposition);
+		assert!(state.is_same_state(&expected));
+
+		state.apply_clifford(&PauliExp {
+			string: PauliString::x(n_qubits / 2),
+			angle: CliffordPauliAngle::PiOver4,
+		});
+		assert!(!state.is_same_state(&expected));
+	}
+}
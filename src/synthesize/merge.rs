@@ -0,0 +1,174 @@
+use crate::pauli::{AngleExpression, CliffordPauliAngle, PauliAngle, PauliExp};
+
+/// # Merge exponentials
+///
+/// Sums the angles of exponentials with the same Pauli string, when they can
+/// be moved next to each other by only passing exponentials that they commute
+/// with. The sums are symbolic for parameters and become Clifford when the
+/// result is a multiple of $\frac{\pi}{4}$.
+///
+/// Exponentials with a zero angle and ones on the identity string (global
+/// phases) are dropped. The order of the remaining exponentials is kept.
+pub fn merge_exponentials(exponentials: Vec<PauliExp<PauliAngle>>) -> Vec<PauliExp<PauliAngle>> {
+	merge_exponentials_with_sources(exponentials).0
+}
+
+/// Like [merge_exponentials], but also gives for every merged exponential the
+/// indices of the inputs that were summed into it, in increasing order.
+/// Inputs that were dropped are in none of them.
+pub fn merge_exponentials_with_sources(
+	exponentials: Vec<PauliExp<PauliAngle>>,
+) -> (Vec<PauliExp<PauliAngle>>, Vec<Vec<usize>>) {
+	// Slots are emptied when angles cancel out
+	let mut merged: Vec<Option<(PauliExp<AngleExpression>, Vec<usize>)>> = Vec::new();
+
+	'outer: for (index, exp) in exponentials.into_iter().enumerate() {
+		if exp.is_empty() {
+			continue;
+		}
+
+		let exp = PauliExp {
+			angle: exp.angle.to_expression(),
+			string: exp.string,
+		};
+
+		for slot in merged.iter_mut().rev() {
+			let Some((other, sources)) = slot else {
+				continue;
+			};
+
+			if other.string == exp.string {
+				other.angle.add_expression(&exp.angle);
+				sources.push(index);
+				if fold(&other.angle).is_none() {
+					*slot = None;
+				}
+				continue 'outer;
+			}
+
+			if other.string.anticommutes_with(&exp.string) {
+				break;
+			}
+		}
+
+		merged.push(Some((exp, vec![index])));
+	}
+
+	merged
+		.into_iter()
+		.flatten()
+		.filter_map(|(exp, sources)| {
+			fold(&exp.angle).map(|angle| {
+				(
+					PauliExp {
+						string: exp.string,
+						angle,
+					},
+					sources,
+				)
+			})
+		})
+		.unzip()
+}
+
+/// The simplest angle for the expression, or `None` if the exponential is
+/// the identity (up to a global phase).
+fn fold(expression: &AngleExpression) -> Option<PauliAngle> {
+	if !expression.terms().is_empty() {
+		return Some(expression.clone().simplify());
+	}
+
+	let v = expression.constant_part();
+	match CliffordPauliAngle::from_multiple_of_pi(v) {
+		Some(CliffordPauliAngle::Zero) => None,
+		Some(angle) => Some(PauliAngle::Clifford(angle)),
+		None => Some(PauliAngle::MultipleOfPi(v)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{pauli::PauliString, pauli_string};
+
+	fn exp(string: PauliString, angle: PauliAngle) -> PauliExp<PauliAngle> {
+		PauliExp { string, angle }
+	}
+
+	fn parameter(neg: bool, name: &str) -> PauliAngle {
+		PauliAngle::Parameter {
+			neg,
+			name: String::from(name),
+		}
+	}
+
+	#[test]
+	fn merges_past_commuting() {
+		let merged = merge_exponentials(vec![
+			exp(pauli_string!("XX"), PauliAngle::MultipleOfPi(0.1)),
+			exp(pauli_string!("ZZ"), PauliAngle::MultipleOfPi(0.2)),
+			exp(pauli_string!("XX"), parameter(false, "a")),
+			// Anticommutes with ZZ and XX, so the next XX can not be merged
+			exp(pauli_string!("ZI"), PauliAngle::MultipleOfPi(0.3)),
+			exp(pauli_string!("XX"), PauliAngle::MultipleOfPi(0.4)),
+		]);
+
+		assert_eq!(merged.len(), 4);
+		let mut expected = AngleExpression::parameter(1.0, "a");
+		expected.add_constant(0.1);
+		assert_eq!(merged[0].string, pauli_string!("XX"));
+		assert_eq!(merged[0].angle, PauliAngle::Expression(expected));
+		assert_eq!(merged[1].string, pauli_string!("ZZ"));
+		assert_eq!(merged[3].angle, PauliAngle::MultipleOfPi(0.4));
+	}
+
+	#[test]
+	fn sources() {
+		let (merged, sources) = merge_exponentials_with_sources(vec![
+			exp(pauli_string!("XX"), PauliAngle::MultipleOfPi(0.1)),
+			exp(pauli_string!("ZZ"), PauliAngle::MultipleOfPi(0.2)),
+			exp(pauli_string!("XX"), PauliAngle::MultipleOfPi(0.3)),
+			exp(pauli_string!("ZZ"), PauliAngle::MultipleOfPi(-0.2)),
+			exp(pauli_string!("ZI"), PauliAngle::MultipleOfPi(0.3)),
+			exp(pauli_string!("XX"), PauliAngle::MultipleOfPi(0.4)),
+			exp(pauli_string!("YY"), PauliAngle::MultipleOfPi(0.0)),
+		]);
+
+		assert_eq!(merged.len(), 3);
+		assert_eq!(sources, vec![vec![0, 2], vec![4], vec![5]]);
+	}
+
+	#[test]
+	fn folds_and_drops() {
+		let merged = merge_exponentials(vec![
+			exp(pauli_string!("XY"), PauliAngle::MultipleOfPi(0.1)),
+			exp(pauli_string!("ZZ"), parameter(false, "a")),
+			exp(pauli_string!("XY"), PauliAngle::MultipleOfPi(0.15)),
+			exp(pauli_string!("ZZ"), parameter(true, "a")),
+			exp(pauli_string!("ZI"), PauliAngle::MultipleOfPi(0.6)),
+			exp(pauli_string!("ZI"), PauliAngle::MultipleOfPi(0.4)),
+			exp(PauliString::id(), PauliAngle::MultipleOfPi(0.3)),
+			exp(pauli_string!("YY"), PauliAngle::MultipleOfPi(0.0)),
+			exp(
+				pauli_string!("XZ"),
+				PauliAngle::Clifford(CliffordPauliAngle::PiOver2),
+			),
+			exp(
+				pauli_string!("XZ"),
+				PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			),
+		]);
+
+		assert_eq!(merged.len(), 2);
+		assert_eq!(merged[0].string, pauli_string!("XY"));
+		assert_eq!(
+			merged[0].angle,
+			PauliAngle::Clifford(CliffordPauliAngle::PiOver4)
+		);
+		assert_eq!(merged[1].string, pauli_string!("XZ"));
+		assert_eq!(
+			merged[1].angle,
+			PauliAngle::Clifford(CliffordPauliAngle::NegPiOver4)
+		);
+	}
+}
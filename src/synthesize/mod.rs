@@ -0,0 +1,767 @@
+mod merge;
+mod ordering;
+
+pub use merge::{merge_exponentials, merge_exponentials_with_sources};
+pub use ordering::{Greedy, InputOrder, Lookahead, Randomized, Selection, SynthesisOrdering};
+
+use crate::{
+	connectivity::{Connectivity, RoutingInstruction, RoutingInstructionTarget},
+	misc::NonZeroEvenUsize,
+	pauli::{CliffordPauliAngle, Negate, PauliAngle, PauliExp, PauliLetter, PauliString},
+};
+
+fn get_remove_indexes<F: Fn(&PauliExp<A>) -> bool, A: Negate>(
+	exponentials: &[PauliExp<A>],
+	f: F,
+) -> Vec<usize> {
+	let mut indexes: Vec<usize> = Vec::new();
+
+	let mut i: usize = 0;
+	for exp in exponentials.iter() {
+		if f(exp) {
+			indexes.push(i);
+		} else {
+			i += 1;
+		}
+	}
+
+	indexes
+}
+
+/// Statistics of one exponential that needed Clifford operations to become a
+/// single qubit one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SynthesisStep {
+	/// Index into [SynthesisResult::exponentials], see
+	/// [SynthesisResult::sources] for the inputs it came from.
+	pub index: usize,
+	/// The steps estimated when the exponential was selected.
+	pub predicted_steps: usize,
+	/// The amount of Clifford operations actually used.
+	pub used_steps: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SynthesisResult {
+	/// The exponentials that were synthesized, that is the input after
+	/// [merge_exponentials].
+	pub exponentials: Vec<PauliExp<PauliAngle>>,
+	/// For each of `exponentials`, the indices of the inputs that were merged
+	/// into it, in increasing order.
+	pub sources: Vec<Vec<usize>>,
+	pub circuit: Vec<PauliExp<PauliAngle>>,
+	/// The Clifford remainder, applied after `circuit`.
+	pub clifford: Vec<PauliExp<CliffordPauliAngle>>,
+	/// Indices into the input in the order they were synthesized. The
+	/// Clifford inputs come last. Inputs merged into one exponential appear
+	/// next to each other and inputs dropped while merging are left out.
+	pub order: Vec<usize>,
+	pub steps: Vec<SynthesisStep>,
+	// Indices into `exponentials` in the order they were synthesized
+	merged_order: Vec<usize>,
+}
+
+impl SynthesisResult {
+	/// The synthesized exponentials in the order they were synthesized.
+	pub fn ordered(&self) -> Vec<PauliExp<PauliAngle>> {
+		self.merged_order
+			.iter()
+			.map(|i| self.exponentials[*i].clone())
+			.collect()
+	}
+}
+
+pub fn synthesize(
+	exponentials: Vec<PauliExp<PauliAngle>>,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Option<&Connectivity>,
+) -> SynthesisResult {
+	synthesize_with_ordering(exponentials, gate_size, connectivity, &mut Greedy)
+}
+
+/// Like [synthesize], but the order in which the exponentials are synthesized
+/// is decided by `ordering`.
+pub fn synthesize_with_ordering<O: SynthesisOrdering + ?Sized>(
+	exponentials: Vec<PauliExp<PauliAngle>>,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Option<&Connectivity>,
+	ordering: &mut O,
+) -> SynthesisResult {
+	synthesize_inner(exponentials, gate_size, connectivity, ordering, false)
+}
+
+/// # Synthesize preserving order
+///
+/// [synthesize] reorders the exponentials freely, which is only correct when
+/// they all commute. This version only reorders exponentials that commute, by
+/// selecting from the front layer of the anticommutation DAG, so the output
+/// is equivalent to the input for any input.
+///
+/// Clifford inputs are pushed through the exponentials after them, so
+/// [SynthesisResult::ordered] is not equivalent to the input in this mode,
+/// but the circuit followed by the Clifford part is.
+pub fn synthesize_preserving_order<O: SynthesisOrdering + ?Sized>(
+	exponentials: Vec<PauliExp<PauliAngle>>,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Option<&Connectivity>,
+	ordering: &mut O,
+) -> SynthesisResult {
+	synthesize_inner(exponentials, gate_size, connectivity, ordering, true)
+}
+
+fn synthesize_inner<O: SynthesisOrdering + ?Sized>(
+	exponentials: Vec<PauliExp<PauliAngle>>,
+	gate_size: NonZeroEvenUsize,
+	connectivity: Option<&Connectivity>,
+	ordering: &mut O,
+	preserve_order: bool,
+) -> SynthesisResult {
+	let (mut exponentials, sources) = merge_exponentials_with_sources(exponentials);
+	let input = exponentials.clone();
+
+	// The index in `input` of each exponential still in `exponentials`
+	let mut indices: Vec<usize> = (0..exponentials.len()).collect();
+	let mut order: Vec<usize> = Vec::new();
+	let mut clifford_order: Vec<usize> = Vec::new();
+	let mut steps: Vec<SynthesisStep> = Vec::new();
+
+	let mut circuit: Vec<PauliExp<PauliAngle>> = Vec::new();
+	let mut clifford_part: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
+
+	// Moving a Clifford $C$ after $X$ turns $X$ into $C^\dagger XC$
+	if preserve_order {
+		let mut moved: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
+		for exp in exponentials.iter_mut() {
+			for clifford in moved.iter() {
+				exp.move_clifford_past(clifford);
+			}
+
+			if let PauliAngle::Clifford(angle) = exp.angle {
+				moved.push(PauliExp {
+					string: exp.string.clone(),
+					angle,
+				});
+			}
+		}
+	}
+
+	// move clifford gates to clifford part
+	let remove_indexes = get_remove_indexes(&exponentials, |p| p.angle.is_clifford());
+	for i in remove_indexes.into_iter() {
+		let clifford = exponentials.remove(i);
+		clifford_order.push(indices.remove(i));
+		if let PauliAngle::Clifford(angle) = clifford.angle {
+			clifford_part.push(PauliExp {
+				string: clifford.string,
+				angle,
+			});
+		} else {
+			unreachable!()
+		}
+	}
+
+	// move single (an no) qubit gates to circuit
+	if !preserve_order && ordering.single_qubit_first() {
+		let remove_indexes = get_remove_indexes(&exponentials, |p| p.len() <= 1);
+		for i in remove_indexes.into_iter() {
+			assert!(exponentials.get(i).unwrap().len() == 1);
+			circuit.push(exponentials.remove(i));
+			order.push(indices.remove(i));
+		}
+	}
+
+	// The amount of earlier exponentials each one anticommutes with
+	let mut predecessors: Vec<usize> = vec![0; exponentials.len()];
+	if preserve_order {
+		for (i, exp) in exponentials.iter().enumerate() {
+			predecessors[i] = exponentials[..i]
+				.iter()
+				.filter(|other| other.string.anticommutes_with(&exp.string))
+				.count();
+		}
+	}
+
+	// main loop
+	while !exponentials.is_empty() {
+		let candidates: Vec<usize> = (0..exponentials.len())
+			.filter(|i| predecessors[*i] == 0)
+			.collect();
+		let Selection {
+			index,
+			instructions,
+		} = ordering.select(&exponentials, &candidates, gate_size, connectivity);
+		assert!(candidates.contains(&index));
+
+		predecessors.remove(index);
+		if preserve_order {
+			let string = &exponentials[index].string;
+			for (i, exp) in exponentials.iter().enumerate().filter(|(i, _)| *i != index) {
+				if exp.string.anticommutes_with(string) {
+					let j = if i < index { i } else { i - 1 };
+					predecessors[j] -= 1;
+				}
+			}
+		}
+
+		let mut exp = exponentials.remove(index);
+		let index = indices.remove(index);
+		let predicted_steps =
+			ordering::estimate_steps(&exp.string, gate_size, &instructions, connectivity);
+		let push_strs = push_strings(&exp.string, gate_size, instructions);
+		let used_steps = push_strs.len();
+		for push_str in push_strs {
+			// push string trough/into things
+			exp.push_pi_over_4(false, &push_str);
+			for exp in exponentials.iter_mut() {
+				exp.push_pi_over_4(false, &push_str);
+			}
+
+			circuit.push(PauliExp {
+				string: push_str.clone(),
+				angle: PauliAngle::Clifford(CliffordPauliAngle::PiOver4),
+			});
+			clifford_part.push(PauliExp {
+				string: push_str,
+				angle: CliffordPauliAngle::NegPiOver4,
+			});
+		}
+
+		assert_eq!(exp.len(), 1);
+		// add exp to circuit
+		circuit.push(exp);
+		order.push(index);
+		steps.push(SynthesisStep {
+			index,
+			predicted_steps,
+			used_steps,
+		});
+	}
+
+	assert!(indices.is_empty());
+
+	let clifford_part: Vec<PauliExp<CliffordPauliAngle>> =
+		clifford_part.into_iter().rev().collect();
+
+	clifford_order.reverse();
+	order.append(&mut clifford_order);
+
+	SynthesisResult {
+		order: order
+			.iter()
+			.flat_map(|i| sources[*i].iter().copied())
+			.collect(),
+		exponentials: input,
+		sources,
+		circuit,
+		clifford: clifford_part,
+		steps,
+		merged_order: order,
+	}
+}
+
+/// The $\frac{\pi}{4}$ strings that turn `string` into a single qubit string,
+/// in the order they have to be pushed. Without routing instructions every
+/// `gate_size` qubits are assumed to be able to interact.
+fn push_strings(
+	string: &PauliString,
+	gate_size: NonZeroEvenUsize,
+	instructions: Vec<RoutingInstruction>,
+) -> Vec<PauliString> {
+	let mut string = string.clone();
+	let mut push_strs = Vec::new();
+	if instructions.is_empty() {
+		while string.len() > 1 {
+			let push_str = full_connectivity_push_string(&string, gate_size);
+			string.pi_over_4_sandwitch(false, &push_str);
+			push_strs.push(push_str);
+		}
+	} else {
+		for instruction in instructions {
+			for push_str in handle_instruction(string.clone(), gate_size, instruction) {
+				string.pi_over_4_sandwitch(false, &push_str);
+				push_strs.push(push_str);
+			}
+		}
+	}
+
+	push_strs
+}
+
+/// The next string to push when every `gate_size` qubits can interact.
+fn full_connectivity_push_string(string: &PauliString, gate_size: NonZeroEvenUsize) -> PauliString {
+	let n = gate_size.as_value();
+	let push_str = if string.len() == n {
+		// One commutes, the rest cancel each other out
+		let mut push_str = string.clone();
+		let (i, l) = push_str.letters().next().unwrap();
+		push_str.set(i, l.next());
+
+		push_str
+	} else if string.len() % 2 == 1 && string.len() < (2 * n) {
+		// Change the string into a n long one
+		if string.len() < n {
+			// We need to increase the amount of letters
+			// Because the exp has an uneven len, we can commute on all
+			let mut push_str = string.clone();
+			for (i, m) in push_str.letters().collect::<Vec<(usize, PauliLetter)>>() {
+				push_str.set(i, m.next());
+			}
+
+			// Then we just add letters to make it n long
+			for i in 0..n {
+				if push_str.get(i) == PauliLetter::I {
+					push_str.set(i, PauliLetter::X);
+					if push_str.len() == n {
+						break;
+					}
+				}
+			}
+
+			push_str
+		} else {
+			// We need to decrease the amount of letters
+			// Select n many letters
+			let mut letters: Vec<(usize, PauliLetter)> = string.letters().take(n).collect();
+
+			// We need to to end up with n letters. This means that we
+			// need to remove string.len() - n letters. This means that
+			// from the n many letters, we only want to keep
+			// n - (string.len() -n ) = 2n - string.len() many.
+			// As these are the ones we keep, we make them anticommute
+			// (the amount is always uneven because string.len() is)
+			for (_, l) in letters.iter_mut().take(2 * n - string.len()) {
+				*l = l.next();
+			}
+
+			// Then we just collect the letters to a string
+			let mut push_str = PauliString::id();
+			for (i, l) in letters {
+				push_str.set(i, l);
+			}
+			push_str
+		}
+	} else {
+		// Now either string.len() is at least len 2n or even.
+		// To reach a uneven len under 2n, we remove as much as possible
+		// and if needed add some letters, because operations have to be
+		// len n.
+
+		// select at least n many letters
+		let mut letters: Vec<(usize, PauliLetter)> = string.letters().take(n).collect();
+
+		// edit first one in order to anticommute (and delete on others)
+		letters.first_mut().unwrap().1 = letters.first_mut().unwrap().1.next();
+
+		// collect as string
+		let mut push_str = PauliString::id();
+		for (i, l) in letters {
+			push_str.set(i, l);
+		}
+		// add letters if needed to make operation n long
+
+		if push_str.len() < n {
+			for i in 0..n {
+				if push_str.get(i) == PauliLetter::I {
+					push_str.set(i, PauliLetter::X);
+					if push_str.len() == n {
+						break;
+					}
+				}
+			}
+		}
+
+		push_str
+	};
+
+	assert_eq!(push_str.len(), n);
+	push_str
+}
+
+pub(crate) fn handle_instruction(
+	mut string: PauliString,
+	gate_size: NonZeroEvenUsize,
+	instruction: RoutingInstruction,
+) -> Vec<PauliString> {
+	let n = gate_size.as_value();
+	let mut push_strs: Vec<PauliString> = Vec::new();
+
+	// Target qubit
+	let target = match instruction.target {
+		RoutingInstructionTarget::Single(target) => target,
+		RoutingInstructionTarget::Multiple(targets) => {
+			let mut target = *targets.first().unwrap();
+			for option in targets {
+				if string.get(option) != PauliLetter::I {
+					target = option;
+					break;
+				}
+			}
+
+			target
+		}
+		RoutingInstructionTarget::Any => *instruction.qubits.first().unwrap(),
+	};
+
+	let mut removable = Vec::new();
+	for qubit in instruction.qubits {
+		if string.get(*qubit) != PauliLetter::I && *qubit != target {
+			removable.push(*qubit);
+		}
+	}
+
+	if removable.is_empty() {
+		return Vec::new();
+	}
+
+	// make shorter than 2n
+	while removable.len() > 2 * n - 2 {
+		let mut push_str = PauliString::id();
+
+		// anticommute on one
+		let anticommute_on = *removable.first().unwrap();
+		push_str.set(anticommute_on, string.get(anticommute_on).next());
+
+		// remove on rest
+		for _ in 0..(n - 1) {
+			let i = removable.pop().unwrap();
+			push_str.set(i, string.get(i));
+		}
+
+		assert_eq!(push_str.len(), n);
+
+		string.pi_over_4_sandwitch(false, &push_str);
+		push_strs.push(push_str);
+	}
+
+	// make sure we have target
+	if string.get(target) == PauliLetter::I {
+		let mut push_str = PauliString::id();
+
+		// Adding the target qubit
+		push_str.set(target, PauliLetter::X);
+
+		// We add one, and then want to have n left
+		let mut n_remove = (removable.len() + 2).saturating_sub(n);
+		// Because we need to add one qubit and anticommute on one, we can only remove an
+		// even amount.
+		if n_remove % 2 == 1 {
+			n_remove += 1;
+		}
+		// The amount of removals is limited by the gate size
+		let n_remove = n_remove.min(n - 2);
+
+		for _ in 0..n_remove {
+			let i = removable.pop().unwrap();
+			push_str.set(i, string.get(i));
+		}
+
+		// We anticommute on as many as possible (keeping the amount uneven)
+		for qubit in removable.iter().rev().take(if removable.len() % 2 == 0 {
+			removable.len() - 1
+		} else {
+			removable.len()
+		}) {
+			if push_str.len() == n {
+				break;
+			}
+			push_str.set(*qubit, string.get(*qubit).next());
+		}
+
+		// If we do not have the option to anticommute on more, we need to add letters
+		for qubit in instruction.qubits.iter().take(n + 1) {
+			if push_str.len() == n {
+				break;
+			}
+			if push_str.get(*qubit) != PauliLetter::I {
+				continue;
+			}
+			if removable.contains(qubit) {
+				continue;
+			}
+
+			removable.push(*qubit);
+			push_str.set(*qubit, PauliLetter::X);
+		}
+
+		// We may need to remove one more. This is because we need to anticommute on uneven amount
+		if push_str.len() < n {
+			assert_eq!(push_str.len(), n - 1);
+			let remove = removable.remove(0);
+			push_str.set(remove, string.get(remove));
+		}
+
+		assert_eq!(push_str.len(), n);
+		string.pi_over_4_sandwitch(false, &push_str);
+		push_strs.push(push_str);
+	}
+
+	// if even and not 4 make so that we are at uneven
+	if removable.len() != n - 1 && (removable.len() + 1).is_multiple_of(2) {
+		let mut push_str = PauliString::id();
+
+		// delete as many as can
+		let n_remove = removable.len().min(n - 1);
+		for qubit in removable.drain(0..n_remove) {
+			push_str.set(qubit, string.get(qubit));
+		}
+
+		// anticommute on target
+		push_str.set(target, string.get(target).next());
+
+		// Fill in if needed
+		for qubit in instruction.qubits.iter().take(n) {
+			if push_str.len() == n {
+				break;
+			}
+			if push_str.get(*qubit) != PauliLetter::I || *qubit == target {
+				continue;
+			}
+
+			removable.push(*qubit);
+			push_str.set(*qubit, PauliLetter::X);
+		}
+
+		assert_eq!(push_str.len(), n);
+		string.pi_over_4_sandwitch(false, &push_str);
+		push_strs.push(push_str);
+	}
+
+	// if uneven make so that len n
+	if !removable.is_empty() && removable.len() % 2 == 0 {
+		let len = removable.len() + 1;
+		if len < n {
+			// We need to add qubits
+			let mut push_str = PauliString::id();
+
+			// Commute on current letters
+			for i in removable.iter() {
+				push_str.set(*i, string.get(*i).next())
+			}
+
+			// Commute on target
+			push_str.set(target, string.get(target).next());
+
+			// these are added
+			for qubit in instruction.qubits.iter() {
+				if push_str.get(*qubit) == PauliLetter::I {
+					push_str.set(*qubit, PauliLetter::X);
+					removable.push(*qubit);
+					if push_str.len() == n {
+						break;
+					}
+				}
+			}
+
+			assert_eq!(push_str.len(), n);
+			string.pi_over_4_sandwitch(false, &push_str);
+			push_strs.push(push_str);
+		} else {
+			// We need to remove qubits
+			let mut letters: Vec<(usize, PauliLetter)> =
+				removable.drain(0..n).map(|i| (i, string.get(i))).collect();
+
+			// These are the ones we keep (we make them anticommute)
+			for (i, l) in letters.iter_mut().take(2 * n - len) {
+				// We need to add these back to the removable ones
+				removable.push(*i);
+				*l = l.next();
+			}
+
+			// The rest of the qubits are removed
+			let mut push_str = PauliString::id();
+			for (i, l) in letters {
+				push_str.set(i, l);
+			}
+
+			assert_eq!(push_str.len(), n);
+			string.pi_over_4_sandwitch(false, &push_str);
+			push_strs.push(push_str);
+		}
+	}
+
+	assert!(removable.is_empty() || removable.len() == n - 1);
+
+	// convert to single qubit if len == n
+	if removable.len() == n - 1 {
+		let mut push_str = PauliString::id();
+		// commute on target
+		push_str.set(target, string.get(target).next());
+
+		// remove the rest
+		for qubit in removable.iter() {
+			push_str.set(*qubit, string.get(*qubit));
+		}
+
+		assert_eq!(push_str.len(), n);
+		string.pi_over_4_sandwitch(false, &push_str);
+		push_strs.push(push_str);
+	}
+
+	for string in push_strs.iter() {
+		assert_eq!(string.len(), n)
+	}
+
+	push_strs
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{experiment::random_exp, pauli_string, simulate::ApplyExp};
+	use rand::prelude::*;
+	use rand_chacha::ChaCha8Rng;
+	use simulator::{Complex, Statevector};
+
+	#[test]
+	fn synthesize_result_has_suitable_operators() {
+		for _ in 0..10 {
+			let mut rng = rand::rng();
+			let input: Vec<PauliExp<PauliAngle>> =
+				(0..30).map(move |_| random_exp(30, &mut rng)).collect();
+
+			let result = synthesize(input, NonZeroEvenUsize::new(4).unwrap(), None);
+
+			let mut order = result.order.clone();
+			order.sort();
+			let mut sources = result.sources.concat();
+			sources.sort();
+			assert_eq!(order, sources);
+
+			for step in result.steps.iter() {
+				assert!(step.used_steps > 0);
+			}
+
+			for exp in result.circuit {
+				assert!(exp.len() == 1 || exp.len() == 4);
+			}
+
+			for exp in result.clifford {
+				assert!(exp.len() == 1 || exp.len() == 4);
+			}
+		}
+	}
+
+	#[test]
+	fn order_indexes_input() {
+		let angles = [0.1, 0.2, 0.05, 0.3, 0.15];
+		let strings = [
+			pauli_string!("XX"),
+			pauli_string!("ZZ"),
+			pauli_string!("XX"),
+			pauli_string!("ZI"),
+			pauli_string!("XX"),
+		];
+		let input: Vec<PauliExp<PauliAngle>> = strings
+			.into_iter()
+			.zip(angles)
+			.map(|(string, angle)| PauliExp {
+				string,
+				angle: PauliAngle::MultipleOfPi(angle),
+			})
+			.collect();
+
+		let result = synthesize(input.clone(), NonZeroEvenUsize::new(2).unwrap(), None);
+
+		assert_eq!(result.sources, vec![vec![0, 2], vec![1], vec![3], vec![4]]);
+		let mut order = result.order.clone();
+		order.sort();
+		assert_eq!(order, (0..input.len()).collect::<Vec<_>>());
+		for (exp, sources) in result.exponentials.iter().zip(result.sources.iter()) {
+			for i in sources {
+				assert_eq!(input[*i].string, exp.string);
+			}
+		}
+
+		// Merged inputs stay together in the order
+		let position = |i: usize| result.order.iter().position(|j| *j == i).unwrap();
+		assert_eq!(position(2), position(0) + 1);
+	}
+
+	#[test]
+	fn preserving_order_is_equivalent() {
+		let n_qubits = 6;
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let connectivity = Connectivity::create_line(gate_size, n_qubits);
+		let mut rng = ChaCha8Rng::seed_from_u64(5);
+
+		for connectivity in [None, Some(&connectivity)] {
+			let input: Vec<PauliExp<PauliAngle>> = (0..20)
+				.map(|_| {
+					let mut exp = random_exp(n_qubits, &mut rng);
+					if rng.random_bool(0.2) {
+						exp.angle = PauliAngle::Clifford(CliffordPauliAngle::PiOver4);
+					}
+					exp
+				})
+				.collect();
+
+			let result =
+				synthesize_preserving_order(input.clone(), gate_size, connectivity, &mut Greedy);
+
+			// The connectivity may have more qubits than the input uses
+			let n_simulated = result
+				.circuit
+				.iter()
+				.map(|exp| exp.string.size())
+				.chain(result.clifford.iter().map(|exp| exp.string.size()))
+				.fold(n_qubits, usize::max);
+			let initial = Statevector {
+				values: (0..(1 << n_simulated))
+					.map(|_| Complex {
+						re: rng.random::<f64>(),
+						im: rng.random::<f64>(),
+					})
+					.collect(),
+				n_qubits: n_simulated,
+			};
+			let mut expected = initial.clone();
+			expected.apply_exps(&input);
+			let mut actual = initial;
+			for exp in result.circuit.iter() {
+				assert!(exp.len() == 1 || exp.len() == 4);
+			}
+			actual.apply_exps(&result.circuit);
+			actual.apply_exps(&result.clifford);
+
+			// Equal up to a global phase
+			let (mut re, mut im, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0, 0.0);
+			for (a, b) in expected.values().iter().zip(actual.values().iter()) {
+				re += a.re * b.re + a.im * b.im;
+				im += a.re * b.im - a.im * b.re;
+				norm_a += a.abs_squared();
+				norm_b += b.abs_squared();
+			}
+			let overlap = (re * re + im * im).sqrt() / (norm_a * norm_b).sqrt();
+			assert!((overlap - 1.0).abs() < 1e-9, "overlap {overlap}");
+		}
+	}
+
+	#[test]
+	fn lookahead_result_has_suitable_operators() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let connectivity = Connectivity::create_line(gate_size, 12);
+		for connectivity in [None, Some(&connectivity)] {
+			let mut rng = ChaCha8Rng::seed_from_u64(6);
+			let input: Vec<PauliExp<PauliAngle>> =
+				(0..20).map(move |_| random_exp(12, &mut rng)).collect();
+
+			let mut ordering = Lookahead { depth: 2, width: 3 };
+			let result = synthesize_with_ordering(input, gate_size, connectivity, &mut ordering);
+
+			assert_eq!(result.ordered().len(), result.exponentials.len());
+			for exp in result.circuit.iter().chain(
+				result
+					.clifford
+					.iter()
+					.map(|exp| PauliExp::<PauliAngle>::from(exp.clone()))
+					.collect::<Vec<_>>()
+					.iter(),
+			) {
+				assert!(exp.len() == 1 || exp.len() == 4);
+				if let Some(connectivity) = connectivity {
+					assert!(connectivity.supports_operation_on(&exp.string.targets()));
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,284 @@
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::push_strings;
+use crate::{
+	connectivity::{Connectivity, RoutingInstruction},
+	misc::NonZeroEvenUsize,
+	pauli::{PauliAngle, PauliExp, PauliString},
+};
+
+/// The exponential that is synthesized next.
+#[derive(Debug)]
+pub struct Selection<'a> {
+	/// Index into the remaining exponentials.
+	pub index: usize,
+	/// How the exponential is routed. Always empty without a connectivity.
+	pub instructions: Vec<RoutingInstruction<'a>>,
+}
+
+impl<'a> Selection<'a> {
+	/// Selects `index` with the default routing path for `string`. Single
+	/// qubit strings need no routing.
+	pub fn routed(
+		index: usize,
+		string: &PauliString,
+		connectivity: Option<&'a Connectivity>,
+	) -> Self {
+		Self {
+			index,
+			instructions: connectivity
+				.filter(|_| string.len() > 1)
+				.map(|connectivity| connectivity.get_routing_path(&string.targets()))
+				.unwrap_or_default(),
+		}
+	}
+}
+
+/// Decides in which order the exponentials are synthesized. The order decides
+/// how the remaining exponentials grow or shrink as the $\frac{\pi}{4}$
+/// strings are pushed through them.
+pub trait SynthesisOrdering {
+	/// Selects one of the remaining exponentials. The selected index has to be
+	/// one of `candidates`, which is sorted and never empty. The candidates
+	/// are all of the exponentials unless the order is preserved, then they
+	/// are the front layer and may contain single qubit exponentials.
+	fn select<'a>(
+		&mut self,
+		exponentials: &[PauliExp<PauliAngle>],
+		candidates: &[usize],
+		gate_size: NonZeroEvenUsize,
+		connectivity: Option<&'a Connectivity>,
+	) -> Selection<'a>;
+
+	/// Whether the single qubit exponentials are synthesized before any are
+	/// selected. They need no Clifford operations, so this only changes the
+	/// order. Unused when the order is preserved.
+	fn single_qubit_first(&self) -> bool {
+		true
+	}
+}
+
+/// The steps needed to turn `string` into a single qubit string.
+pub(super) fn estimate_steps(
+	string: &PauliString,
+	gate_size: NonZeroEvenUsize,
+	instructions: &[RoutingInstruction],
+	connectivity: Option<&Connectivity>,
+) -> usize {
+	match connectivity {
+		Some(_) => string.steps_to_solve_instructions(gate_size, instructions),
+		None => string.steps_to_len_one(gate_size),
+	}
+}
+
+/// Selects the exponential that needs the fewest steps (the first one on
+/// ties).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Greedy;
+
+impl SynthesisOrdering for Greedy {
+	fn select<'a>(
+		&mut self,
+		exponentials: &[PauliExp<PauliAngle>],
+		candidates: &[usize],
+		gate_size: NonZeroEvenUsize,
+		connectivity: Option<&'a Connectivity>,
+	) -> Selection<'a> {
+		// (steps, selection)
+		let mut shortest: Option<(usize, Selection<'a>)> = None;
+		for i in candidates.iter().copied() {
+			let exp = &exponentials[i];
+			let selection = Selection::routed(i, &exp.string, connectivity);
+			let steps = estimate_steps(
+				&exp.string,
+				gate_size,
+				&selection.instructions,
+				connectivity,
+			);
+			if shortest
+				.as_ref()
+				.is_none_or(|(shortest, _)| steps < *shortest)
+			{
+				shortest = Some((steps, selection));
+			}
+		}
+
+		shortest.unwrap().1
+	}
+}
+
+/// Synthesizes the exponentials in the order of the input, a baseline for the
+/// other orderings. Single qubit exponentials keep their place too, only
+/// equal strings are still merged and Clifford ones go to the Clifford part.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputOrder;
+
+impl SynthesisOrdering for InputOrder {
+	fn select<'a>(
+		&mut self,
+		exponentials: &[PauliExp<PauliAngle>],
+		candidates: &[usize],
+		_gate_size: NonZeroEvenUsize,
+		connectivity: Option<&'a Connectivity>,
+	) -> Selection<'a> {
+		let index = candidates[0];
+		Selection::routed(index, &exponentials[index].string, connectivity)
+	}
+
+	fn single_qubit_first(&self) -> bool {
+		false
+	}
+}
+
+/// Selects uniformly at random, reproducible with the seed.
+#[derive(Debug, Clone)]
+pub struct Randomized {
+	rng: ChaCha8Rng,
+}
+
+impl Randomized {
+	pub fn new(seed: u64) -> Self {
+		Self {
+			rng: ChaCha8Rng::seed_from_u64(seed),
+		}
+	}
+}
+
+impl SynthesisOrdering for Randomized {
+	fn select<'a>(
+		&mut self,
+		exponentials: &[PauliExp<PauliAngle>],
+		candidates: &[usize],
+		_gate_size: NonZeroEvenUsize,
+		connectivity: Option<&'a Connectivity>,
+	) -> Selection<'a> {
+		let index = candidates[self.rng.random_range(0..candidates.len())];
+		Selection::routed(index, &exponentials[index].string, connectivity)
+	}
+}
+
+/// A beam search that looks `depth` selections ahead. At every level only the
+/// `width` exponentials with the fewest steps are tried, and the selection
+/// that leaves the smallest total of [PauliString::steps_to_len_one] behind
+/// after its pushes wins. Only the first level is limited to the candidates.
+#[derive(Debug, Clone, Copy)]
+pub struct Lookahead {
+	pub depth: usize,
+	pub width: usize,
+}
+
+impl SynthesisOrdering for Lookahead {
+	fn select<'a>(
+		&mut self,
+		exponentials: &[PauliExp<PauliAngle>],
+		candidates: &[usize],
+		gate_size: NonZeroEvenUsize,
+		connectivity: Option<&'a Connectivity>,
+	) -> Selection<'a> {
+		let strings: Vec<PauliString> = exponentials.iter().map(|exp| exp.string.clone()).collect();
+		let (index, _) = self.search(
+			&strings,
+			candidates,
+			gate_size,
+			connectivity,
+			self.depth.max(1),
+		);
+		Selection::routed(index, &strings[index], connectivity)
+	}
+}
+
+impl Lookahead {
+	/// Returns (index, score), where the score is the amount of pushes used
+	/// plus the total [PauliString::steps_to_len_one] of what remains after
+	/// the last level.
+	fn search(
+		&self,
+		strings: &[PauliString],
+		candidates: &[usize],
+		gate_size: NonZeroEvenUsize,
+		connectivity: Option<&Connectivity>,
+		depth: usize,
+	) -> (usize, usize) {
+		let mut candidates: Vec<(usize, usize, Selection)> = candidates
+			.iter()
+			.map(|&i| {
+				let string = &strings[i];
+				let selection = Selection::routed(i, string, connectivity);
+				let steps =
+					estimate_steps(string, gate_size, &selection.instructions, connectivity);
+				(steps, i, selection)
+			})
+			.collect();
+		candidates.sort_by_key(|(steps, i, _)| (*steps, *i));
+
+		let mut best: Option<(usize, usize)> = None;
+		for (_, index, selection) in candidates.into_iter().take(self.width.max(1)) {
+			let push_strs = push_strings(&strings[index], gate_size, selection.instructions);
+			let mut remaining: Vec<PauliString> = strings
+				.iter()
+				.enumerate()
+				.filter(|(i, _)| *i != index)
+				.map(|(_, string)| string.clone())
+				.collect();
+			for push_str in push_strs.iter() {
+				for string in remaining.iter_mut() {
+					string.pi_over_4_sandwitch(false, push_str);
+				}
+			}
+
+			// Single qubit strings are done without any pushes
+			remaining.retain(|string| string.len() > 1);
+			let rest = if depth <= 1 || remaining.is_empty() {
+				remaining
+					.iter()
+					.map(|string| string.steps_to_len_one(gate_size))
+					.sum()
+			} else {
+				let all: Vec<usize> = (0..remaining.len()).collect();
+				self.search(&remaining, &all, gate_size, connectivity, depth - 1)
+					.1
+			};
+
+			let score = push_strs.len() + rest;
+			if best.is_none_or(|(_, best)| score < best) {
+				best = Some((index, score));
+			}
+		}
+
+		best.unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{experiment::random_exp, synthesize::synthesize_with_ordering};
+
+	fn input() -> Vec<PauliExp<PauliAngle>> {
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		(0..20).map(|_| random_exp(10, &mut rng)).collect()
+	}
+
+	#[test]
+	fn input_order_keeps_order() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let connectivity = Connectivity::create_line(gate_size, 10);
+		for connectivity in [None, Some(&connectivity)] {
+			let input = input();
+			assert!(input.iter().any(|exp| exp.len() == 1));
+			let n = input.len();
+			let result = synthesize_with_ordering(input, gate_size, connectivity, &mut InputOrder);
+			assert_eq!(result.order, (0..n).collect::<Vec<_>>());
+		}
+	}
+
+	#[test]
+	fn randomized_is_reproducible() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let a = synthesize_with_ordering(input(), gate_size, None, &mut Randomized::new(7));
+		let b = synthesize_with_ordering(input(), gate_size, None, &mut Randomized::new(7));
+		assert_eq!(a.order, b.order);
+		assert_eq!(a.steps, b.steps);
+	}
+}
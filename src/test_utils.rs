@@ -0,0 +1,52 @@
+//! Random fixtures shared by the tests.
+
+use rand::{Rng, RngExt};
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{CliffordPauliAngle, PauliExp, PauliLetter, PauliString},
+};
+
+/// A string with a uniformly random letter, identity included, on each of
+/// the `n_qubits` qubits.
+pub(crate) fn random_string<R: Rng>(n_qubits: usize, rng: &mut R) -> PauliString {
+	let mut string = PauliString::default();
+	for i in 0..n_qubits {
+		let letter = match rng.random_range(0..4) {
+			0 => PauliLetter::I,
+			1 => PauliLetter::X,
+			2 => PauliLetter::Y,
+			_ => PauliLetter::Z,
+		};
+		string.set(i, letter);
+	}
+	string
+}
+
+/// `count` Clifford exponentials with a [random_string] and a non-zero angle.
+pub(crate) fn random_cliffords<R: Rng>(
+	n_qubits: usize,
+	count: usize,
+	rng: &mut R,
+) -> Vec<PauliExp<CliffordPauliAngle>> {
+	(0..count)
+		.map(|_| PauliExp {
+			string: random_string(n_qubits, rng),
+			angle: match rng.random_range(0..4) {
+				0 => CliffordPauliAngle::NegPiOver2,
+				1 => CliffordPauliAngle::NegPiOver4,
+				2 => CliffordPauliAngle::PiOver4,
+				_ => CliffordPauliAngle::PiOver2,
+			},
+		})
+		.collect()
+}
+
+/// The tableau of the Cliffords, index 0 first.
+pub(crate) fn tableau(cliffords: &[PauliExp<CliffordPauliAngle>]) -> CliffordTableau {
+	let mut tableau = CliffordTableau::id();
+	for clifford in cliffords.iter() {
+		tableau.merge_clifford(clifford.clone());
+	}
+	tableau
+}
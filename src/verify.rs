@@ -0,0 +1,402 @@
+//! Equivalence checks for synthesized circuits.
+//!
+//! Circuits are first compared in a canonical form. All Clifford exponentials
+//! are pushed to the end and merged into a [CliffordTableau]. The
+//! non-Clifford exponentials that remain are merged with
+//! [merge_exponentials] and sorted into layers of commuting exponentials.
+//! Equal canonical forms prove equivalence (up to a global phase). When the
+//! canonical forms differ and the circuits are small enough, they are
+//! simulated on several random product states with
+//! [simulator::ParallelStatevector]. That is only evidence, not a proof, see
+//! [Proof::Sampled].
+
+use std::{error::Error, fmt};
+
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use simulator::ParallelStatevector;
+
+use crate::{
+	clifford_tableau::CliffordTableau,
+	pauli::{
+		CliffordPauliAngle, ParameterTable, PauliAngle, PauliExp, PauliString, bind,
+		free_parameters,
+	},
+	simulate::ApplyExp,
+	synthesize::merge_exponentials,
+};
+
+/// Circuits on more qubits are not simulated.
+pub const MAX_SIMULATED_QUBITS: usize = 20;
+
+const TOLERANCE: f64 = 1e-8;
+
+/// The amount of random states both circuits are simulated on.
+pub const SAMPLES: usize = 4;
+
+/// How the equivalence was shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proof {
+	/// The canonical forms are equal.
+	Canonical,
+	/// The canonical forms differ, but the states are equal after
+	/// simulating both circuits on [SAMPLES] independent random product
+	/// states, each with new random values for the parameters. Circuits that
+	/// differ are very unlikely to pass this, but it is not a proof.
+	Sampled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+	/// The canonical forms differ and the circuits are too large to simulate.
+	TooLarge { qubits: usize },
+	/// The simulated states differ, `overlap` is the smallest
+	/// $|\langle a|b\rangle|$ of the samples.
+	NotEquivalent { overlap: f64 },
+}
+
+impl fmt::Display for VerificationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			VerificationError::TooLarge { qubits } => write!(
+				f,
+				"canonical forms differ and {qubits} qubits are too many to simulate (max {MAX_SIMULATED_QUBITS})"
+			),
+			VerificationError::NotEquivalent { overlap } => {
+				write!(f, "circuits are not equivalent (overlap {overlap})")
+			}
+		}
+	}
+}
+
+impl Error for VerificationError {}
+
+/// # Verify synthesis
+///
+/// Checks that `circuit` followed by `clifford` is equivalent to `input`, as
+/// returned by [crate::synthesize::synthesize] and friends. When the order is
+/// not preserved the input is [crate::synthesize::SynthesisResult::ordered].
+pub fn verify_synthesis(
+	input: &[PauliExp<PauliAngle>],
+	circuit: &[PauliExp<PauliAngle>],
+	clifford: &[PauliExp<CliffordPauliAngle>],
+) -> Result<Proof, VerificationError> {
+	let output: Vec<PauliExp<PauliAngle>> = circuit
+		.iter()
+		.cloned()
+		.chain(clifford.iter().cloned().map(PauliExp::<PauliAngle>::from))
+		.collect();
+
+	verify_equivalence(input, &output)
+}
+
+/// # Verify equivalence
+///
+/// Checks that the two circuits are equal up to a global phase. Index 0 is
+/// applied first. Parameters are compared symbolically, for the simulation
+/// they are bound to random values. An equivalence shown by simulation is
+/// [Proof::Sampled], which is probabilistic.
+pub fn verify_equivalence(
+	a: &[PauliExp<PauliAngle>],
+	b: &[PauliExp<PauliAngle>],
+) -> Result<Proof, VerificationError> {
+	if Canonical::new(a) == Canonical::new(b) {
+		return Ok(Proof::Canonical);
+	}
+
+	let qubits = a
+		.iter()
+		.chain(b.iter())
+		.map(|exp| exp.string.size())
+		.max()
+		.unwrap_or_default()
+		.max(1);
+	if qubits > MAX_SIMULATED_QUBITS {
+		return Err(VerificationError::TooLarge { qubits });
+	}
+
+	let mut rng = ChaCha8Rng::seed_from_u64(0);
+	let mut parameters = free_parameters(a);
+	parameters.append(&mut free_parameters(b));
+
+	let mut overlap = f64::INFINITY;
+	for _ in 0..SAMPLES {
+		let table: ParameterTable = parameters
+			.iter()
+			.map(|name| (name.clone(), rng.random::<f64>()))
+			.collect();
+
+		let mut state_a = random_product_state(qubits, &mut rng);
+		let mut state_b = state_a.clone();
+		state_a.apply_exps(&bind(a, &table));
+		state_b.apply_exps(&bind(b, &table));
+
+		overlap = overlap.min(state_a.inner_product(&state_b).abs_squared().sqrt());
+	}
+
+	if overlap > 1.0 - TOLERANCE {
+		Ok(Proof::Sampled)
+	} else {
+		Err(VerificationError::NotEquivalent { overlap })
+	}
+}
+
+/// The non-Clifford exponentials in layers of commuting ones, followed by
+/// the Clifford part.
+#[derive(Debug)]
+struct Canonical {
+	layers: Vec<Vec<PauliExp<PauliAngle>>>,
+	tableau: CliffordTableau,
+}
+
+impl Canonical {
+	fn new(exponentials: &[PauliExp<PauliAngle>]) -> Self {
+		// The Cliffords in time order, they are all applied after `rotations`
+		let mut cliffords: Vec<PauliExp<CliffordPauliAngle>> = Vec::new();
+		let mut rotations: Vec<PauliExp<PauliAngle>> = Vec::new();
+		for exp in exponentials.iter() {
+			match exp.angle {
+				PauliAngle::Clifford(angle) => cliffords.push(PauliExp {
+					string: exp.string.clone(),
+					angle,
+				}),
+				_ => {
+					let mut exp = exp.clone();
+					for clifford in cliffords.iter().rev() {
+						exp.move_clifford_past(clifford);
+					}
+					rotations.push(exp);
+				}
+			}
+		}
+
+		// Merging can create new Cliffords, which are moved to just after
+		// the rotations
+		loop {
+			rotations = merge_exponentials(rotations);
+			let Some(i) = rotations.iter().position(|exp| exp.angle.is_clifford()) else {
+				break;
+			};
+			let PauliExp {
+				string,
+				angle: PauliAngle::Clifford(angle),
+			} = rotations.remove(i)
+			else {
+				unreachable!()
+			};
+			let clifford = PauliExp { string, angle };
+			for exp in rotations[i..].iter_mut() {
+				exp.move_clifford_past(&clifford);
+			}
+			cliffords.insert(0, clifford);
+		}
+
+		let mut tableau = CliffordTableau::id();
+		for clifford in cliffords.into_iter() {
+			tableau.merge_clifford(clifford);
+		}
+
+		// Every exponential goes to the layer after the last one it
+		// anticommutes with
+		let mut layers: Vec<Vec<PauliExp<PauliAngle>>> = Vec::new();
+		for exp in rotations.into_iter() {
+			let layer = layers
+				.iter()
+				.rposition(|layer| {
+					layer
+						.iter()
+						.any(|other| other.string.anticommutes_with(&exp.string))
+				})
+				.map(|i| i + 1)
+				.unwrap_or_default();
+			if layer == layers.len() {
+				layers.push(Vec::new());
+			}
+			layers[layer].push(exp);
+		}
+		for layer in layers.iter_mut() {
+			layer.sort_by_cached_key(|exp| string_key(&exp.string));
+		}
+
+		Self { layers, tableau }
+	}
+}
+
+impl PartialEq for Canonical {
+	fn eq(&self, other: &Self) -> bool {
+		self.tableau == other.tableau
+			&& self.layers.len() == other.layers.len()
+			&& self.layers.iter().zip(other.layers.iter()).all(|(a, b)| {
+				a.len() == b.len()
+					&& a.iter()
+						.zip(b.iter())
+						.all(|(a, b)| a.string == b.string && angles_are_equal(&a.angle, &b.angle))
+			})
+	}
+}
+
+fn string_key(string: &PauliString) -> Vec<(usize, u8)> {
+	string
+		.letters()
+		.map(|(i, letter)| (i, letter as u8))
+		.collect()
+}
+
+/// Equal up to a multiple of pi, which is a global phase.
+fn angles_are_equal(a: &PauliAngle, b: &PauliAngle) -> bool {
+	let mut difference = a.to_expression();
+	let mut b = b.to_expression();
+	b.scale(-1.0);
+	difference.add_expression(&b);
+
+	let constant = difference.constant_part();
+	difference
+		.terms()
+		.iter()
+		.all(|(coefficient, _)| coefficient.abs() < TOLERANCE)
+		&& (constant - constant.round()).abs() < TOLERANCE
+}
+
+fn random_product_state(qubits: usize, rng: &mut ChaCha8Rng) -> ParallelStatevector {
+	let mut state = ParallelStatevector::new(qubits);
+	for i in 0..qubits {
+		state.apply_exp(&PauliExp {
+			string: PauliString::y(i),
+			angle: PauliAngle::MultipleOfPi(rng.random()),
+		});
+		state.apply_exp(&PauliExp {
+			string: PauliString::z(i),
+			angle: PauliAngle::MultipleOfPi(rng.random::<f64>() * 2.0),
+		});
+	}
+
+	state
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		connectivity::Connectivity,
+		experiment::random_exp,
+		misc::NonZeroEvenUsize,
+		pauli_string,
+		synthesize::{Greedy, synthesize, synthesize_preserving_order},
+	};
+
+	fn input(n_qubits: usize, seed: u64) -> Vec<PauliExp<PauliAngle>> {
+		let mut rng = ChaCha8Rng::seed_from_u64(seed);
+		(0..20).map(|_| random_exp(n_qubits, &mut rng)).collect()
+	}
+
+	#[test]
+	fn synthesis_is_verified() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let connectivity = Connectivity::create_line(gate_size, 8);
+		for seed in 0..5 {
+			for connectivity in [None, Some(&connectivity)] {
+				let input = input(8, seed);
+				let result = synthesize_preserving_order(
+					input.clone(),
+					gate_size,
+					connectivity,
+					&mut Greedy,
+				);
+				assert_eq!(
+					verify_synthesis(&input, &result.circuit, &result.clifford),
+					Ok(Proof::Canonical)
+				);
+
+				let result = synthesize(input, gate_size, connectivity);
+				assert_eq!(
+					verify_synthesis(&result.ordered(), &result.circuit, &result.clifford),
+					Ok(Proof::Canonical)
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn differences_are_found() {
+		let gate_size = NonZeroEvenUsize::new(4).unwrap();
+		let input = input(8, 1);
+		let result = synthesize_preserving_order(input.clone(), gate_size, None, &mut Greedy);
+
+		let mut circuit = result.circuit.clone();
+		let i = circuit
+			.iter()
+			.position(|exp| !exp.angle.is_clifford())
+			.unwrap();
+		circuit[i].angle = PauliAngle::MultipleOfPi(0.123);
+		assert!(matches!(
+			verify_synthesis(&input, &circuit, &result.clifford),
+			Err(VerificationError::NotEquivalent { .. })
+		));
+
+		let mut clifford = result.clifford.clone();
+		clifford.pop();
+		assert!(matches!(
+			verify_synthesis(&input, &result.circuit, &clifford),
+			Err(VerificationError::NotEquivalent { .. })
+		));
+
+		let big = vec![PauliExp {
+			string: PauliString::x(30),
+			angle: PauliAngle::MultipleOfPi(0.1),
+		}];
+		assert_eq!(
+			verify_equivalence(&big, &[]),
+			Err(VerificationError::TooLarge { qubits: 31 })
+		);
+	}
+
+	#[test]
+	fn statevector_fallback() {
+		let exp = |string, angle| PauliExp {
+			string,
+			angle: PauliAngle::MultipleOfPi(angle),
+		};
+		let parameter = PauliExp {
+			string: pauli_string!("IY"),
+			angle: PauliAngle::Parameter {
+				neg: false,
+				name: String::from("a"),
+			},
+		};
+
+		// The same rotation with XZX and ZXZ Euler angles
+		let a = vec![
+			exp(pauli_string!("X"), 0.1),
+			exp(pauli_string!("Z"), 0.2),
+			exp(pauli_string!("X"), 0.3),
+			parameter.clone(),
+		];
+		let b = vec![
+			exp(pauli_string!("Z"), 0.24013642448798328),
+			exp(pauli_string!("X"), 0.3194668333903979),
+			exp(pauli_string!("Z"), 0.10579523413589481),
+			parameter,
+		];
+		assert_eq!(verify_equivalence(&a, &b), Ok(Proof::Sampled));
+
+		let mut b = b;
+		b[1].angle = PauliAngle::MultipleOfPi(0.32);
+		assert!(matches!(
+			verify_equivalence(&a, &b),
+			Err(VerificationError::NotEquivalent { .. })
+		));
+
+		// $e^{i\frac{\pi}{8}X}$ is $e^{i\frac{\pi}{8}Z}$ conjugated by a Clifford
+		let clifford = |angle| PauliExp {
+			string: pauli_string!("Y"),
+			angle: PauliAngle::Clifford(angle),
+		};
+		let a = vec![exp(pauli_string!("X"), 0.125)];
+		let b = vec![
+			clifford(CliffordPauliAngle::PiOver4),
+			exp(pauli_string!("Z"), 0.125),
+			clifford(CliffordPauliAngle::NegPiOver4),
+		];
+		assert_eq!(verify_equivalence(&a, &b), Ok(Proof::Canonical));
+	}
+}